@@ -1,23 +1,37 @@
 use crate::{
+    helpers::validate_bps,
     msg::FeeRecipient,
-    state::{remove_swap_route, store_swap_route, CONFIG},
-    types::{Config, SwapRoute},
+    queries::get_route_denoms,
+    state::{
+        read_swap_route, remove_swap_route, store_swap_route, CONFIG, CW20_OUTPUT_ADAPTERS, FEE_RECIPIENTS_BY_DENOM, LARGE_SWAP_THRESHOLDS, RESERVES,
+    },
+    types::{Config, DustPolicy, OverDeliveryPolicy, SwapRoute},
     ContractError,
     ContractError::CustomError,
 };
-use cosmwasm_std::{ensure, ensure_eq, Addr, Attribute, BankMsg, Coin, Deps, DepsMut, Env, Event, Response, StdResult};
+use cosmwasm_std::{ensure, ensure_eq, Addr, Attribute, BankMsg, Coin, Deps, DepsMut, Env, Event, Response, Uint128};
 use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQuerier, InjectiveQueryWrapper, MarketId};
 use std::collections::HashSet;
 
-pub fn save_config(deps: DepsMut<InjectiveQueryWrapper>, env: Env, admin: Addr, fee_recipient: FeeRecipient) -> StdResult<()> {
+pub fn save_config(deps: DepsMut<InjectiveQueryWrapper>, env: Env, admin: Addr, fee_recipient: FeeRecipient) -> Result<(), ContractError> {
+    let admin = validate_address(deps.as_ref(), "admin", &admin)?;
     let fee_recipient = match fee_recipient {
-        FeeRecipient::Address(addr) => addr,
+        FeeRecipient::Address(addr) => validate_address(deps.as_ref(), "fee_recipient", &addr)?,
         FeeRecipient::SwapContract => env.contract.address,
     };
-    let config = Config { fee_recipient, admin };
+    let config = Config::new(fee_recipient, admin);
     config.to_owned().validate()?;
 
-    CONFIG.save(deps.storage, &config)
+    CONFIG.save(deps.storage, &config)?;
+    Ok(())
+}
+
+pub(crate) fn validate_address(deps: Deps<InjectiveQueryWrapper>, field: &str, address: &Addr) -> Result<Addr, ContractError> {
+    deps.api.addr_validate(address.as_str()).map_err(|err| ContractError::InvalidAddress {
+        field: field.to_string(),
+        address: address.to_string(),
+        reason: err.to_string(),
+    })
 }
 
 pub fn verify_sender_is_admin(deps: Deps<InjectiveQueryWrapper>, sender: &Addr) -> Result<(), ContractError> {
@@ -26,27 +40,159 @@ pub fn verify_sender_is_admin(deps: Deps<InjectiveQueryWrapper>, sender: &Addr)
     Ok(())
 }
 
+/// Config fields to change in `update_config`, fields left as `None` keep their current value. Optional config
+/// fields are cleared with `Some(None)`.
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub admin: Option<Addr>,
+    pub fee_recipient: Option<FeeRecipient>,
+    pub paused: Option<bool>,
+    pub swap_fee_bps: Option<u16>,
+    pub allow_direct_fallback: Option<bool>,
+    pub strict_funds: Option<bool>,
+    pub fee_in_output_denom: Option<bool>,
+    pub refund_dust_threshold: Option<Uint128>,
+    pub dust_policy: Option<DustPolicy>,
+    pub min_fee: Option<Uint128>,
+    pub max_fee: Option<Option<Uint128>>,
+    pub over_delivery_policy: Option<OverDeliveryPolicy>,
+    pub admin_fee_exempt: Option<bool>,
+    pub hop_retry_price_band_bps: Option<u16>,
+    pub fee_burn_bps: Option<u16>,
+    pub burn_address: Option<Option<Addr>>,
+    pub allow_custom_routes: Option<bool>,
+    pub large_swap_delay_blocks: Option<u64>,
+    pub reject_unaligned_exact_output: Option<bool>,
+    pub exact_output_tolerance_bps: Option<u16>,
+    pub min_refund_amount: Option<Uint128>,
+    pub positive_slippage_bps: Option<u16>,
+    pub high_impact_threshold_bps: Option<u16>,
+    pub reject_sub_tick_amounts: Option<bool>,
+    pub output_timelock_seconds: Option<u64>,
+}
+
 pub fn update_config(
     deps: DepsMut<InjectiveQueryWrapper>,
     env: Env,
     sender: Addr,
-    admin: Option<Addr>,
-    fee_recipient: Option<FeeRecipient>,
+    update: ConfigUpdate,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     verify_sender_is_admin(deps.as_ref(), &sender)?;
     let mut config = CONFIG.load(deps.storage)?;
     let mut updated_config_event_attrs: Vec<Attribute> = Vec::new();
-    if let Some(admin) = admin {
+    if let Some(admin) = update.admin {
         config.admin = admin.clone();
         updated_config_event_attrs.push(Attribute::new("admin", admin.to_string()));
     }
-    if let Some(fee_recipient) = fee_recipient {
+    if let Some(fee_recipient) = update.fee_recipient {
         config.fee_recipient = match fee_recipient {
             FeeRecipient::Address(addr) => addr,
             FeeRecipient::SwapContract => env.contract.address,
         };
         updated_config_event_attrs.push(Attribute::new("fee_recipient", config.fee_recipient.to_string()));
     }
+    if let Some(paused) = update.paused {
+        config.paused = paused;
+        updated_config_event_attrs.push(Attribute::new("paused", paused.to_string()));
+    }
+    if let Some(swap_fee_bps) = update.swap_fee_bps {
+        config.swap_fee_bps = swap_fee_bps;
+        updated_config_event_attrs.push(Attribute::new("swap_fee_bps", swap_fee_bps.to_string()));
+    }
+    if let Some(allow_direct_fallback) = update.allow_direct_fallback {
+        config.allow_direct_fallback = allow_direct_fallback;
+        updated_config_event_attrs.push(Attribute::new("allow_direct_fallback", allow_direct_fallback.to_string()));
+    }
+    if let Some(strict_funds) = update.strict_funds {
+        config.strict_funds = strict_funds;
+        updated_config_event_attrs.push(Attribute::new("strict_funds", strict_funds.to_string()));
+    }
+    if let Some(fee_in_output_denom) = update.fee_in_output_denom {
+        config.fee_in_output_denom = fee_in_output_denom;
+        updated_config_event_attrs.push(Attribute::new("fee_in_output_denom", fee_in_output_denom.to_string()));
+    }
+    if let Some(refund_dust_threshold) = update.refund_dust_threshold {
+        config.refund_dust_threshold = refund_dust_threshold;
+        updated_config_event_attrs.push(Attribute::new("refund_dust_threshold", refund_dust_threshold.to_string()));
+    }
+    if let Some(dust_policy) = update.dust_policy {
+        updated_config_event_attrs.push(Attribute::new("dust_policy", format!("{dust_policy:?}")));
+        config.dust_policy = dust_policy;
+    }
+    if let Some(min_fee) = update.min_fee {
+        config.min_fee = min_fee;
+        updated_config_event_attrs.push(Attribute::new("min_fee", min_fee.to_string()));
+    }
+    if let Some(max_fee) = update.max_fee {
+        config.max_fee = max_fee;
+        updated_config_event_attrs.push(Attribute::new(
+            "max_fee",
+            max_fee.map_or("none".to_string(), |max_fee| max_fee.to_string()),
+        ));
+    }
+    if let Some(over_delivery_policy) = update.over_delivery_policy {
+        updated_config_event_attrs.push(Attribute::new("over_delivery_policy", format!("{over_delivery_policy:?}")));
+        config.over_delivery_policy = over_delivery_policy;
+    }
+    if let Some(admin_fee_exempt) = update.admin_fee_exempt {
+        config.admin_fee_exempt = admin_fee_exempt;
+        updated_config_event_attrs.push(Attribute::new("admin_fee_exempt", admin_fee_exempt.to_string()));
+    }
+    if let Some(hop_retry_price_band_bps) = update.hop_retry_price_band_bps {
+        config.hop_retry_price_band_bps = hop_retry_price_band_bps;
+        updated_config_event_attrs.push(Attribute::new("hop_retry_price_band_bps", hop_retry_price_band_bps.to_string()));
+    }
+    if let Some(fee_burn_bps) = update.fee_burn_bps {
+        config.fee_burn_bps = fee_burn_bps;
+        updated_config_event_attrs.push(Attribute::new("fee_burn_bps", fee_burn_bps.to_string()));
+    }
+    if let Some(burn_address) = update.burn_address {
+        config.burn_address = burn_address
+            .as_ref()
+            .map(|burn_address| validate_address(deps.as_ref(), "burn_address", burn_address))
+            .transpose()?;
+        updated_config_event_attrs.push(Attribute::new(
+            "burn_address",
+            burn_address.map_or("none".to_string(), |burn_address| burn_address.to_string()),
+        ));
+    }
+    if let Some(allow_custom_routes) = update.allow_custom_routes {
+        config.allow_custom_routes = allow_custom_routes;
+        updated_config_event_attrs.push(Attribute::new("allow_custom_routes", allow_custom_routes.to_string()));
+    }
+    if let Some(large_swap_delay_blocks) = update.large_swap_delay_blocks {
+        config.large_swap_delay_blocks = large_swap_delay_blocks;
+        updated_config_event_attrs.push(Attribute::new("large_swap_delay_blocks", large_swap_delay_blocks.to_string()));
+    }
+    if let Some(reject_unaligned_exact_output) = update.reject_unaligned_exact_output {
+        config.reject_unaligned_exact_output = reject_unaligned_exact_output;
+        updated_config_event_attrs.push(Attribute::new("reject_unaligned_exact_output", reject_unaligned_exact_output.to_string()));
+    }
+    if let Some(exact_output_tolerance_bps) = update.exact_output_tolerance_bps {
+        config.exact_output_tolerance_bps = exact_output_tolerance_bps;
+        updated_config_event_attrs.push(Attribute::new("exact_output_tolerance_bps", exact_output_tolerance_bps.to_string()));
+    }
+    if let Some(min_refund_amount) = update.min_refund_amount {
+        config.min_refund_amount = min_refund_amount;
+        updated_config_event_attrs.push(Attribute::new("min_refund_amount", min_refund_amount.to_string()));
+    }
+    if let Some(positive_slippage_bps) = update.positive_slippage_bps {
+        config.positive_slippage_bps = positive_slippage_bps;
+        updated_config_event_attrs.push(Attribute::new("positive_slippage_bps", positive_slippage_bps.to_string()));
+    }
+    if let Some(high_impact_threshold_bps) = update.high_impact_threshold_bps {
+        config.high_impact_threshold_bps = high_impact_threshold_bps;
+        updated_config_event_attrs.push(Attribute::new("high_impact_threshold_bps", high_impact_threshold_bps.to_string()));
+    }
+    if let Some(reject_sub_tick_amounts) = update.reject_sub_tick_amounts {
+        config.reject_sub_tick_amounts = reject_sub_tick_amounts;
+        updated_config_event_attrs.push(Attribute::new("reject_sub_tick_amounts", reject_sub_tick_amounts.to_string()));
+    }
+    if let Some(output_timelock_seconds) = update.output_timelock_seconds {
+        config.output_timelock_seconds = output_timelock_seconds;
+        updated_config_event_attrs.push(Attribute::new("output_timelock_seconds", output_timelock_seconds.to_string()));
+    }
+    config.to_owned().validate()?;
     CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::new()
@@ -54,6 +200,111 @@ pub fn update_config(
         .add_event(Event::new("config_updated").add_attributes(updated_config_event_attrs)))
 }
 
+pub fn set_max_slippage(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    max_slippage_bps: Option<u16>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let mut config = CONFIG.load(deps.storage)?;
+    config.max_allowed_slippage_bps = max_slippage_bps;
+    config.to_owned().validate()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_max_slippage")
+        .add_attribute("max_slippage_bps", max_slippage_bps.map_or("none".to_string(), |bps| bps.to_string())))
+}
+
+pub fn set_cw20_output(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    denom: String,
+    adapter: Option<Addr>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let response = Response::new().add_attribute("method", "set_cw20_output").add_attribute("denom", &denom);
+
+    match adapter {
+        Some(adapter) => {
+            let adapter = validate_address(deps.as_ref(), "adapter", &adapter)?;
+            CW20_OUTPUT_ADAPTERS.save(deps.storage, denom, &adapter)?;
+            Ok(response.add_attribute("adapter", adapter))
+        }
+        None => {
+            CW20_OUTPUT_ADAPTERS.remove(deps.storage, denom);
+            Ok(response.add_attribute("adapter", "none"))
+        }
+    }
+}
+
+pub fn set_fee_recipient_for_denom(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    denom: String,
+    recipient: Option<Addr>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let response = Response::new()
+        .add_attribute("method", "set_fee_recipient_for_denom")
+        .add_attribute("denom", &denom);
+
+    match recipient {
+        Some(recipient) => {
+            let recipient = validate_address(deps.as_ref(), "fee_recipient", &recipient)?;
+            FEE_RECIPIENTS_BY_DENOM.save(deps.storage, denom, &recipient)?;
+            Ok(response.add_attribute("fee_recipient", recipient))
+        }
+        None => {
+            FEE_RECIPIENTS_BY_DENOM.remove(deps.storage, denom);
+            Ok(response.add_attribute("fee_recipient", "none"))
+        }
+    }
+}
+
+pub fn set_large_swap_threshold(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    denom: String,
+    threshold: Option<Uint128>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let response = Response::new()
+        .add_attribute("method", "set_large_swap_threshold")
+        .add_attribute("denom", &denom);
+
+    match threshold {
+        Some(threshold) => {
+            LARGE_SWAP_THRESHOLDS.save(deps.storage, denom, &threshold)?;
+            Ok(response.add_attribute("threshold", threshold))
+        }
+        None => {
+            LARGE_SWAP_THRESHOLDS.remove(deps.storage, denom);
+            Ok(response.add_attribute("threshold", "none"))
+        }
+    }
+}
+
+pub fn set_reserve(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    denom: String,
+    amount: Uint128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let response = Response::new()
+        .add_attribute("method", "set_reserve")
+        .add_attribute("denom", &denom)
+        .add_attribute("amount", amount);
+
+    if amount.is_zero() {
+        RESERVES.remove(deps.storage, denom);
+    } else {
+        RESERVES.save(deps.storage, denom, &amount)?;
+    }
+    Ok(response)
+}
+
 pub fn withdraw_support_funds(
     deps: DepsMut<InjectiveQueryWrapper>,
     sender: Addr,
@@ -81,33 +332,128 @@ pub fn set_route(
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     verify_sender_is_admin(deps.as_ref(), sender)?;
 
-    if source_denom == target_denom {
+    let mut route = SwapRoute {
+        steps: route,
+        source_denom,
+        target_denom,
+        enabled: true,
+        fee_bps: None,
+    };
+    let warnings = store_routes(deps, std::slice::from_mut(&mut route))?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_route")
+        .add_attributes(warnings)
+        .add_event(route_event("route_set", &route)))
+}
+
+pub fn set_routes(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    mut routes: Vec<SwapRoute>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+
+    let warnings = store_routes(deps, &mut routes)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_routes")
+        .add_attribute("routes_count", routes.len().to_string())
+        .add_attributes(warnings)
+        .add_events(routes.iter().map(|route| route_event("route_set", route))))
+}
+
+/// Validates and stores a batch of routes, returning a warning for each route a round trip was dropped from.
+pub fn store_routes(deps: DepsMut<InjectiveQueryWrapper>, routes: &mut [SwapRoute]) -> Result<Vec<Attribute>, ContractError> {
+    // all routes are validated before any is stored, so an invalid route rejects the whole batch
+    let mut warnings = vec![];
+    for route in routes.iter_mut() {
+        validate_route(deps.as_ref(), route)?;
+        let dropped_steps = collapse_round_trips(deps.as_ref(), route)?;
+        // collapsing may leave a route that no longer leads to its target
+        validate_route(deps.as_ref(), route)?;
+        if dropped_steps > 0 {
+            warnings.push(Attribute::new("warning", round_trip_warning(route, dropped_steps)));
+        }
+    }
+    for route in routes.iter() {
+        store_swap_route(deps.storage, route)?;
+    }
+    Ok(warnings)
+}
+
+/// Drops the steps of a route leading back to a denom it passed through before, such as selling into a quote denom
+/// and buying out of it again, and returns how many were dropped. Fails if nothing is left of the route, or if what is
+/// left does not end at its target.
+fn collapse_round_trips(deps: Deps<InjectiveQueryWrapper>, route: &mut SwapRoute) -> Result<usize, ContractError> {
+    let denoms = get_route_denoms(deps, route)?;
+    let mut steps: Vec<MarketId> = vec![];
+    let mut passed_denoms = vec![&denoms[0]];
+    for (market_id, denom) in route.steps.iter().zip(denoms.iter().skip(1)) {
+        match passed_denoms.iter().position(|passed_denom| *passed_denom == denom) {
+            Some(idx) => {
+                passed_denoms.truncate(idx + 1);
+                steps.truncate(idx);
+            }
+            None => {
+                passed_denoms.push(denom);
+                steps.push(market_id.to_owned());
+            }
+        }
+    }
+
+    ensure!(
+        !steps.is_empty(),
+        ContractError::EmptyRoute {
+            source_denom: route.source_denom.to_owned(),
+            target_denom: route.target_denom.to_owned(),
+        }
+    );
+    ensure!(
+        passed_denoms.last() == Some(&&route.target_denom),
+        ContractError::RouteDenomMismatch {
+            denom: route.target_denom.to_owned(),
+            market_id: steps.last().unwrap().to_owned(),
+        }
+    );
+
+    let dropped_steps = route.steps.len() - steps.len();
+    route.steps = steps;
+    Ok(dropped_steps)
+}
+
+fn round_trip_warning(route: &SwapRoute, dropped_steps: usize) -> String {
+    format!(
+        "dropped {dropped_steps} steps of the route from {} to {} going back to a denom it passed through before",
+        route.source_denom, route.target_denom
+    )
+}
+
+pub(crate) fn validate_route(deps: Deps<InjectiveQueryWrapper>, route: &SwapRoute) -> Result<(), ContractError> {
+    if route.source_denom == route.target_denom {
         return Err(ContractError::CustomError {
             val: "Cannot set a route with the same denom being source and target".to_string(),
         });
     }
 
-    if route.is_empty() {
+    if route.steps.is_empty() {
         return Err(ContractError::CustomError {
             val: "Route must have at least one step".to_string(),
         });
     }
 
-    if route.clone().into_iter().collect::<HashSet<MarketId>>().len() < route.len() {
-        return Err(ContractError::CustomError {
-            val: "Route cannot have duplicate steps!".to_string(),
-        });
+    if let Some(fee_bps) = route.fee_bps {
+        validate_bps("fee_bps", fee_bps)?;
     }
 
-    let route = SwapRoute {
-        steps: route,
-        source_denom,
-        target_denom,
-    };
-    verify_route_exists(deps.as_ref(), &route)?;
-    store_swap_route(deps.storage, &route)?;
+    let mut seen_markets = HashSet::new();
+    if let Some(market_id) = route.steps.iter().find(|market_id| !seen_markets.insert(*market_id)) {
+        return Err(ContractError::DuplicateMarketInRoute {
+            market_id: market_id.to_owned(),
+        });
+    }
 
-    Ok(Response::new().add_attribute("method", "set_route"))
+    verify_route_exists(deps, route)
 }
 
 fn verify_route_exists(deps: Deps<InjectiveQueryWrapper>, route: &SwapRoute) -> Result<(), ContractError> {
@@ -119,9 +465,12 @@ fn verify_route_exists(deps: Deps<InjectiveQueryWrapper>, route: &SwapRoute) ->
     let querier = InjectiveQuerier::new(&deps.querier);
 
     for market_id in route.steps.iter() {
-        let market = querier.query_spot_market(market_id)?.market.ok_or(CustomError {
-            val: format!("Market {} not found", market_id.as_str()).to_string(),
-        })?;
+        let market = querier
+            .query_spot_market(market_id)?
+            .market
+            .ok_or_else(|| ContractError::MarketNotFound {
+                market_id: market_id.clone(),
+            })?;
 
         denoms.push(MarketDenom {
             quote_denom: market.quote_denom,
@@ -136,22 +485,65 @@ fn verify_route_exists(deps: Deps<InjectiveQueryWrapper>, route: &SwapRoute) ->
             val: "No market denoms found".to_string()
         }
     );
+    // the source has to be spendable on the first market and the target receivable on the last one
     ensure!(
         denoms.first().unwrap().quote_denom == route.source_denom || denoms.first().unwrap().base_denom == route.source_denom,
-        CustomError {
-            val: "Source denom not found in first market".to_string()
+        ContractError::RouteDenomMismatch {
+            denom: route.source_denom.to_owned(),
+            market_id: route.steps.first().unwrap().to_owned(),
         }
     );
     ensure!(
         denoms.last().unwrap().quote_denom == route.target_denom || denoms.last().unwrap().base_denom == route.target_denom,
-        CustomError {
-            val: "Target denom not found in last market".to_string()
+        ContractError::RouteDenomMismatch {
+            denom: route.target_denom.to_owned(),
+            market_id: route.steps.last().unwrap().to_owned(),
         }
     );
 
     Ok(())
 }
 
+pub fn set_route_enabled(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    source_denom: String,
+    target_denom: String,
+    enabled: bool,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    let mut route = read_swap_route(deps.storage, &source_denom, &target_denom)?;
+    route.enabled = enabled;
+    store_swap_route(deps.storage, &route)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_route_enabled")
+        .add_attribute("enabled", enabled.to_string())
+        .add_event(route_event("route_set", &route)))
+}
+
+/// Sets the fee charged on swaps along a route instead of the global `swap_fee_bps`, `None` clears it.
+pub fn set_route_fee(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    source_denom: String,
+    target_denom: String,
+    fee_bps: Option<u16>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+    if let Some(fee_bps) = fee_bps {
+        validate_bps("fee_bps", fee_bps)?;
+    }
+    let mut route = read_swap_route(deps.storage, &source_denom, &target_denom)?;
+    route.fee_bps = fee_bps;
+    store_swap_route(deps.storage, &route)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_route_fee")
+        .add_attribute("fee_bps", fee_bps.map_or("none".to_string(), |bps| bps.to_string()))
+        .add_event(route_event("route_set", &route)))
+}
+
 pub fn delete_route(
     deps: DepsMut<InjectiveQueryWrapper>,
     sender: &Addr,
@@ -159,7 +551,21 @@ pub fn delete_route(
     target_denom: String,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     verify_sender_is_admin(deps.as_ref(), sender)?;
+    let deleted_route = read_swap_route(deps.storage, &source_denom, &target_denom).ok();
     remove_swap_route(deps.storage, &source_denom, &target_denom);
 
-    Ok(Response::new().add_attribute("method", "delete_route"))
+    let mut response = Response::new().add_attribute("method", "delete_route");
+    if let Some(route) = deleted_route {
+        response = response.add_event(route_event("route_deleted", &route));
+    }
+    Ok(response)
+}
+
+/// Event for indexers tracking the route configuration, emitted as `wasm-<name>`.
+fn route_event(name: &str, route: &SwapRoute) -> Event {
+    Event::new(name)
+        .add_attribute("source_denom", route.source_denom.to_owned())
+        .add_attribute("target_denom", route.target_denom.to_owned())
+        .add_attribute("steps_count", route.steps.len().to_string())
+        .add_attribute("enabled", route.enabled.to_string())
 }
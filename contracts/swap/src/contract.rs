@@ -1,14 +1,27 @@
 use crate::{
-    admin::{delete_route, save_config, set_route, update_config, withdraw_support_funds},
+    admin::{
+        delete_route, save_config, set_cw20_output, set_fee_recipient_for_denom, set_large_swap_threshold, set_max_slippage, set_reserve, set_route,
+        set_route_enabled, set_route_fee, set_routes, store_routes, update_config, withdraw_support_funds, ConfigUpdate,
+    },
     error::ContractError,
     msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg},
-    queries::{estimate_swap_result, SwapQuantity},
-    state::{get_all_swap_routes, get_config, read_swap_route},
-    swap::{handle_atomic_order_reply, start_swap_flow},
-    types::{ConfigResponse, SwapQuantityMode},
+    queries::{
+        can_swap, estimate_break_even_output, estimate_gross_input, estimate_output_for_fee_budget, estimate_swap_gas, estimate_swap_result,
+        get_broken_routes, get_contract_balances, get_implied_rate, get_min_effective_input, get_swap_bounds, is_swappable, preview_swap_plan,
+        quote_swap, swap_route_response, SwapQuantity,
+    },
+    state::{
+        get_accounting, get_all_swap_routes, get_config, get_debug_state, get_limits, get_market_fees, get_reachable_targets, get_routes_to_target,
+        get_source_denoms, get_stale_swaps, get_swap_routes_by_market, get_swap_volume, get_user_swap_count, read_swap_route, STEP_STATE,
+    },
+    swap::{
+        cancel_large_swap, cancel_swap, claim_output, commit_large_swap, execute_large_swap, handle_atomic_order_reply, refund_all_in_flight,
+        start_swap_flow, swap_to_portfolio, SwapOptions,
+    },
+    types::{ConfigResponse, SwapQuantityMode, SwapRouteResponse},
 };
 
-use cosmwasm_std::{entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError};
+use cosmwasm_std::{ensure, entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError};
 use cw2::{get_contract_version, set_contract_version};
 use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper};
 
@@ -20,15 +33,20 @@ pub const DEPOSIT_REPLY_ID: u64 = 2u64;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut<InjectiveQueryWrapper>,
+    mut deps: DepsMut<InjectiveQueryWrapper>,
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    save_config(deps, env, msg.admin, msg.fee_recipient)?;
+    save_config(deps.branch(), env, msg.admin, msg.fee_recipient)?;
+    let mut initial_routes = msg.initial_routes;
+    let warnings = store_routes(deps, &mut initial_routes)?;
 
-    Ok(Response::new().add_attribute("method", "instantiate").add_attribute("owner", info.sender))
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("owner", info.sender)
+        .add_attributes(warnings))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -42,25 +60,171 @@ pub fn execute(
         ExecuteMsg::SwapMinOutput {
             target_denom,
             min_output_quantity,
-        } => start_swap_flow(deps, env, info, target_denom, SwapQuantityMode::MinOutputQuantity(min_output_quantity)),
+            auto_convert_to,
+            memo,
+            input_fraction_bps,
+            min_output_value,
+            slices,
+            acknowledge_high_impact,
+        } => start_swap_flow(
+            deps,
+            env,
+            info,
+            target_denom,
+            SwapQuantityMode::MinOutputQuantity(min_output_quantity),
+            SwapOptions {
+                auto_convert_to,
+                memo,
+                input_fraction_bps,
+                min_output_value,
+                slices,
+                acknowledge_high_impact,
+                ..SwapOptions::default()
+            },
+        ),
         ExecuteMsg::SwapExactOutput {
             target_denom,
             target_output_quantity,
+            max_input,
+            compound_refund,
+            memo,
+            acknowledge_high_impact,
         } => start_swap_flow(
             deps,
             env,
             info,
             target_denom,
             SwapQuantityMode::ExactOutputQuantity(target_output_quantity),
+            SwapOptions {
+                max_input,
+                compound_refund,
+                memo,
+                acknowledge_high_impact,
+                ..SwapOptions::default()
+            },
+        ),
+        ExecuteMsg::SwapAndDistribute {
+            target_denom,
+            min_output_quantity,
+            recipients,
+        } => start_swap_flow(
+            deps,
+            env,
+            info,
+            target_denom,
+            SwapQuantityMode::MinOutputQuantity(min_output_quantity),
+            SwapOptions {
+                recipients,
+                ..SwapOptions::default()
+            },
         ),
+        ExecuteMsg::SwapMinOutputWithRoute {
+            target_denom,
+            min_output_quantity,
+            route,
+        } => start_swap_flow(
+            deps,
+            env,
+            info,
+            target_denom,
+            SwapQuantityMode::MinOutputQuantity(min_output_quantity),
+            SwapOptions {
+                custom_route: Some(route),
+                ..SwapOptions::default()
+            },
+        ),
+        ExecuteMsg::SwapToPortfolio { source_denom, targets } => swap_to_portfolio(deps, env, info, source_denom, targets),
+        ExecuteMsg::CancelSwap {} => cancel_swap(deps, env, info.sender),
+        ExecuteMsg::CommitLargeSwap {
+            target_denom,
+            swap_quantity_mode,
+            acknowledge_high_impact,
+        } => commit_large_swap(deps, env, info, target_denom, swap_quantity_mode, acknowledge_high_impact),
+        ExecuteMsg::ExecuteLargeSwap {} => execute_large_swap(deps, env, info.sender),
+        ExecuteMsg::CancelLargeSwap {} => cancel_large_swap(deps, info.sender),
+        ExecuteMsg::ClaimOutput {} => claim_output(deps, env, info.sender),
+        ExecuteMsg::RefundAllInFlight {} => refund_all_in_flight(deps, env, &info.sender),
         // Admin functions:
         ExecuteMsg::SetRoute {
             source_denom,
             target_denom,
             route,
         } => set_route(deps, &info.sender, source_denom, target_denom, route),
+        ExecuteMsg::SetRoutes { routes } => set_routes(deps, &info.sender, routes),
+        ExecuteMsg::SetRouteEnabled {
+            source_denom,
+            target_denom,
+            enabled,
+        } => set_route_enabled(deps, &info.sender, source_denom, target_denom, enabled),
+        ExecuteMsg::SetRouteFee {
+            source_denom,
+            target_denom,
+            fee_bps,
+        } => set_route_fee(deps, &info.sender, source_denom, target_denom, fee_bps),
         ExecuteMsg::DeleteRoute { source_denom, target_denom } => delete_route(deps, &info.sender, source_denom, target_denom),
-        ExecuteMsg::UpdateConfig { admin, fee_recipient } => update_config(deps, env, info.sender, admin, fee_recipient),
+        ExecuteMsg::UpdateConfig {
+            admin,
+            fee_recipient,
+            paused,
+            swap_fee_bps,
+            allow_direct_fallback,
+            strict_funds,
+            fee_in_output_denom,
+            refund_dust_threshold,
+            dust_policy,
+            min_fee,
+            max_fee,
+            over_delivery_policy,
+            admin_fee_exempt,
+            hop_retry_price_band_bps,
+            fee_burn_bps,
+            burn_address,
+            allow_custom_routes,
+            large_swap_delay_blocks,
+            reject_unaligned_exact_output,
+            exact_output_tolerance_bps,
+            min_refund_amount,
+            positive_slippage_bps,
+            high_impact_threshold_bps,
+            reject_sub_tick_amounts,
+            output_timelock_seconds,
+        } => update_config(
+            deps,
+            env,
+            info.sender,
+            ConfigUpdate {
+                admin,
+                fee_recipient,
+                paused,
+                swap_fee_bps,
+                allow_direct_fallback,
+                strict_funds,
+                fee_in_output_denom,
+                refund_dust_threshold,
+                dust_policy,
+                min_fee,
+                max_fee,
+                over_delivery_policy,
+                admin_fee_exempt,
+                hop_retry_price_band_bps,
+                fee_burn_bps,
+                burn_address,
+                allow_custom_routes,
+                large_swap_delay_blocks,
+                reject_unaligned_exact_output,
+                exact_output_tolerance_bps,
+                min_refund_amount,
+                positive_slippage_bps,
+                high_impact_threshold_bps,
+                reject_sub_tick_amounts,
+                output_timelock_seconds,
+            },
+        ),
+        ExecuteMsg::SetCw20Output { denom, adapter } => set_cw20_output(deps, &info.sender, denom, adapter),
+        ExecuteMsg::SetFeeRecipientForDenom { denom, recipient } => set_fee_recipient_for_denom(deps, &info.sender, denom, recipient),
+        ExecuteMsg::SetLargeSwapThreshold { denom, threshold } => set_large_swap_threshold(deps, &info.sender, denom, threshold),
+        ExecuteMsg::SetMaxSlippage { max_slippage_bps } => set_max_slippage(deps, &info.sender, max_slippage_bps),
+        ExecuteMsg::SetReserve { denom, amount } => set_reserve(deps, &info.sender, denom, amount),
         ExecuteMsg::WithdrawSupportFunds { coins, target_address } => withdraw_support_funds(deps, info.sender, coins, target_address),
     }
 }
@@ -68,7 +232,11 @@ pub fn execute(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut<InjectiveQueryWrapper>, env: Env, msg: Reply) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
     match msg.id {
-        ATOMIC_ORDER_REPLY_ID => handle_atomic_order_reply(deps, env, msg),
+        ATOMIC_ORDER_REPLY_ID => {
+            // only the order of the current swap step awaits a reply, no other one may advance or finalize a swap
+            ensure!(STEP_STATE.exists(deps.storage), ContractError::UnexpectedReply(msg.id));
+            handle_atomic_order_reply(deps, env, msg)
+        }
         _ => Err(ContractError::UnrecognizedReply(msg.id)),
     }
 }
@@ -76,7 +244,9 @@ pub fn reply(deps: DepsMut<InjectiveQueryWrapper>, env: Env, msg: Reply) -> Resu
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps<InjectiveQueryWrapper>, env: Env, msg: QueryMsg) -> Result<Binary, StdError> {
     match msg {
-        QueryMsg::GetRoute { source_denom, target_denom } => to_json_binary(&read_swap_route(deps.storage, &source_denom, &target_denom)?),
+        QueryMsg::GetRoute { source_denom, target_denom } => {
+            to_json_binary(&swap_route_response(deps, read_swap_route(deps.storage, &source_denom, &target_denom)?))
+        }
         QueryMsg::GetOutputQuantity {
             from_quantity,
             source_denom,
@@ -101,7 +271,52 @@ pub fn query(deps: Deps<InjectiveQueryWrapper>, env: Env, msg: QueryMsg) -> Resu
             SwapQuantity::OutputQuantity(to_quantity),
         )?),
 
-        QueryMsg::GetAllRoutes { start_after, limit } => to_json_binary(&get_all_swap_routes(deps.storage, start_after, limit)?),
+        QueryMsg::GetImpliedRate { source_denom, target_denom } => to_json_binary(&get_implied_rate(deps, &source_denom, &target_denom)?),
+        QueryMsg::GetMinEffectiveInput { source_denom, target_denom } => {
+            to_json_binary(&get_min_effective_input(deps, &env, &source_denom, &target_denom)?)
+        }
+
+        QueryMsg::GetQuote {
+            from_quantity,
+            source_denom,
+            target_denom,
+            sender,
+        } => to_json_binary(&quote_swap(deps, &env, sender, source_denom, &target_denom, from_quantity)?),
+
+        QueryMsg::GetGrossInputQuantity {
+            to_quantity,
+            source_denom,
+            target_denom,
+        } => to_json_binary(&estimate_gross_input(deps, &env, &source_denom, &target_denom, to_quantity)?),
+
+        QueryMsg::GetOutputForFeeBudget {
+            source_denom,
+            target_denom,
+            max_fee,
+        } => to_json_binary(&estimate_output_for_fee_budget(deps, &env, source_denom, target_denom, max_fee)?),
+
+        QueryMsg::DebugDumpState { sender } => to_json_binary(&get_debug_state(deps.storage, sender)?),
+        QueryMsg::PreviewPlan {
+            sender,
+            source_denom,
+            target_denom,
+            input_amount,
+        } => to_json_binary(&preview_swap_plan(deps, &env, sender, source_denom, target_denom, input_amount)?),
+        QueryMsg::IsSwappable { source_denom, target_denom } => to_json_binary(&is_swappable(deps, &source_denom, &target_denom)?),
+        QueryMsg::CanSwap { sender } => to_json_binary(&can_swap(deps, &env, &sender)?),
+        QueryMsg::GetBreakEvenOutput {
+            source_denom,
+            target_denom,
+            input_amount,
+        } => to_json_binary(&estimate_break_even_output(deps, &env, source_denom, target_denom, input_amount)?),
+
+        QueryMsg::GetContractBalances { start_after, limit } => to_json_binary(&get_contract_balances(deps, &env, start_after, limit)?),
+        QueryMsg::GetAllRoutes { start_after, limit } => to_json_binary(
+            &get_all_swap_routes(deps.storage, start_after, limit)?
+                .into_iter()
+                .map(|route| swap_route_response(deps, route))
+                .collect::<Vec<SwapRouteResponse>>(),
+        ),
 
         QueryMsg::GetConfig {} => {
             let config = get_config(deps.storage)?;
@@ -111,6 +326,26 @@ pub fn query(deps: Deps<InjectiveQueryWrapper>, env: Env, msg: QueryMsg) -> Resu
             };
             Ok(to_json_binary(&config_response)?)
         }
+
+        QueryMsg::GetAdmin {} => to_json_binary(&get_config(deps.storage)?.admin),
+
+        QueryMsg::GetGasEstimate { mode, route_steps } => to_json_binary(&estimate_swap_gas(mode, route_steps)),
+        QueryMsg::GetSwapBounds { source_denom, target_denom } => to_json_binary(&get_swap_bounds(deps, &source_denom, &target_denom)?),
+        QueryMsg::GetLimits {} => to_json_binary(&get_limits(deps.storage)?),
+        QueryMsg::GetAccounting {} => to_json_binary(&get_accounting(deps.storage)?),
+        QueryMsg::GetVolume { denom } => to_json_binary(&get_swap_volume(deps.storage, &denom)?),
+        QueryMsg::GetUserSwapCount { sender } => to_json_binary(&get_user_swap_count(deps.storage, &sender)?),
+
+        QueryMsg::GetRoutesByMarket { market_id } => to_json_binary(&get_swap_routes_by_market(deps.storage, &market_id)?),
+        QueryMsg::GetMarketFees { market_id } => to_json_binary(&get_market_fees(deps.storage, &market_id)?),
+        QueryMsg::GetBrokenRoutes {} => to_json_binary(&get_broken_routes(deps)?),
+
+        QueryMsg::GetSourceDenoms {} => to_json_binary(&get_source_denoms(deps.storage)?),
+
+        QueryMsg::GetReachableTargets { source_denom } => to_json_binary(&get_reachable_targets(deps.storage, &source_denom)?),
+        QueryMsg::GetRoutesToTarget { target_denom } => to_json_binary(&get_routes_to_target(deps.storage, &target_denom)?),
+
+        QueryMsg::GetStaleSwaps { older_than_seconds } => to_json_binary(&get_stale_swaps(deps.storage, env.block.time, older_than_seconds)?),
     }
 }
 
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use injective_cosmwasm::MarketId;
 use injective_math::FPDecimal;
 use thiserror::Error;
 
@@ -19,6 +20,9 @@ pub enum ContractError {
     #[error("Unrecognized reply id: {0}")]
     UnrecognizedReply(u64),
 
+    #[error("Reply {0} does not answer a pending swap order")]
+    UnexpectedReply(u64),
+
     #[error("Invalid reply from sub-message {id}, {err}")]
     ReplyParseFailure { id: u64, err: String },
 
@@ -28,6 +32,117 @@ pub enum ContractError {
     #[error("Provided amount of {0} is below required amount of {1}")]
     InsufficientFundsProvided(FPDecimal, FPDecimal),
 
+    #[error("Required input of {required} exceeds max input of {max}")]
+    InputExceedsMax { required: FPDecimal, max: Uint128 },
+
+    #[error("Requested slippage of {requested_bps} bps exceeds max allowed slippage of {max_bps} bps")]
+    SlippageTooLoose { requested_bps: FPDecimal, max_bps: u16 },
+
+    #[error("Memo of {length} bytes exceeds max length of {max_length} bytes")]
+    MemoTooLong { length: usize, max_length: usize },
+
+    #[error("{field} of {bps} bps must not exceed 10000 bps")]
+    InvalidBps { field: String, bps: u16 },
+
+    #[error("Input fraction of {fraction_bps} bps must be above zero and at most 10000 bps")]
+    InvalidInputFraction { fraction_bps: u16 },
+
+    #[error("No swap in flight")]
+    NoPendingSwap {},
+
+    #[error("Another swap is in flight")]
+    SwapInFlight {},
+
+    #[error("Swap started in this block can not be cancelled")]
+    SwapStartedInThisBlock {},
+
+    #[error("Recipient weights must add up to 10000 bps, got {total_bps} bps")]
+    InvalidRecipientWeights { total_bps: u32 },
+
+    #[error("Portfolio target weights must add up to 10000 bps, got {total_bps} bps")]
+    InvalidPortfolioWeights { total_bps: u32 },
+
+    #[error("Price impact of {impact_bps} bps is above {threshold_bps} bps and has to be acknowledged")]
+    HighImpactNotAcknowledged { impact_bps: FPDecimal, threshold_bps: u16 },
+
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("Contract cannot swap on its own behalf")]
+    SelfCall {},
+
+    #[error("Refund of {refund} exceeds provided input of {input}")]
+    RefundExceedsInput { refund: Uint128, input: Uint128 },
+
+    #[error("Invalid {field} address {address}: {reason}")]
+    InvalidAddress { field: String, address: String, reason: String },
+
+    #[error("Swap output of {amount} {denom} is below the smallest deliverable unit")]
+    OutputIsDust { amount: FPDecimal, denom: String },
+
+    #[error("Route denom {denom} is not traded on market {}", market_id.as_str())]
+    RouteDenomMismatch { denom: String, market_id: MarketId },
+
+    #[error("Market {} not found", market_id.as_str())]
+    MarketNotFound { market_id: MarketId },
+
+    #[error("Contract holds {available} {denom}, less than the {required} it has to send")]
+    BalanceDrift { denom: String, required: Uint128, available: Uint128 },
+
+    #[error("Swap would leave {remaining} {denom} with the contract, below its reserve of {reserve}")]
+    ReserveProtected { denom: String, reserve: Uint128, remaining: Uint128 },
+
+    #[error("Output worth {value} is below the minimum output value {min_value}")]
+    OutputValueNotReached { value: FPDecimal, min_value: FPDecimal },
+
+    #[error("Swaps along custom routes are disabled")]
+    CustomRoutesDisabled {},
+
+    #[error("Route from {source_denom} to {target_denom} has no steps")]
+    EmptyRoute { source_denom: String, target_denom: String },
+
+    #[error("Route from {source_denom} to {target_denom} is disabled")]
+    RouteDisabled { source_denom: String, target_denom: String },
+
+    #[error("Market {} appears more than once in the route", market_id.as_str())]
+    DuplicateMarketInRoute { market_id: MarketId },
+
+    #[error("Swap input of {amount} is above the large swap threshold of {threshold}, commit it with CommitLargeSwap")]
+    LargeSwapRequiresCommit { amount: Uint128, threshold: Uint128 },
+
+    #[error("Sender already has a committed large swap")]
+    LargeSwapAlreadyCommitted {},
+
+    #[error("Sender has a pending large swap commit")]
+    PendingLargeSwapCommit {},
+
+    #[error("Output can't be claimed before {claimable_at}")]
+    OutputStillLocked { claimable_at: u64 },
+
+    #[error("Sender has no output to claim")]
+    NoOutputToClaim {},
+
+    #[error("Sender has no committed large swap")]
+    NoCommittedLargeSwap {},
+
+    #[error("Committed large swap can't be executed before block {ready_at_height}")]
+    LargeSwapNotReady { ready_at_height: u64 },
+
+    #[error("Swap can't be split into {slices} slices, it takes between 1 and {max_slices}")]
+    InvalidSlices { slices: u8, max_slices: u8 },
+
+    #[error("Amount {amount} is below the min quantity tick {min_quantity_tick_size}")]
+    AmountBelowTick { amount: FPDecimal, min_quantity_tick_size: FPDecimal },
+
+    #[error("Output quantity {quantity} is not a multiple of the min quantity tick {min_quantity_tick_size}")]
+    UnalignedOutputQuantity {
+        quantity: FPDecimal,
+        min_quantity_tick_size: FPDecimal,
+    },
+
+    #[error("Swap takes {expected}, got {got}")]
+    WrongInputDenom { expected: String, got: String },
+
     #[error("Contract can't be migrated")]
     MigrationError {},
 }
@@ -1,10 +1,14 @@
-use cosmwasm_std::{CosmosMsg, DepsMut, Response, SubMsg};
+use cosmwasm_std::{ensure, CosmosMsg, DepsMut, Response, SubMsg, Uint128, Uint256};
 
 use cw_storage_plus::Item;
-use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper};
+use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQueryWrapper, SpotMarket};
 use injective_math::FPDecimal;
 
-use crate::{state::CONFIG, types::Config, ContractError};
+use crate::{
+    state::{CONFIG, MAX_BPS},
+    types::Config,
+    ContractError,
+};
 
 pub fn i32_to_dec(source: i32) -> FPDecimal {
     FPDecimal::from(i128::from(source))
@@ -18,9 +22,11 @@ pub fn get_message_data(response: &[SubMsg<InjectiveMsgWrapper>], position: usiz
     sth
 }
 
+/// Rounds `num` up to the nearest multiple of `min_tick`, zero stays zero. A zero `min_tick`
+/// (misconfigured market) means there is nothing to round to, so `num` is returned as is.
 pub fn round_up_to_min_tick(num: FPDecimal, min_tick: FPDecimal) -> FPDecimal {
-    if num < min_tick {
-        return min_tick;
+    if min_tick.is_zero() {
+        return num;
     }
 
     let remainder = FPDecimal::from(num.num % min_tick.num);
@@ -32,6 +38,66 @@ pub fn round_up_to_min_tick(num: FPDecimal, min_tick: FPDecimal) -> FPDecimal {
     FPDecimal::from(num.num - remainder.num + min_tick.num)
 }
 
+/// Whether spending `input_denom` on `market` is a buy. Routes traverse markets in either direction,
+/// so this only depends on which side of the market the input is on: spending the quote buys the base.
+pub fn is_buy_step(market: &SpotMarket, input_denom: &str) -> bool {
+    market.quote_denom == input_denom
+}
+
+/// Whole units of `amount` to send. Bank amounts are integers, so the fraction is always dropped,
+/// which makes sure the contract never sends more than the orders filled for.
+pub fn floor_to_uint(amount: FPDecimal) -> Uint128 {
+    amount.int().into()
+}
+
+/// Part of `provided` given back when `required_input` of it is used up, fees included. A fraction of the
+/// required input takes a whole unit, so the refund never exceeds what is left over, and it is zero when
+/// nothing is left.
+pub(crate) fn compute_refund(provided: Uint128, required_input: FPDecimal) -> Uint128 {
+    let provided = FPDecimal::from(provided);
+    if required_input >= provided {
+        return Uint128::zero();
+    }
+    floor_to_uint(provided - required_input)
+}
+
+/// `fee_bps` of `amount`, rounded down: amounts below `MAX_BPS / fee_bps` pay nothing.
+pub fn calculate_fee(amount: Uint128, fee_bps: u16) -> Uint128 {
+    amount.multiply_ratio(fee_bps, MAX_BPS)
+}
+
+/// Rejects a bps config `field` set above `MAX_BPS`.
+pub fn validate_bps(field: &str, bps: u16) -> Result<(), ContractError> {
+    ensure!(
+        bps <= MAX_BPS,
+        ContractError::InvalidBps {
+            field: field.to_string(),
+            bps,
+        }
+    );
+    Ok(())
+}
+
+/// Integer-only counterpart of selling `quantity` at `price` with `fee_percent` taken from the proceeds.
+/// The fee is rounded up so the output is a whole amount, leaving no dust to truncate when it is sent.
+/// Returns `(output, fee)`, or `None` when `quantity` or `price` is fractional or the proceeds overflow.
+pub fn integer_sell_output(quantity: FPDecimal, price: FPDecimal, fee_percent: FPDecimal) -> Option<(Uint128, Uint128)> {
+    if quantity != quantity.int() || price != price.int() {
+        return None;
+    }
+
+    let quantity: Uint128 = quantity.into();
+    let price: Uint128 = price.into();
+    let exchange_quantity = quantity.checked_mul(price).ok()?;
+
+    // fee_percent has 18 decimals, so its atomics over 10^18 is the exact ratio
+    let one = Uint256::from(10u128.pow(18));
+    let fee_numerator = exchange_quantity.full_mul(Uint128::new(fee_percent.num.as_u128()));
+    let fee = Uint128::try_from((fee_numerator + one - Uint256::one()) / one).ok()?;
+
+    Some((exchange_quantity.checked_sub(fee).ok()?, fee))
+}
+
 pub trait Scaled {
     fn scaled(self, digits: i32) -> Self;
 }
@@ -52,10 +118,7 @@ const V100CONFIG: Item<V100Config> = Item::new("config");
 pub fn handle_config_migration(deps: DepsMut<InjectiveQueryWrapper>) -> Result<Response, ContractError> {
     let v100_config = V100CONFIG.load(deps.storage)?;
 
-    let config = Config {
-        fee_recipient: v100_config.fee_recipient,
-        admin: v100_config.admin,
-    };
+    let config = Config::new(v100_config.fee_recipient, v100_config.admin);
 
     CONFIG.save(deps.storage, &config)?;
 
@@ -67,6 +130,8 @@ pub fn handle_config_migration(deps: DepsMut<InjectiveQueryWrapper>) -> Result<R
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
     use std::str::FromStr;
 
     #[test]
@@ -103,5 +168,75 @@ mod tests {
 
         let result = round_up_to_min_tick(num, min_tick);
         assert_eq!(result, FPDecimal::from_str("0.000001").unwrap());
+
+        let result = round_up_to_min_tick(FPDecimal::ZERO, min_tick);
+        assert_eq!(result, FPDecimal::ZERO);
+    }
+
+    #[test]
+    fn test_round_up_to_zero_min_tick_does_not_round() {
+        let num = FPDecimal::from_str("0.00000153").unwrap();
+
+        let result = round_up_to_min_tick(num, FPDecimal::ZERO);
+        assert_eq!(result, num);
+    }
+
+    // builds a decimal from its raw 18 decimals representation, e.g. 1 -> 0.000000000000000001
+    fn from_atomics(raw: u128) -> FPDecimal {
+        FPDecimal::from(raw) / FPDecimal::from(10u128.pow(18))
+    }
+
+    fn assert_round_up_invariants(num: FPDecimal, min_tick: FPDecimal) -> Result<(), TestCaseError> {
+        let result = round_up_to_min_tick(num, min_tick);
+
+        prop_assert!(result >= num, "{result} is below {num}");
+        prop_assert!((result.num % min_tick.num).is_zero(), "{result} is not a multiple of {min_tick}");
+        prop_assert!(result - num < min_tick, "{result} is a full tick or more above {num}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_refund() {
+        let cases = [
+            (10u128, "0", 10u128),
+            (10, "4", 6),
+            (10, "4.2", 5),
+            (10, "9.999999999999999999", 0),
+            (10, "10", 0),
+            (10, "10.5", 0),
+            (10, "1000", 0),
+            (0, "0", 0),
+            (0, "1", 0),
+            (1, "0.000000000000000001", 0),
+        ];
+
+        for (provided, required_input, expected) in cases {
+            assert_eq!(
+                compute_refund(Uint128::new(provided), FPDecimal::must_from_str(required_input)),
+                Uint128::new(expected),
+                "wrong refund of {provided} for a required input of {required_input}"
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn compute_refund_never_gives_back_more_than_is_left(provided in 0u128..10u128.pow(21), required_input in 0u128..10u128.pow(38)) {
+            let required_input = from_atomics(required_input);
+            let refund = compute_refund(Uint128::new(provided), required_input);
+
+            prop_assert!(refund.is_zero() || FPDecimal::from(refund) + required_input <= FPDecimal::from(provided), "refund {refund} of {provided} exceeds what {required_input} leaves");
+            prop_assert!(FPDecimal::from(refund) + required_input + FPDecimal::ONE > FPDecimal::from(provided), "refund {refund} of {provided} is short by a unit or more after {required_input}");
+        }
+
+        #[test]
+        fn round_up_to_min_tick_invariants(num in 0u128..10u128.pow(24), min_tick in 1u128..10u128.pow(21)) {
+            assert_round_up_invariants(from_atomics(num), from_atomics(min_tick))?;
+        }
+
+        #[test]
+        fn round_up_to_min_tick_invariants_below_one_tick((num, min_tick) in (1u128..10u128.pow(21)).prop_flat_map(|tick| (0..tick, Just(tick)))) {
+            assert_round_up_invariants(from_atomics(num), from_atomics(min_tick))?;
+        }
     }
 }
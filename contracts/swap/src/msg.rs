@@ -1,7 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Coin, Uint128};
 use injective_cosmwasm::MarketId;
 use injective_math::FPDecimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::types::{DustPolicy, MinOutputValue, OverDeliveryPolicy, PortfolioTarget, SwapMode, SwapQuantityMode, SwapRoute};
 
 #[cw_serde]
 pub enum FeeRecipient {
@@ -13,26 +16,105 @@ pub enum FeeRecipient {
 pub struct InstantiateMsg {
     pub fee_recipient: FeeRecipient,
     pub admin: Addr,
+    // routes registered along with the config, held to the same rules as routes set by the admin later on
+    #[serde(default)]
+    pub initial_routes: Vec<SwapRoute>,
 }
 
 #[cw_serde]
 pub struct MigrateMsg {}
 
+/// Reads a field that is present as `Some`, so that an `Option<Option<T>>` field set to null clears the value
+/// while leaving the field out keeps it.
+fn deserialize_some<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     SwapMinOutput {
         target_denom: String,
         min_output_quantity: FPDecimal,
+        // if set and different from target_denom, the output is converted further into this denom
+        // using the registered route, min_output_quantity then applies to the converted amount
+        auto_convert_to: Option<String>,
+        // opaque tag emitted in the swap execution event, at most MAX_MEMO_LENGTH bytes
+        memo: Option<String>,
+        // if set, only this fraction of the attached funds is swapped and the rest is given back
+        input_fraction_bps: Option<u16>,
+        // if set, the output also has to be worth at least this much at an oracle price
+        min_output_value: Option<MinOutputValue>,
+        // if set, the input is swapped in this many sub-orders placed one after another, at most MAX_SWAP_SLICES
+        slices: Option<u8>,
+        // has to be set for swaps with a price impact above the configured threshold
+        #[serde(default)]
+        acknowledge_high_impact: bool,
     },
     SwapExactOutput {
         target_denom: String,
         target_output_quantity: FPDecimal,
+        // if set, the swap is aborted when it would spend more than this amount of the provided funds
+        max_input: Option<Uint128>,
+        // if set, a refund above the configured dust threshold is swapped into target_denom as well
+        #[serde(default)]
+        compound_refund: bool,
+        memo: Option<String>,
+        #[serde(default)]
+        acknowledge_high_impact: bool,
+    },
+    // like SwapMinOutput, but the output is split across recipients by bps weights adding up to 10000
+    SwapAndDistribute {
+        target_denom: String,
+        min_output_quantity: FPDecimal,
+        recipients: Vec<(Addr, u16)>,
+    },
+    // splits the input across target denoms by bps weights adding up to 10000, each part swapped along its own route
+    SwapToPortfolio {
+        source_denom: String,
+        targets: Vec<PortfolioTarget>,
+    },
+    // like SwapMinOutput, but along the given market ids instead of the registered route, if custom routes are allowed
+    SwapMinOutputWithRoute {
+        target_denom: String,
+        min_output_quantity: FPDecimal,
+        route: Vec<MarketId>,
+    },
+    // aborts the in-flight swap of the sender, if it was started in an earlier block
+    CancelSwap {},
+    // holds the attached funds for a swap above the large swap threshold, to be executed in a later block
+    CommitLargeSwap {
+        target_denom: String,
+        swap_quantity_mode: SwapQuantityMode,
+        // has to be set for swaps with a price impact above the configured threshold, checked once the swap is executed
+        #[serde(default)]
+        acknowledge_high_impact: bool,
     },
+    // executes the committed swap of the sender once the configured number of blocks has passed
+    ExecuteLargeSwap {},
+    // gives back the funds of the committed swap of the sender
+    CancelLargeSwap {},
+    // sends the sender their timelocked outputs that can be claimed by now
+    ClaimOutput {},
+    // admin only: gives back the funds of the in-flight swap and of the committed large swaps, in batches
+    RefundAllInFlight {},
     SetRoute {
         source_denom: String,
         target_denom: String,
         route: Vec<MarketId>,
     },
+    SetRoutes {
+        routes: Vec<SwapRoute>,
+    },
+    SetRouteEnabled {
+        source_denom: String,
+        target_denom: String,
+        enabled: bool,
+    },
+    SetRouteFee {
+        source_denom: String,
+        target_denom: String,
+        fee_bps: Option<u16>,
+    },
     DeleteRoute {
         source_denom: String,
         target_denom: String,
@@ -40,6 +122,56 @@ pub enum ExecuteMsg {
     UpdateConfig {
         admin: Option<Addr>,
         fee_recipient: Option<FeeRecipient>,
+        paused: Option<bool>,
+        swap_fee_bps: Option<u16>,
+        allow_direct_fallback: Option<bool>,
+        strict_funds: Option<bool>,
+        fee_in_output_denom: Option<bool>,
+        refund_dust_threshold: Option<Uint128>,
+        dust_policy: Option<DustPolicy>,
+        min_fee: Option<Uint128>,
+        // null removes the cap
+        #[serde(default, deserialize_with = "deserialize_some")]
+        max_fee: Option<Option<Uint128>>,
+        over_delivery_policy: Option<OverDeliveryPolicy>,
+        admin_fee_exempt: Option<bool>,
+        hop_retry_price_band_bps: Option<u16>,
+        fee_burn_bps: Option<u16>,
+        // null stops burning, the whole fee goes to the fee recipient again
+        #[serde(default, deserialize_with = "deserialize_some")]
+        burn_address: Option<Option<Addr>>,
+        allow_custom_routes: Option<bool>,
+        large_swap_delay_blocks: Option<u64>,
+        reject_unaligned_exact_output: Option<bool>,
+        exact_output_tolerance_bps: Option<u16>,
+        min_refund_amount: Option<Uint128>,
+        positive_slippage_bps: Option<u16>,
+        high_impact_threshold_bps: Option<u16>,
+        reject_sub_tick_amounts: Option<bool>,
+        output_timelock_seconds: Option<u64>,
+    },
+    // output in `denom` is delivered as the CW20 token the adapter minted it for, or as the bank denom again if unset
+    SetCw20Output {
+        denom: String,
+        adapter: Option<Addr>,
+    },
+    // contract fees charged in `denom` go to the recipient instead of the configured fee recipient, or to that again if unset
+    SetFeeRecipientForDenom {
+        denom: String,
+        recipient: Option<Addr>,
+    },
+    // swaps of more than `threshold` of `denom` have to be committed first, or run right away again if unset
+    SetLargeSwapThreshold {
+        denom: String,
+        threshold: Option<Uint128>,
+    },
+    SetMaxSlippage {
+        max_slippage_bps: Option<u16>,
+    },
+    // balance in `denom` that swaps must leave with the contract, a zero amount removes the reserve
+    SetReserve {
+        denom: String,
+        amount: Uint128,
     },
     WithdrawSupportFunds {
         coins: Vec<Coin>,
@@ -63,9 +195,110 @@ pub enum QueryMsg {
         source_denom: String,
         target_denom: String,
     },
+    // indicative rate of target per source denom from the mid prices along the route, independent of the size swapped
+    GetImpliedRate {
+        source_denom: String,
+        target_denom: String,
+    },
+    // smallest input for which any output is delivered, below it everything rounds away
+    GetMinEffectiveInput {
+        source_denom: String,
+        target_denom: String,
+    },
+    // like GetOutputQuantity, but reports a failing estimation in the response instead of failing
+    GetQuote {
+        from_quantity: FPDecimal,
+        source_denom: String,
+        target_denom: String,
+        // if set, the settlement is quoted for this sender: their fee exemption, and any fee netted into what they receive
+        #[serde(default)]
+        sender: Option<Addr>,
+    },
+    // input to send with SwapExactOutput, including the contract fee
+    GetGrossInputQuantity {
+        to_quantity: FPDecimal,
+        source_denom: String,
+        target_denom: String,
+    },
+    // largest swap whose contract fee stays within max_fee, in the source denom
+    GetOutputForFeeBudget {
+        source_denom: String,
+        target_denom: String,
+        max_fee: Uint128,
+    },
     GetAllRoutes {
         start_after: Option<(String, String)>,
         limit: Option<u32>,
     },
     GetConfig {},
+    // bank balances of the contract ordered by denom
+    GetContractBalances {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GetAdmin {},
+    // rough worst-case gas of a swap along a route of `route_steps` markets, for relayers to set limits with
+    GetGasEstimate {
+        mode: SwapMode,
+        route_steps: u32,
+    },
+    // valid range of the input of a swap between the pair
+    GetSwapBounds {
+        source_denom: String,
+        target_denom: String,
+    },
+    // config and contract limits a swap has to stay within
+    GetLimits {},
+    // per denom fees collected, swapped volume, refunds and completed swaps
+    GetAccounting {},
+    GetVolume {
+        denom: String,
+    },
+    // swaps the sender completed, for activity based fee tiers or rewards
+    GetUserSwapCount {
+        sender: Addr,
+    },
+    GetRoutesByMarket {
+        market_id: MarketId,
+    },
+    // trading fees paid on the market by settled swaps, in units of its quote denom
+    GetMarketFees {
+        market_id: MarketId,
+    },
+    // routes through a market that is delisted or otherwise not active anymore
+    GetBrokenRoutes {},
+    GetSourceDenoms {},
+    GetReachableTargets {
+        source_denom: String,
+    },
+    // routes from every denom that can be swapped into `target_denom`, oriented towards it
+    GetRoutesToTarget {
+        target_denom: String,
+    },
+    GetStaleSwaps {
+        older_than_seconds: u64,
+    },
+    // public, like all queries: the in-flight swap caches are shared by all senders, `sender` only filters them
+    DebugDumpState {
+        sender: Option<Addr>,
+    },
+    PreviewPlan {
+        sender: Addr,
+        source_denom: String,
+        target_denom: String,
+        input_amount: Uint128,
+    },
+    IsSwappable {
+        source_denom: String,
+        target_denom: String,
+    },
+    // whether the sender may start a swap right now, with a reason when it may not
+    CanSwap {
+        sender: Addr,
+    },
+    GetBreakEvenOutput {
+        source_denom: String,
+        target_denom: String,
+        input_amount: FPDecimal,
+    },
 }
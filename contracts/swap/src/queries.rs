@@ -1,17 +1,109 @@
-use cosmwasm_std::{Addr, Deps, Env, StdError, StdResult};
-use injective_cosmwasm::{InjectiveQuerier, InjectiveQueryWrapper, MarketId, OrderSide, PriceLevel, SpotMarket};
+use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Deps, Env, Order, StdError, StdResult, Uint128, WasmMsg};
+use injective_cosmwasm::{InjectiveMsgWrapper, InjectiveQuerier, InjectiveQueryWrapper, MarketId, MarketStatus, OrderSide, PriceLevel, SpotMarket};
 use injective_math::utils::round_to_min_tick;
 use injective_math::FPDecimal;
-
-use crate::helpers::round_up_to_min_tick;
-use crate::state::{read_swap_route, CONFIG};
-use crate::types::{FPCoin, StepExecutionEstimate, SwapEstimationAmount, SwapEstimationResult};
+use injective_std::types::cosmos::bank::v1beta1::BankQuerier;
+use injective_std::types::cosmos::base::query::v1beta1::PageRequest;
+
+use crate::helpers::{floor_to_uint, integer_sell_output, is_buy_step, round_up_to_min_tick};
+use crate::state::{
+    get_route_market_ids, read_swap_route, CONFIG, DEFAULT_LIMIT, LARGE_SWAP_COMMITS, MAX_BPS, STEP_STATE, SWAP_OPERATION_STATE, SWAP_ROUTES,
+};
+use crate::swap::plan_settlement_messages;
+use crate::types::{
+    Config, CurrentSwapOperation, FPCoin, FeeBudgetResponse, PlannedMessage, QuoteResponse, StepExecutionEstimate, SwapBoundsResponse,
+    SwapEstimationAmount, SwapEstimationResult, SwapMode, SwapQuantityMode, SwapRoute, SwapRouteResponse, SwappableResponse,
+};
+
+// rough upper bounds of the gas taken by the parts of a swap
+const SWAP_BASE_GAS: u64 = 150_000;
+const ORDER_STEP_GAS: u64 = 120_000;
+const EXACT_OUTPUT_ESTIMATION_STEP_GAS: u64 = 40_000;
+const BANK_SEND_GAS: u64 = 20_000;
 
 pub enum SwapQuantity {
     InputQuantity(FPDecimal),
     OutputQuantity(FPDecimal),
 }
 
+/// Worst-case gas of a swap along `route_steps` markets: every step places an order and handles its reply, exact
+/// output swaps estimate the required input along the route first and may settle a refund besides the output and fee.
+pub fn estimate_swap_gas(mode: SwapMode, route_steps: u32) -> u64 {
+    let (step_gas, bank_sends) = match mode {
+        SwapMode::MinOutput => (ORDER_STEP_GAS, 2),
+        SwapMode::ExactOutput => (ORDER_STEP_GAS + EXACT_OUTPUT_ESTIMATION_STEP_GAS, 3),
+    };
+    SWAP_BASE_GAS + u64::from(route_steps) * step_gas + bank_sends * BANK_SEND_GAS
+}
+
+/// Denoms a route passes through in order, from its source to its target.
+pub fn get_route_denoms(deps: Deps<InjectiveQueryWrapper>, route: &SwapRoute) -> StdResult<Vec<String>> {
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let mut denoms = vec![route.source_denom.to_owned()];
+    for market_id in &route.steps {
+        let market = querier
+            .query_spot_market(market_id)?
+            .market
+            .ok_or_else(|| StdError::generic_err(format!("Market {} not found", market_id.as_str())))?;
+        let current_denom = denoms.last().expect("denoms start with the source");
+        let next_denom = if market.base_denom == *current_denom {
+            market.quote_denom
+        } else if market.quote_denom == *current_denom {
+            market.base_denom
+        } else {
+            return Err(StdError::generic_err(format!(
+                "Route denom {current_denom} is not traded on market {}",
+                market_id.as_str()
+            )));
+        };
+        denoms.push(next_denom);
+    }
+    Ok(denoms)
+}
+
+pub fn swap_route_response(deps: Deps<InjectiveQueryWrapper>, route: SwapRoute) -> SwapRouteResponse {
+    let denoms = get_route_denoms(deps, &route).unwrap_or_default();
+    SwapRouteResponse {
+        steps: route.steps,
+        source_denom: route.source_denom,
+        target_denom: route.target_denom,
+        enabled: route.enabled,
+        fee_bps: route.fee_bps,
+        denoms,
+    }
+}
+
+/// Returns the registered route between two denoms. When none is registered and `allow_direct_fallback`
+/// is enabled, a single-step route is built over a market trading the pair directly. Only markets
+/// already used by registered routes are considered.
+pub fn find_swap_route(deps: Deps<InjectiveQueryWrapper>, source_denom: &str, target_denom: &str) -> StdResult<SwapRoute> {
+    let registered_route = read_swap_route(deps.storage, source_denom, target_denom);
+    if registered_route.is_ok() || !CONFIG.load(deps.storage)?.allow_direct_fallback {
+        return registered_route;
+    }
+
+    let querier = InjectiveQuerier::new(&deps.querier);
+    for market_id in get_route_market_ids(deps.storage)? {
+        let Some(market) = querier.query_spot_market(&market_id)?.market else {
+            continue;
+        };
+
+        let is_direct_market = (market.base_denom == source_denom && market.quote_denom == target_denom)
+            || (market.base_denom == target_denom && market.quote_denom == source_denom);
+        if is_direct_market {
+            return Ok(SwapRoute {
+                steps: vec![market_id],
+                source_denom: source_denom.to_string(),
+                target_denom: target_denom.to_string(),
+                enabled: true,
+                fee_bps: None,
+            });
+        }
+    }
+
+    registered_route
+}
+
 pub fn estimate_swap_result(
     deps: Deps<InjectiveQueryWrapper>,
     env: &Env,
@@ -32,7 +124,7 @@ pub fn estimate_swap_result(
         }
     }
 
-    let route = read_swap_route(deps.storage, &source_denom, &target_denom)?;
+    let route = find_swap_route(deps, &source_denom, &target_denom)?;
 
     let (steps, mut current_swap) = match swap_quantity {
         SwapQuantity::InputQuantity(quantity) => (
@@ -56,6 +148,8 @@ pub fn estimate_swap_result(
     };
 
     let mut fees: Vec<FPCoin> = vec![];
+    // a single hop delivers its estimate directly, so it can avoid fractional dust altogether
+    let is_single_hop = steps.len() == 1;
 
     for step in steps {
         let swap_estimate = estimate_single_swap_execution(
@@ -67,6 +161,7 @@ pub fn estimate_swap_result(
                 SwapQuantity::OutputQuantity(_) => SwapEstimationAmount::ReceiveQuantity(current_swap.clone()),
             },
             true,
+            is_single_hop,
         )?;
 
         current_swap.amount = swap_estimate.result_quantity;
@@ -83,12 +178,638 @@ pub fn estimate_swap_result(
     })
 }
 
+/// Estimates the input an exact output swap needs to deliver `target_output_quantity`, rounded to what
+/// the first market of the route accepts. The contract fee is not included.
+pub fn estimate_required_input(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    source_denom: &str,
+    target_denom: &str,
+    target_output_quantity: FPDecimal,
+) -> StdResult<(SwapEstimationResult, FPDecimal)> {
+    let estimation = estimate_swap_result(
+        deps,
+        env,
+        source_denom.to_owned(),
+        target_denom.to_owned(),
+        SwapQuantity::OutputQuantity(target_output_quantity),
+    )?;
+
+    let first_market_id = find_swap_route(deps, source_denom, target_denom)?.steps_from(source_denom)[0].to_owned();
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let first_market = querier
+        .query_spot_market(&first_market_id)?
+        .market
+        .ok_or_else(|| StdError::generic_err(format!("Market {} not found", first_market_id.as_str())))?;
+
+    let required_input = if is_buy_step(&first_market, source_denom) {
+        estimation.result_quantity.int() + FPDecimal::ONE
+    } else {
+        round_up_to_min_tick(estimation.result_quantity, first_market.min_quantity_tick_size)
+    };
+
+    Ok((estimation, required_input))
+}
+
+/// Bank balances of the contract ordered by denom, `limit` of them after `start_after`. Every page is a paginated
+/// bank query of its own, so the whole balance is never loaded at once.
+pub fn get_contract_balances(deps: Deps<InjectiveQueryWrapper>, env: &Env, start_after: Option<String>, limit: Option<u32>) -> StdResult<Vec<Coin>> {
+    // balances are keyed by denom, the key with a zero byte appended is the first one sorting after `start_after`
+    let key = start_after.map_or(vec![], |start_after| [start_after.as_bytes(), &[0]].concat());
+    let response = BankQuerier::new(&deps.querier).all_balances(
+        env.contract.address.to_string(),
+        Some(PageRequest {
+            key,
+            limit: u64::from(limit.unwrap_or(DEFAULT_LIMIT)),
+            ..PageRequest::default()
+        }),
+        false,
+    )?;
+    response
+        .balances
+        .into_iter()
+        .map(|balance| Ok(Coin::new(balance.amount.parse::<Uint128>()?, balance.denom)))
+        .collect()
+}
+
+/// Indicative rate of `target_denom` per unit of `source_denom`: the mid prices of the markets along the route
+/// composed hop by hop, regardless of the depth behind them.
+pub fn get_implied_rate(deps: Deps<InjectiveQueryWrapper>, source_denom: &str, target_denom: &str) -> StdResult<FPDecimal> {
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let mut current_denom = source_denom.to_string();
+    let mut rate = FPDecimal::ONE;
+    for market_id in find_swap_route(deps, source_denom, target_denom)?.steps_from(source_denom) {
+        let market = querier
+            .query_spot_market(&market_id)?
+            .market
+            .ok_or_else(|| StdError::generic_err(format!("Market {} not found", market_id.as_str())))?;
+        let mid_price = get_mid_price(&querier, &market_id)?;
+        if is_buy_step(&market, &current_denom) {
+            rate = rate / mid_price;
+            current_denom = market.base_denom;
+        } else {
+            rate = rate * mid_price;
+            current_denom = market.quote_denom;
+        }
+    }
+    Ok(rate)
+}
+
+fn get_mid_price(querier: &InjectiveQuerier, market_id: &MarketId) -> StdResult<FPDecimal> {
+    let best_buy = querier
+        .query_spot_market_orderbook(market_id, OrderSide::Buy, None, None)?
+        .buys_price_level
+        .first()
+        .map(|level| level.p);
+    let best_sell = querier
+        .query_spot_market_orderbook(market_id, OrderSide::Sell, None, None)?
+        .sells_price_level
+        .first()
+        .map(|level| level.p);
+    match (best_buy, best_sell) {
+        (Some(best_buy), Some(best_sell)) => Ok((best_buy + best_sell) / FPDecimal::from(2u128)),
+        _ => Err(StdError::generic_err(format!("No liquidity on market {}", market_id.as_str()))),
+    }
+}
+
+/// Smallest whole input of `source_denom` estimated to deliver any `target_denom` at all. Outputs are sent in
+/// whole units and bought quantities are rounded down to the market tick, so smaller inputs yield nothing.
+pub fn get_min_effective_input(deps: Deps<InjectiveQueryWrapper>, env: &Env, source_denom: &str, target_denom: &str) -> StdResult<Uint128> {
+    let route = find_swap_route(deps, source_denom, target_denom)?;
+    let last_market_id = route
+        .steps_from(source_denom)
+        .last()
+        .ok_or_else(|| StdError::generic_err(format!("Route from {source_denom} to {target_denom} has no steps")))?
+        .to_owned();
+    let last_market = InjectiveQuerier::new(&deps.querier)
+        .query_spot_market(&last_market_id)?
+        .market
+        .ok_or_else(|| StdError::generic_err(format!("Market {} not found", last_market_id.as_str())))?;
+    let min_output = if last_market.base_denom == target_denom {
+        round_up_to_min_tick(FPDecimal::ONE, last_market.min_quantity_tick_size)
+    } else {
+        FPDecimal::ONE
+    };
+
+    let delivers_output = |input: Uint128| {
+        estimate_swap_result(
+            deps,
+            env,
+            source_denom.to_owned(),
+            target_denom.to_owned(),
+            SwapQuantity::InputQuantity(input.into()),
+        )
+        .map_or(false, |estimation| estimation.result_quantity >= FPDecimal::ONE)
+    };
+
+    // the input required for the smallest output is rounded up, so it bounds the search from above
+    let (_, required_input) = estimate_required_input(deps, env, source_denom, target_denom, min_output)?;
+    let mut effective = floor_to_uint(required_input) + Uint128::one();
+    if !delivers_output(effective) {
+        return Err(StdError::generic_err(format!("No input of {source_denom} delivers any {target_denom}")));
+    }
+    let mut ineffective = Uint128::zero();
+    while effective - ineffective > Uint128::one() {
+        let input = ineffective + (effective - ineffective) / Uint128::new(2);
+        if delivers_output(input) {
+            effective = input;
+        } else {
+            ineffective = input;
+        }
+    }
+    Ok(effective)
+}
+
+/// Estimates the input an exact output swap has to be sent, the required input plus the contract fee
+/// charged on top of it.
+pub fn estimate_gross_input(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    source_denom: &str,
+    target_denom: &str,
+    target_output_quantity: FPDecimal,
+) -> StdResult<FPDecimal> {
+    let (_, required_input) = estimate_required_input(deps, env, source_denom, target_denom, target_output_quantity)?;
+    let route = find_swap_route(deps, source_denom, target_denom)?;
+    let config = CONFIG.load(deps.storage)?.with_route_fee(route.fee_bps);
+
+    Ok(required_input + FPDecimal::from(config.swap_fee(required_input.into())))
+}
+
+/// Finds the largest input of a min output swap whose contract fee is at most `max_fee`, and estimates the
+/// output it buys. Fails when the fee is charged in the output denom or can not exceed the budget at all.
+pub fn estimate_output_for_fee_budget(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    source_denom: String,
+    target_denom: String,
+    max_fee: Uint128,
+) -> StdResult<FeeBudgetResponse> {
+    let route = find_swap_route(deps, &source_denom, &target_denom)?;
+    let config = CONFIG.load(deps.storage)?.with_route_fee(route.fee_bps);
+    if config.fee_in_output_denom {
+        return Err(StdError::generic_err("fee budget is only supported for fees charged in the source denom"));
+    }
+    if config.min_fee > max_fee {
+        return Err(StdError::generic_err(format!(
+            "fee budget is below the minimum fee of {}",
+            config.min_fee
+        )));
+    }
+    if config.swap_fee_bps == 0 || config.max_fee.is_some_and(|fee_cap| fee_cap <= max_fee) {
+        return Err(StdError::generic_err("contract fee never exceeds the fee budget"));
+    }
+
+    // the bps fee is rounded down, so it stays within the budget while input * bps < (max_fee + 1) * MAX_BPS
+    let input_quantity = (max_fee + Uint128::one())
+        .checked_mul(Uint128::from(MAX_BPS))?
+        .checked_sub(Uint128::one())?
+        / Uint128::from(config.swap_fee_bps);
+    let fee = config.swap_fee(input_quantity);
+
+    let estimation = estimate_swap_result(
+        deps,
+        env,
+        source_denom,
+        target_denom,
+        SwapQuantity::InputQuantity(FPDecimal::from(input_quantity - fee)),
+    )?;
+
+    Ok(FeeBudgetResponse {
+        input_quantity,
+        fee,
+        result_quantity: estimation.result_quantity,
+    })
+}
+
+/// Estimates the output of swapping `input` through the given markets.
+pub fn estimate_steps_output(deps: Deps<InjectiveQueryWrapper>, env: &Env, steps: &[MarketId], input: FPCoin) -> StdResult<FPCoin> {
+    let mut current_swap = input;
+    for step in steps {
+        let swap_estimate = estimate_single_swap_execution(&deps, env, step, SwapEstimationAmount::InputQuantity(current_swap), true, false)?;
+        current_swap = FPCoin {
+            amount: swap_estimate.result_quantity,
+            denom: swap_estimate.result_denom,
+        };
+    }
+    Ok(current_swap)
+}
+
+/// Price impact of swapping `input` along `steps`: the share of the output lost to each order filling down to
+/// its worst price rather than at the best price of its orderbook, compounded over the steps.
+pub fn estimate_price_impact(deps: Deps<InjectiveQueryWrapper>, env: &Env, steps: &[MarketId], input: FPCoin) -> StdResult<FPDecimal> {
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let mut current_swap = input;
+    let mut kept_share = FPDecimal::ONE;
+    for step in steps {
+        let swap_estimate = estimate_single_swap_execution(&deps, env, step, SwapEstimationAmount::InputQuantity(current_swap), true, false)?;
+        let best_price = if swap_estimate.is_buy_order {
+            let orders = querier.query_spot_market_orderbook(step, OrderSide::Sell, None, None)?;
+            orders.sells_price_level.first().map(|level| level.p)
+        } else {
+            let orders = querier.query_spot_market_orderbook(step, OrderSide::Buy, None, None)?;
+            orders.buys_price_level.first().map(|level| level.p)
+        }
+        .ok_or_else(|| StdError::generic_err(format!("No liquidity on market {}", step.as_str())))?;
+
+        // buys pay more than the best price, sells receive less
+        kept_share = kept_share
+            * if swap_estimate.is_buy_order {
+                best_price / swap_estimate.worst_price
+            } else {
+                swap_estimate.worst_price / best_price
+            };
+        current_swap = FPCoin {
+            amount: swap_estimate.result_quantity,
+            denom: swap_estimate.result_denom,
+        };
+    }
+    Ok(FPDecimal::ONE - kept_share)
+}
+
+/// Messages `finalize_swap` would settle a min output swap of `input` by `sender` with, had its last step delivered
+/// `output` and the swap charged `fee`. Min output swaps only refund when a fraction of the input is swapped, which
+/// is never planned here.
+fn plan_settlement(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    config: &Config,
+    sender: Addr,
+    input: Coin,
+    fee: Coin,
+    output: Coin,
+) -> StdResult<Vec<CosmosMsg<InjectiveMsgWrapper>>> {
+    let swap = CurrentSwapOperation {
+        sender_address: sender,
+        swap_steps: vec![],
+        swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ZERO),
+        refund: Coin::new(0u128, input.denom.to_owned()),
+        input_funds: input,
+        fee,
+        compound_refund: false,
+        memo: None,
+        started_at: env.block.time,
+        recipients: vec![],
+        min_output_value: None,
+        slices: None,
+        estimated_output: None,
+        exact_output_input: None,
+        portfolio: vec![],
+        route_fee_bps: None,
+    };
+    plan_settlement_messages(deps, config, &env.contract.address, &swap, output)
+}
+
+/// Settlement message as listed in a preview.
+fn planned_message(message: CosmosMsg<InjectiveMsgWrapper>) -> StdResult<PlannedMessage> {
+    match message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, mut amount }) if amount.len() == 1 => Ok(PlannedMessage::BankSend {
+            to_address: Addr::unchecked(to_address),
+            amount: amount.remove(0),
+        }),
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => Ok(PlannedMessage::WasmExecute {
+            contract_addr: Addr::unchecked(contract_addr),
+            msg,
+            funds,
+        }),
+        message => Err(StdError::generic_err(format!("Unexpected settlement message {message:?}"))),
+    }
+}
+
+/// Lists the market orders and settlement messages a min output swap of `input_amount` would emit, based on
+/// estimated step results. Orders after the first one depend on the actual results of the previous steps, the
+/// settlement is planned the way `finalize_swap` sends it: no output send while outputs are timelocked, and the
+/// output of a CW20 denom redeemed through its adapter.
+pub fn preview_swap_plan(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    sender: Addr,
+    source_denom: String,
+    target_denom: String,
+    input_amount: Uint128,
+) -> StdResult<Vec<PlannedMessage>> {
+    let route = find_swap_route(deps, &source_denom, &target_denom)?;
+    let config = CONFIG.load(deps.storage)?.with_route_fee(route.fee_bps);
+    let steps = route.steps_from(&source_denom);
+    let querier = InjectiveQuerier::new(&deps.querier);
+
+    let input = Coin::new(input_amount, source_denom.to_owned());
+    let mut fee = if config.fee_in_output_denom {
+        Coin::new(0u128, target_denom)
+    } else {
+        Coin::new(config.swap_fee_for(&sender, input_amount), source_denom.to_owned())
+    };
+    let mut current_balance = FPCoin {
+        amount: input_amount.checked_sub(fee.amount)?.into(),
+        denom: source_denom,
+    };
+    let mut plan = vec![];
+
+    for (step_idx, market_id) in steps.iter().enumerate() {
+        let estimate = estimate_single_swap_execution(
+            &deps,
+            env,
+            market_id,
+            SwapEstimationAmount::InputQuantity(current_balance.clone()),
+            true,
+            false,
+        )?;
+        plan.push(PlannedMessage::MarketOrder {
+            market_id: market_id.to_owned(),
+            is_buy: estimate.is_buy_order,
+            price: estimate.worst_price,
+            quantity: if estimate.is_buy_order {
+                estimate.result_quantity
+            } else {
+                current_balance.amount
+            },
+            input: current_balance,
+        });
+
+        // same rounding as applied to the step results before the next sell
+        let mut result_quantity = estimate.result_quantity;
+        if let Some(next_market_id) = steps.get(step_idx + 1) {
+            let next_market = querier
+                .query_spot_market(next_market_id)?
+                .market
+                .ok_or_else(|| StdError::generic_err(format!("Market {} not found", next_market_id.as_str())))?;
+            if !is_buy_step(&next_market, &estimate.result_denom) {
+                result_quantity = round_to_min_tick(result_quantity, next_market.min_quantity_tick_size);
+            }
+        }
+        current_balance = FPCoin {
+            amount: result_quantity,
+            denom: estimate.result_denom,
+        };
+    }
+
+    if config.fee_in_output_denom {
+        fee.amount = config.swap_fee_for(&sender, current_balance.amount.into());
+        current_balance.amount -= FPDecimal::from(fee.amount);
+    }
+
+    for message in plan_settlement(deps, env, &config, sender, input, fee, current_balance.into())? {
+        plan.push(planned_message(message)?);
+    }
+
+    Ok(plan)
+}
+
+/// Valid range of the input reaching the first market of the route, i.e. after any contract fee charged on
+/// the input. The minimum is one quantity tick of the market (at the best price for buys, and never below the
+/// min notional), the maximum the whole orderbook depth on the side the input is filled against.
+pub fn get_swap_bounds(deps: Deps<InjectiveQueryWrapper>, source_denom: &str, target_denom: &str) -> StdResult<SwapBoundsResponse> {
+    let steps = find_swap_route(deps, source_denom, target_denom)?.steps_from(source_denom);
+    let first_market_id = steps.first().ok_or_else(|| StdError::generic_err("Route has no steps"))?;
+
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let market = querier
+        .query_spot_market(first_market_id)?
+        .market
+        .ok_or_else(|| StdError::generic_err(format!("Market {} not found", first_market_id.as_str())))?;
+
+    if is_buy_step(&market, source_denom) {
+        let orders = querier.query_spot_market_orderbook(&market.market_id, OrderSide::Sell, None, None)?;
+        let best_price = orders
+            .sells_price_level
+            .first()
+            .ok_or_else(|| StdError::generic_err(format!("No liquidity on market {}", market.market_id.as_str())))?
+            .p;
+
+        let min_tick_input = market.min_quantity_tick_size * best_price;
+        Ok(SwapBoundsResponse {
+            min_input: if min_tick_input < market.min_notional {
+                market.min_notional
+            } else {
+                min_tick_input
+            },
+            max_input: orders
+                .sells_price_level
+                .iter()
+                .fold(FPDecimal::ZERO, |depth, level| depth + level.p * level.q),
+        })
+    } else {
+        let orders = querier.query_spot_market_orderbook(&market.market_id, OrderSide::Buy, None, None)?;
+
+        Ok(SwapBoundsResponse {
+            min_input: market.min_quantity_tick_size,
+            max_input: orders.buys_price_level.iter().fold(FPDecimal::ZERO, |depth, level| depth + level.q),
+        })
+    }
+}
+
+/// Like `estimate_swap_result` from an input quantity, but never fails: when a route or market query errors,
+/// the fees estimated up to the failing step are returned with the error instead.
+pub fn quote_swap(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    sender: Option<Addr>,
+    source_denom: String,
+    target_denom: &str,
+    input_quantity: FPDecimal,
+) -> StdResult<QuoteResponse> {
+    let mut quote = QuoteResponse {
+        result_quantity: None,
+        expected_fees: vec![],
+        error: None,
+        expected_bank_msgs: 0,
+    };
+
+    let route = match find_swap_route(deps, &source_denom, target_denom) {
+        Ok(route) => route,
+        Err(err) => {
+            quote.error = Some(err.to_string());
+            return Ok(quote);
+        }
+    };
+    let steps = route.steps_from(&source_denom);
+    let is_single_hop = steps.len() == 1;
+    let mut current_swap = FPCoin {
+        amount: input_quantity,
+        denom: source_denom.to_owned(),
+    };
+
+    for step in steps {
+        let swap_estimate = match estimate_single_swap_execution(
+            &deps,
+            env,
+            &step,
+            SwapEstimationAmount::InputQuantity(current_swap.clone()),
+            true,
+            is_single_hop,
+        ) {
+            Ok(swap_estimate) => swap_estimate,
+            Err(err) => {
+                quote.error = Some(format!("Estimation failed on market {}: {err}", step.as_str()));
+                return Ok(quote);
+            }
+        };
+
+        quote
+            .expected_fees
+            .push(swap_estimate.fee_estimate.expect("fee estimate should be available"));
+        current_swap = FPCoin {
+            amount: swap_estimate.result_quantity,
+            denom: swap_estimate.result_denom,
+        };
+    }
+
+    // without a sender, the quote is for one that is neither exempt from the fee nor receives any of it
+    let sender = sender.unwrap_or_else(|| env.contract.address.to_owned());
+    let config = CONFIG.load(deps.storage)?.with_route_fee(route.fee_bps);
+    let input: Coin = FPCoin {
+        amount: input_quantity,
+        denom: source_denom,
+    }
+    .into();
+    let mut output: Coin = current_swap.clone().into();
+    let fee = if config.fee_in_output_denom {
+        let fee = Coin::new(config.swap_fee_for(&sender, output.amount), output.denom.to_owned());
+        output.amount = output.amount.saturating_sub(fee.amount);
+        fee
+    } else {
+        Coin::new(config.swap_fee_for(&sender, input.amount), input.denom.to_owned())
+    };
+    quote.expected_bank_msgs = plan_settlement(deps, env, &config, sender, input, fee, output)?.len() as u32;
+
+    quote.result_quantity = Some(current_swap.amount);
+    Ok(quote)
+}
+
+/// Checks whether a swap between the two denoms could be started right now.
+pub fn is_swappable(deps: Deps<InjectiveQueryWrapper>, source_denom: &str, target_denom: &str) -> StdResult<SwappableResponse> {
+    if CONFIG.load(deps.storage)?.paused {
+        return Ok(SwappableResponse::not_swappable("Contract is paused"));
+    }
+
+    let route = match find_swap_route(deps, source_denom, target_denom) {
+        Ok(route) => route,
+        Err(err) => return Ok(SwappableResponse::not_swappable(err.to_string())),
+    };
+    if !route.enabled {
+        return Ok(SwappableResponse::not_swappable("Route is disabled"));
+    }
+
+    let querier = InjectiveQuerier::new(&deps.querier);
+    for market_id in route.steps {
+        if !is_market_active(&querier, &market_id) {
+            return Ok(SwappableResponse::not_swappable(format!("Market {} is not active", market_id.as_str())));
+        }
+    }
+
+    Ok(SwappableResponse::swappable())
+}
+
+/// Whether `sender` may start a swap right now, independent of the pair it would swap.
+pub fn can_swap(deps: Deps<InjectiveQueryWrapper>, env: &Env, sender: &Addr) -> StdResult<SwappableResponse> {
+    if CONFIG.load(deps.storage)?.paused {
+        return Ok(SwappableResponse::not_swappable("Contract is paused"));
+    }
+    if *sender == env.contract.address {
+        return Ok(SwappableResponse::not_swappable("Contract cannot swap on its own behalf"));
+    }
+    if SWAP_OPERATION_STATE.exists(deps.storage) || STEP_STATE.exists(deps.storage) {
+        return Ok(SwappableResponse::not_swappable("Another swap is in flight"));
+    }
+    if LARGE_SWAP_COMMITS.has(deps.storage, sender) {
+        return Ok(SwappableResponse::not_swappable("Sender has a pending large swap commit"));
+    }
+
+    Ok(SwappableResponse::swappable())
+}
+
+/// Routes going through a market that is no longer active, e.g. because it was delisted, and can't be swapped along.
+pub fn get_broken_routes(deps: Deps<InjectiveQueryWrapper>) -> StdResult<Vec<SwapRoute>> {
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let mut broken_routes = vec![];
+    for item in SWAP_ROUTES.range(deps.storage, None, None, Order::Ascending) {
+        let (_, route) = item?;
+        if route.steps.iter().any(|market_id| !is_market_active(&querier, market_id)) {
+            broken_routes.push(route);
+        }
+    }
+    Ok(broken_routes)
+}
+
+fn is_market_active(querier: &InjectiveQuerier, market_id: &MarketId) -> bool {
+    let market = querier.query_spot_market(market_id).ok().and_then(|response| response.market);
+    matches!(market, Some(market) if market.status == MarketStatus::Active)
+}
+
+/// Estimates the output at which swapping `input_quantity` exactly covers the contract and market fees it pays,
+/// i.e. the fee-free output plus all fees, each fee converted into the target denom at fee-free prices.
+pub fn estimate_break_even_output(
+    deps: Deps<InjectiveQueryWrapper>,
+    env: &Env,
+    source_denom: String,
+    target_denom: String,
+    input_quantity: FPDecimal,
+) -> StdResult<FPDecimal> {
+    if input_quantity.is_zero() || input_quantity.is_negative() {
+        return Err(StdError::generic_err("source_quantity must be positive"));
+    }
+
+    let route = find_swap_route(deps, &source_denom, &target_denom)?;
+    let config = CONFIG.load(deps.storage)?.with_route_fee(route.fee_bps);
+
+    let contract_fee = if config.fee_in_output_denom {
+        FPDecimal::ZERO
+    } else {
+        FPDecimal::from(config.swap_fee(input_quantity.into()))
+    };
+    let steps = route.steps_from(&source_denom);
+    // same estimates as `estimate_swap_result`
+    let is_single_hop = steps.len() == 1;
+    let mut current_swap = FPCoin {
+        amount: input_quantity - contract_fee,
+        denom: source_denom,
+    };
+    // fees paid so far, expressed in the denom of current_swap
+    let mut fees = contract_fee;
+
+    for step in steps {
+        let swap_estimate = estimate_single_swap_execution(
+            &deps,
+            env,
+            &step,
+            SwapEstimationAmount::InputQuantity(current_swap.clone()),
+            true,
+            is_single_hop,
+        )?;
+        let step_fee = swap_estimate.fee_estimate.expect("fee estimate should be available").amount;
+
+        // buys pay the market fee in the input denom, sells in the output denom
+        let (fee_in_input, fee_in_output) = if swap_estimate.is_buy_order {
+            (step_fee, FPDecimal::ZERO)
+        } else {
+            (FPDecimal::ZERO, step_fee)
+        };
+        let fee_free_rate = (swap_estimate.result_quantity + fee_in_output) / (current_swap.amount - fee_in_input);
+        fees = (fees + fee_in_input) * fee_free_rate + fee_in_output;
+
+        current_swap = FPCoin {
+            amount: swap_estimate.result_quantity,
+            denom: swap_estimate.result_denom,
+        };
+    }
+
+    if config.fee_in_output_denom {
+        let output_fee = FPDecimal::from(config.swap_fee(current_swap.amount.into()));
+        current_swap.amount -= output_fee;
+        fees += output_fee;
+    }
+
+    let fee_free_output = current_swap.amount + fees;
+    Ok(fee_free_output + fees)
+}
+
 pub fn estimate_single_swap_execution(
     deps: &Deps<InjectiveQueryWrapper>,
     env: &Env,
     market_id: &MarketId,
     swap_estimation_amount: SwapEstimationAmount,
     is_simulation: bool,
+    use_integer_math: bool,
 ) -> StdResult<StepExecutionEstimate> {
     let querier = InjectiveQuerier::new(&deps.querier);
 
@@ -97,7 +818,10 @@ pub fn estimate_single_swap_execution(
         SwapEstimationAmount::ReceiveQuantity(fp) => fp,
     };
 
-    let market = querier.query_spot_market(market_id)?.market.expect("market should be available");
+    let market = querier
+        .query_spot_market(market_id)?
+        .market
+        .ok_or_else(|| StdError::generic_err(format!("Market {} not found", market_id.as_str())))?;
 
     let has_invalid_denom = balance_in.denom != market.quote_denom && balance_in.denom != market.base_denom;
     if has_invalid_denom {
@@ -113,10 +837,11 @@ pub fn estimate_single_swap_execution(
 
     let is_estimating_from_target = matches!(swap_estimation_amount, SwapEstimationAmount::ReceiveQuantity(_));
 
+    // when estimating from the target, the step buys if it has to deliver the base denom
     let is_buy = if is_estimating_from_target {
         balance_in.denom == market.base_denom
     } else {
-        balance_in.denom != market.base_denom
+        is_buy_step(&market, &balance_in.denom)
     };
 
     if is_buy {
@@ -130,7 +855,7 @@ pub fn estimate_single_swap_execution(
             is_simulation,
         )
     } else {
-        estimate_execution_sell(deps, &querier, &market, swap_estimation_amount, fee_percent)
+        estimate_execution_sell(deps, &querier, &market, swap_estimation_amount, fee_percent, use_integer_math)
     }
 }
 
@@ -164,12 +889,7 @@ fn estimate_execution_buy_from_source(
 
     // check if user funds + contract funds are enough to create order
     let required_funds = worst_price * expected_base_quantity * (FPDecimal::ONE + fee_percent);
-    let funds_in_contract = deps
-        .querier
-        .query_balance(contract_address, &market.quote_denom)
-        .expect("query own balance should not fail")
-        .amount
-        .into();
+    let funds_in_contract = deps.querier.query_balance(contract_address, &market.quote_denom)?.amount.into();
 
     let funds_for_margin = match is_simulation {
         false => funds_in_contract, // in execution mode funds_in_contract already contain user funds so we don't want to count them double
@@ -225,12 +945,7 @@ fn estimate_execution_buy_from_target(
     // check if user funds + contract funds are enough to create order
     let required_funds = worst_price * rounded_target_base_output_quantity * (FPDecimal::ONE + fee_percent);
 
-    let funds_in_contract = deps
-        .querier
-        .query_balance(contract_address, &market.quote_denom)
-        .expect("query own balance should not fail")
-        .amount
-        .into();
+    let funds_in_contract = deps.querier.query_balance(contract_address, &market.quote_denom)?.amount.into();
 
     let funds_for_margin = match is_simulation {
         false => funds_in_contract, // in execution mode funds_in_contract already contain user funds so we don't want to count them double
@@ -284,6 +999,7 @@ fn estimate_execution_sell_from_source(
     market: &SpotMarket,
     input_base_quantity: FPDecimal,
     fee_percent: FPDecimal,
+    use_integer_math: bool,
 ) -> StdResult<StepExecutionEstimate> {
     let orders = querier.query_spot_market_orderbook(&market.market_id, OrderSide::Buy, Some(input_base_quantity), None)?;
 
@@ -299,9 +1015,20 @@ fn estimate_execution_sell_from_source(
     let average_price = get_average_price_from_orders(&top_orders, market.min_price_tick_size, false);
     let worst_price = get_worst_price_from_orders(&top_orders);
 
-    let expected_exchange_quantity = input_base_quantity * average_price;
-    let fee_estimate = expected_exchange_quantity * fee_percent;
-    let expected_quantity = expected_exchange_quantity - fee_estimate;
+    let integer_estimate = if use_integer_math {
+        integer_sell_output(input_base_quantity, average_price, fee_percent)
+    } else {
+        None
+    };
+
+    let (expected_quantity, fee_estimate) = match integer_estimate {
+        Some((output, fee)) => (FPDecimal::from(output), FPDecimal::from(fee)),
+        None => {
+            let expected_exchange_quantity = input_base_quantity * average_price;
+            let fee_estimate = expected_exchange_quantity * fee_percent;
+            (expected_exchange_quantity - fee_estimate, fee_estimate)
+        }
+    };
 
     Ok(StepExecutionEstimate {
         worst_price,
@@ -358,6 +1085,7 @@ fn estimate_execution_sell(
     market: &SpotMarket,
     swap_estimation_amount: SwapEstimationAmount,
     fee_percent: FPDecimal,
+    use_integer_math: bool,
 ) -> StdResult<StepExecutionEstimate> {
     let amount_coin = match swap_estimation_amount.to_owned() {
         SwapEstimationAmount::InputQuantity(fp) => fp,
@@ -369,7 +1097,7 @@ fn estimate_execution_sell(
     if is_estimating_from_target {
         estimate_execution_sell_from_target(deps, querier, market, amount_coin.amount, fee_percent)
     } else {
-        estimate_execution_sell_from_source(deps, querier, market, amount_coin.amount, fee_percent)
+        estimate_execution_sell_from_source(deps, querier, market, amount_coin.amount, fee_percent, use_integer_math)
     }
 }
 
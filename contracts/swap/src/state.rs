@@ -1,20 +1,129 @@
-use crate::types::{Config, CurrentSwapOperation, CurrentSwapStep, SwapResults, SwapRoute};
+use crate::helpers::{calculate_fee, floor_to_uint, validate_bps};
+use crate::types::{
+    AccountingResponse, Config, CurrentSwapOperation, CurrentSwapStep, DebugStateResponse, DenomAccounting, LargeSwapCommit, LimitsResponse,
+    SwapResults, SwapRoute,
+};
+use crate::ContractError;
+use std::collections::BTreeMap;
 
-use cosmwasm_std::{Order, StdError, StdResult, Storage};
+use cosmwasm_std::{ensure, Addr, Coin, Order, StdError, StdResult, Storage, Timestamp, Uint128};
 use cw_storage_plus::{Bound, Item, Map};
+use injective_cosmwasm::MarketId;
+use injective_math::FPDecimal;
 
 pub const SWAP_ROUTES: Map<(String, String), SwapRoute> = Map::new("swap_routes");
 pub const SWAP_OPERATION_STATE: Item<CurrentSwapOperation> = Item::new("current_swap_cache");
 pub const STEP_STATE: Item<CurrentSwapStep> = Item::new("current_step_cache");
 pub const SWAP_RESULTS: Item<Vec<SwapResults>> = Item::new("swap_results");
 pub const CONFIG: Item<Config> = Item::new("config");
+pub const SWAP_VOLUMES: Map<String, Uint128> = Map::new("swap_volumes");
+pub const ACCOUNTING: Map<String, DenomAccounting> = Map::new("accounting");
+pub const LARGE_SWAP_COMMITS: Map<&Addr, LargeSwapCommit> = Map::new("large_swap_commits");
+pub const USER_SWAP_COUNTS: Map<&Addr, u64> = Map::new("user_swap_counts");
+// CW20 adapters of the bank denoms delivered as their CW20 token
+pub const CW20_OUTPUT_ADAPTERS: Map<String, Addr> = Map::new("cw20_output_adapters");
+// amounts per denom the contract keeps for itself, settlement never draws its balance below them
+pub const RESERVES: Map<String, Uint128> = Map::new("reserves");
+// largest input of a denom swapped directly, larger ones have to be committed and executed in a later block
+pub const LARGE_SWAP_THRESHOLDS: Map<String, Uint128> = Map::new("large_swap_thresholds");
+// fractions of outputs below one unit, kept by the contract when the output was rounded down for delivery
+pub const TRUNCATED_OUTPUTS: Map<String, FPDecimal> = Map::new("truncated_outputs");
+// outputs held back by the output timelock, by recipient and the time in seconds they can be claimed from
+pub const OUTPUT_CLAIMS: Map<(&Addr, u64), Coin> = Map::new("output_claims");
+// treasuries receiving the contract fees charged in a denom instead of the configured fee recipient
+pub const FEE_RECIPIENTS_BY_DENOM: Map<String, Addr> = Map::new("fee_recipients_by_denom");
+// trading fees paid on the fills of settled swaps per market, in units of the market's quote denom
+pub const MARKET_FEES: Map<String, Uint128> = Map::new("market_fees");
 
 pub const DEFAULT_LIMIT: u32 = 100u32;
+pub const MAX_BPS: u16 = 10_000u16;
+// committed large swaps refunded per call of the emergency refund
+pub const REFUND_ALL_BATCH_SIZE: usize = 50;
+pub const MAX_MEMO_LENGTH: usize = 256;
+pub const MAX_HOP_RETRIES: u8 = 1;
+// codespace and code of the exchange module's "slippage exceeds worst price" error, which a failed hop is retried on
+pub const EXCHANGE_CODESPACE: &str = "exchange";
+pub const SLIPPAGE_EXCEEDS_WORST_PRICE_CODE: u32 = 25;
+pub const MAX_SWAP_SLICES: u8 = 10;
 
 impl Config {
-    pub fn validate(self) -> StdResult<()> {
+    pub fn validate(self) -> Result<(), ContractError> {
+        validate_bps("swap_fee_bps", self.swap_fee_bps)?;
+        if let Some(max_allowed_slippage_bps) = self.max_allowed_slippage_bps {
+            validate_bps("max_allowed_slippage_bps", max_allowed_slippage_bps)?;
+        }
+        validate_bps("fee_burn_bps", self.fee_burn_bps)?;
+        validate_bps("hop_retry_price_band_bps", self.hop_retry_price_band_bps)?;
+        validate_bps("high_impact_threshold_bps", self.high_impact_threshold_bps)?;
+        validate_bps("positive_slippage_bps", self.positive_slippage_bps)?;
+        validate_bps("exact_output_tolerance_bps", self.exact_output_tolerance_bps)?;
+        // a band or tolerance of the whole price would allow any price at all
+        ensure!(
+            self.hop_retry_price_band_bps < MAX_BPS,
+            StdError::generic_err(format!("hop_retry_price_band_bps must be below {MAX_BPS}"))
+        );
+        ensure!(
+            self.exact_output_tolerance_bps < MAX_BPS,
+            StdError::generic_err(format!("exact_output_tolerance_bps must be below {MAX_BPS}"))
+        );
+        ensure!(
+            self.max_fee.map_or(true, |max_fee| self.min_fee <= max_fee),
+            StdError::generic_err("min_fee must not exceed max_fee")
+        );
         Ok(())
     }
+
+    /// Contract fee charged on `amount`: `swap_fee_bps` of it clamped to `min_fee` and `max_fee`,
+    /// but never more than `amount` itself. The bps share is rounded down, so without a `min_fee`
+    /// swaps too small for it to reach one unit are not charged at all.
+    pub fn swap_fee(&self, amount: Uint128) -> Uint128 {
+        let fee = calculate_fee(amount, self.swap_fee_bps).max(self.min_fee);
+        let fee = self.max_fee.map_or(fee, |max_fee| fee.min(max_fee));
+        fee.min(amount)
+    }
+
+    /// Config with `swap_fee_bps` replaced by the fee of the route a swap goes along, if it has one.
+    /// `min_fee` and `max_fee` still apply.
+    pub fn with_route_fee(self, route_fee_bps: Option<u16>) -> Self {
+        Config {
+            swap_fee_bps: route_fee_bps.unwrap_or(self.swap_fee_bps),
+            ..self
+        }
+    }
+
+    /// Recipients of the contract fee `fee`: the `fee_burn_bps` share goes to the burn address if one is set,
+    /// the rest to `fee_recipient` unless that is the contract itself. Empty shares are left out.
+    pub fn fee_sends(&self, fee: &Coin, fee_recipient: &Addr, contract_address: &Addr) -> Vec<(Addr, Coin)> {
+        let mut sends = vec![];
+        let mut remainder = fee.amount;
+        if let Some(burn_address) = &self.burn_address {
+            let burned = calculate_fee(fee.amount, self.fee_burn_bps);
+            remainder -= burned;
+            if !burned.is_zero() {
+                sends.push((burn_address.to_owned(), Coin::new(burned, fee.denom.to_owned())));
+            }
+        }
+        if !remainder.is_zero() && fee_recipient != contract_address {
+            sends.push((fee_recipient.to_owned(), Coin::new(remainder, fee.denom.to_owned())));
+        }
+        sends
+    }
+
+    /// Contract fee charged on a swap of `amount` by `sender`, nothing for the admin's own swaps when they are exempt.
+    pub fn swap_fee_for(&self, sender: &Addr, amount: Uint128) -> Uint128 {
+        if self.admin_fee_exempt && *sender == self.admin {
+            Uint128::zero()
+        } else {
+            self.swap_fee(amount)
+        }
+    }
+}
+
+/// Recipient of the contract fees charged in `denom`: its override if one is set, the configured fee recipient otherwise.
+pub fn fee_recipient_for(storage: &dyn Storage, config: &Config, denom: &str) -> StdResult<Addr> {
+    Ok(FEE_RECIPIENTS_BY_DENOM
+        .may_load(storage, denom.to_string())?
+        .unwrap_or_else(|| config.fee_recipient.to_owned()))
 }
 
 pub fn store_swap_route(storage: &mut dyn Storage, route: &SwapRoute) -> StdResult<()> {
@@ -48,6 +157,221 @@ pub fn get_all_swap_routes(storage: &dyn Storage, start_after: Option<(String, S
     Ok(routes)
 }
 
+pub fn get_swap_routes_by_market(storage: &dyn Storage, market_id: &MarketId) -> StdResult<Vec<SwapRoute>> {
+    SWAP_ROUTES
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, route)| route))
+        .filter(|route| route.as_ref().map_or(true, |r| r.steps.contains(market_id)))
+        .collect::<StdResult<Vec<SwapRoute>>>()
+}
+
+pub fn get_route_market_ids(storage: &dyn Storage) -> StdResult<Vec<MarketId>> {
+    let mut market_ids: Vec<MarketId> = vec![];
+    for route in SWAP_ROUTES.range(storage, None, None, Order::Ascending) {
+        for market_id in route?.1.steps {
+            if !market_ids.contains(&market_id) {
+                market_ids.push(market_id);
+            }
+        }
+    }
+    Ok(market_ids)
+}
+
+pub fn get_source_denoms(storage: &dyn Storage) -> StdResult<Vec<String>> {
+    let mut denoms: Vec<String> = vec![];
+    for route in SWAP_ROUTES.range(storage, None, None, Order::Ascending) {
+        let source_denom = route?.1.source_denom;
+        if !denoms.contains(&source_denom) {
+            denoms.push(source_denom);
+        }
+    }
+    Ok(denoms)
+}
+
+/// Returns the denoms `source_denom` can be swapped into. Routes can be traversed both ways, so the
+/// source of a route is reachable from its target too.
+pub fn get_reachable_targets(storage: &dyn Storage, source_denom: &str) -> StdResult<Vec<String>> {
+    let mut targets: Vec<String> = vec![];
+    for route in SWAP_ROUTES.range(storage, None, None, Order::Ascending) {
+        let route = route?.1;
+        let target = if route.source_denom == source_denom {
+            route.target_denom
+        } else if route.target_denom == source_denom {
+            route.source_denom
+        } else {
+            continue;
+        };
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// Returns the routes that can produce `target_denom`, turned around where needed so that each one leads from
+/// its `source_denom` to `target_denom`.
+pub fn get_routes_to_target(storage: &dyn Storage, target_denom: &str) -> StdResult<Vec<SwapRoute>> {
+    let mut routes: Vec<SwapRoute> = vec![];
+    for route in SWAP_ROUTES.range(storage, None, None, Order::Ascending) {
+        let route = route?.1;
+        let source_denom = if route.target_denom == target_denom {
+            route.source_denom.to_owned()
+        } else if route.source_denom == target_denom {
+            route.target_denom.to_owned()
+        } else {
+            continue;
+        };
+        routes.push(SwapRoute {
+            steps: route.steps_from(&source_denom),
+            source_denom,
+            target_denom: target_denom.to_string(),
+            enabled: route.enabled,
+            fee_bps: route.fee_bps,
+        });
+    }
+    Ok(routes)
+}
+
+/// Returns the in-flight swap if it was started more than `older_than_seconds` before `now`. Swaps
+/// share a single cache, so there is at most one.
+pub fn get_stale_swaps(storage: &dyn Storage, now: Timestamp, older_than_seconds: u64) -> StdResult<Vec<CurrentSwapOperation>> {
+    let operation = SWAP_OPERATION_STATE.may_load(storage)?;
+    Ok(operation
+        .into_iter()
+        .filter(|operation| now.seconds().saturating_sub(operation.started_at.seconds()) > older_than_seconds)
+        .collect())
+}
+
+/// Returns the in-flight swap caches, restricted to the swap of `sender` if given. Anyone can read them, as any
+/// contract state, so nothing sensitive may be cached there.
+pub fn get_debug_state(storage: &dyn Storage, sender: Option<Addr>) -> StdResult<DebugStateResponse> {
+    let operation = SWAP_OPERATION_STATE.may_load(storage)?;
+    let is_other_sender = match (&operation, &sender) {
+        (Some(operation), Some(sender)) => operation.sender_address != *sender,
+        _ => false,
+    };
+    if is_other_sender {
+        return Ok(DebugStateResponse {
+            operation: None,
+            step: None,
+            results: None,
+        });
+    }
+
+    Ok(DebugStateResponse {
+        operation,
+        step: STEP_STATE.may_load(storage)?,
+        results: SWAP_RESULTS.may_load(storage)?,
+    })
+}
+
+pub fn get_limits(storage: &dyn Storage) -> StdResult<LimitsResponse> {
+    let config = CONFIG.load(storage)?;
+    Ok(LimitsResponse {
+        paused: config.paused,
+        swap_fee_bps: config.swap_fee_bps,
+        min_fee: config.min_fee,
+        max_fee: config.max_fee,
+        max_allowed_slippage_bps: config.max_allowed_slippage_bps,
+        max_inputs: LARGE_SWAP_THRESHOLDS
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(denom, threshold)| Coin::new(threshold, denom)))
+            .collect::<StdResult<Vec<Coin>>>()?,
+        max_swap_slices: MAX_SWAP_SLICES,
+        max_memo_length: MAX_MEMO_LENGTH,
+    })
+}
+
+pub fn increase_swap_volume(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    SWAP_VOLUMES.update(storage, denom.to_string(), |volume| -> StdResult<_> {
+        Ok(volume.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+pub fn get_swap_volume(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    Ok(SWAP_VOLUMES.may_load(storage, denom.to_string())?.unwrap_or_default())
+}
+
+/// Adds the trading fee of each fill of a settled swap to the fees of its market, rounded down to whole units.
+pub fn record_market_fees(storage: &mut dyn Storage, swap_results: &[SwapResults]) -> StdResult<()> {
+    for result in swap_results {
+        let fee = floor_to_uint(result.fee);
+        if fee.is_zero() {
+            continue;
+        }
+        MARKET_FEES.update(storage, result.market_id.as_str().to_string(), |fees| -> StdResult<_> {
+            Ok(fees.unwrap_or_default().checked_add(fee)?)
+        })?;
+    }
+    Ok(())
+}
+
+pub fn get_market_fees(storage: &dyn Storage, market_id: &MarketId) -> StdResult<Uint128> {
+    Ok(MARKET_FEES.may_load(storage, market_id.as_str().to_string())?.unwrap_or_default())
+}
+
+pub fn increase_user_swap_count(storage: &mut dyn Storage, sender: &Addr) -> StdResult<()> {
+    USER_SWAP_COUNTS.update(storage, sender, |count| -> StdResult<_> { Ok(count.unwrap_or_default() + 1) })?;
+    Ok(())
+}
+
+pub fn get_user_swap_count(storage: &dyn Storage, sender: &Addr) -> StdResult<u64> {
+    Ok(USER_SWAP_COUNTS.may_load(storage, sender)?.unwrap_or_default())
+}
+
+pub fn credit_truncated_output(storage: &mut dyn Storage, denom: &str, fraction: FPDecimal) -> StdResult<()> {
+    if fraction.is_zero() {
+        return Ok(());
+    }
+    TRUNCATED_OUTPUTS.update(storage, denom.to_string(), |credited| -> StdResult<_> {
+        Ok(credited.unwrap_or(FPDecimal::ZERO) + fraction)
+    })?;
+    Ok(())
+}
+
+/// Adds a completed swap of `input_denom` and the fee and refund it settled with to the accounting.
+pub fn record_swap_accounting(storage: &mut dyn Storage, input_denom: &str, fee: &Coin, refund: &Coin) -> StdResult<()> {
+    update_accounting(storage, input_denom, |accounting| {
+        accounting.completed_swaps += 1;
+        Ok(())
+    })?;
+    if !fee.amount.is_zero() {
+        update_accounting(storage, &fee.denom, |accounting| {
+            accounting.fees_collected = accounting.fees_collected.checked_add(fee.amount)?;
+            Ok(())
+        })?;
+    }
+    if !refund.amount.is_zero() {
+        update_accounting(storage, &refund.denom, |accounting| {
+            accounting.refunded = accounting.refunded.checked_add(refund.amount)?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn update_accounting(storage: &mut dyn Storage, denom: &str, update: impl FnOnce(&mut DenomAccounting) -> StdResult<()>) -> StdResult<()> {
+    let mut accounting = ACCOUNTING.may_load(storage, denom.to_string())?.unwrap_or_default();
+    update(&mut accounting)?;
+    ACCOUNTING.save(storage, denom.to_string(), &accounting)
+}
+
+/// Per denom accounting of all completed swaps, including their swapped volume.
+pub fn get_accounting(storage: &dyn Storage) -> StdResult<AccountingResponse> {
+    let mut denoms = ACCOUNTING
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<BTreeMap<String, DenomAccounting>>>()?;
+    for item in SWAP_VOLUMES.range(storage, None, None, Order::Ascending) {
+        let (denom, volume) = item?;
+        denoms.entry(denom).or_default().volume = volume;
+    }
+
+    Ok(AccountingResponse {
+        denoms: denoms.into_iter().collect(),
+    })
+}
+
 pub fn remove_swap_route(storage: &mut dyn Storage, source_denom: &str, target_denom: &str) {
     let key = route_key(source_denom, target_denom);
     SWAP_ROUTES.remove(storage, key)
@@ -60,3 +384,21 @@ fn route_key<'a>(source_denom: &'a str, target_denom: &'a str) -> (String, Strin
         (target_denom.to_string(), source_denom.to_string())
     }
 }
+
+/// Holds back `output` for `recipient` until `claimable_at`. Claims maturing at the same time are merged when in the
+/// same denom, otherwise the later one matures a second later.
+pub fn lock_output(storage: &mut dyn Storage, recipient: &Addr, mut claimable_at: u64, output: Coin) -> StdResult<()> {
+    if output.amount.is_zero() {
+        return Ok(());
+    }
+    loop {
+        match OUTPUT_CLAIMS.may_load(storage, (recipient, claimable_at))? {
+            Some(mut claim) if claim.denom == output.denom => {
+                claim.amount += output.amount;
+                return OUTPUT_CLAIMS.save(storage, (recipient, claimable_at), &claim);
+            }
+            Some(_) => claimable_at += 1,
+            None => return OUTPUT_CLAIMS.save(storage, (recipient, claimable_at), &output),
+        }
+    }
+}
@@ -1,33 +1,106 @@
 use crate::{
+    admin::{validate_address, validate_route, verify_sender_is_admin},
     contract::ATOMIC_ORDER_REPLY_ID,
     error::ContractError,
-    helpers::{dec_scale_factor, round_up_to_min_tick},
-    queries::{estimate_single_swap_execution, estimate_swap_result, SwapQuantity},
-    state::{read_swap_route, CONFIG, STEP_STATE, SWAP_OPERATION_STATE, SWAP_RESULTS},
-    types::{CurrentSwapOperation, CurrentSwapStep, FPCoin, SwapEstimationAmount, SwapQuantityMode, SwapResults},
+    helpers::{compute_refund, dec_scale_factor, floor_to_uint, is_buy_step, round_up_to_min_tick},
+    queries::{
+        estimate_price_impact, estimate_required_input, estimate_single_swap_execution, estimate_steps_output, find_swap_route, get_route_denoms,
+    },
+    state::{
+        credit_truncated_output, fee_recipient_for, get_reachable_targets, increase_swap_volume, increase_user_swap_count, lock_output,
+        record_market_fees, record_swap_accounting, CONFIG, CW20_OUTPUT_ADAPTERS, EXCHANGE_CODESPACE, LARGE_SWAP_COMMITS, LARGE_SWAP_THRESHOLDS,
+        MAX_BPS, MAX_HOP_RETRIES, MAX_MEMO_LENGTH, MAX_SWAP_SLICES, OUTPUT_CLAIMS, REFUND_ALL_BATCH_SIZE, RESERVES,
+        SLIPPAGE_EXCEEDS_WORST_PRICE_CODE, STEP_STATE, SWAP_OPERATION_STATE, SWAP_RESULTS,
+    },
+    types::{
+        Config, CurrentSwapOperation, CurrentSwapStep, Cw20AdapterMsg, DustPolicy, ExactOutputInput, FPCoin, LargeSwapCommit, MinOutputValue,
+        OrderFill, OverDeliveryPolicy, PortfolioLeg, PortfolioTarget, SwapEstimationAmount, SwapQuantityMode, SwapResults, SwapRoute, SwapSlices,
+    },
 };
 
-use cosmwasm_std::{BankMsg, Coin, DepsMut, Env, Event, MessageInfo, Reply, Response, StdResult, SubMsg};
+use cosmwasm_std::{
+    ensure, to_json_binary, Addr, BankMsg, Coin, Coins, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response, StdError,
+    StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
+};
+use cw_storage_plus::Bound;
 use injective_cosmwasm::{
     create_spot_market_order_msg, get_default_subaccount_id_for_checked_address, InjectiveMsgWrapper, InjectiveQuerier, InjectiveQueryWrapper,
-    OrderType, SpotOrder,
+    MarketId, OrderType, SpotOrder,
 };
 use injective_math::{round_to_min_tick, FPDecimal};
 use injective_std::types::injective::exchange::v1beta1::MsgCreateSpotMarketOrderResponse;
 use prost::Message;
 use std::str::FromStr;
 
+/// Optional per-swap settings of `start_swap_flow`.
+#[derive(Default)]
+pub struct SwapOptions {
+    pub auto_convert_to: Option<String>,
+    pub max_input: Option<Uint128>,
+    pub compound_refund: bool,
+    pub memo: Option<String>,
+    pub input_fraction_bps: Option<u16>,
+    pub recipients: Vec<(Addr, u16)>,
+    pub min_output_value: Option<MinOutputValue>,
+    pub custom_route: Option<Vec<MarketId>>,
+    // set for swaps executed from a large swap commit, which are exempt from the large swap threshold
+    pub committed: bool,
+    // number of sub-orders the input of a min output swap is split into
+    pub slices: Option<u8>,
+    // parts of a portfolio swap to be swapped after this one
+    pub portfolio: Vec<PortfolioLeg>,
+    // set for the parts of a portfolio swap, whose whole input was held to the large swap threshold instead
+    pub portfolio_leg: bool,
+    // lets the swap go through a price impact above the configured threshold
+    pub acknowledge_high_impact: bool,
+}
+
 pub fn start_swap_flow(
     deps: DepsMut<InjectiveQueryWrapper>,
     env: Env,
     info: MessageInfo,
     target_denom: String,
     swap_quantity_mode: SwapQuantityMode,
+    options: SwapOptions,
 ) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
-    if info.funds.len() != 1 {
-        return Err(ContractError::CustomError {
-            val: "Only one denom can be passed in funds".to_string(),
-        });
+    let config = CONFIG.load(deps.storage)?;
+    ensure!(!config.paused, ContractError::Paused {});
+    ensure!(info.sender != env.contract.address, ContractError::SelfCall {});
+    ensure_no_swap_in_flight(deps.storage)?;
+    // a committed swap is removed before it is started, so this only stops swapping next to a pending commit
+    ensure!(
+        !LARGE_SWAP_COMMITS.has(deps.storage, &info.sender),
+        ContractError::PendingLargeSwapCommit {}
+    );
+    if let Some(memo) = &options.memo {
+        ensure!(
+            memo.len() <= MAX_MEMO_LENGTH,
+            ContractError::MemoTooLong {
+                length: memo.len(),
+                max_length: MAX_MEMO_LENGTH,
+            }
+        );
+    }
+    if !options.recipients.is_empty() {
+        let total_weight: u32 = options.recipients.iter().map(|(_, weight_bps)| u32::from(*weight_bps)).sum();
+        ensure!(
+            total_weight == u32::from(MAX_BPS),
+            ContractError::InvalidRecipientWeights { total_bps: total_weight }
+        );
+        for (recipient, _) in &options.recipients {
+            validate_address(deps.as_ref(), "recipients", recipient)?;
+        }
+    }
+    if let Some(fraction_bps) = options.input_fraction_bps {
+        ensure!(
+            fraction_bps > 0 && fraction_bps <= MAX_BPS,
+            ContractError::InvalidInputFraction { fraction_bps }
+        );
+    }
+
+    let (input_funds, ignored_funds) = select_input_funds(deps.as_ref(), config.strict_funds, &info.funds, &target_denom)?;
+    if !options.committed && !options.portfolio_leg {
+        ensure_below_large_swap_threshold(deps.storage, &input_funds)?;
     }
     let quantity = match swap_quantity_mode {
         SwapQuantityMode::MinOutputQuantity(q) => q,
@@ -40,42 +113,94 @@ pub fn start_swap_flow(
         });
     }
 
-    let source_denom = &info.funds[0].denom;
-    let route = read_swap_route(deps.storage, source_denom, &target_denom)?;
-    let steps = route.steps_from(source_denom);
+    if let Some(slices) = options.slices {
+        ensure!(
+            slices > 0 && slices <= MAX_SWAP_SLICES,
+            ContractError::InvalidSlices {
+                slices,
+                max_slices: MAX_SWAP_SLICES,
+            }
+        );
+    }
+
+    let source_denom = &input_funds.denom;
+    let output_denom = options.auto_convert_to.clone().unwrap_or_else(|| target_denom.to_owned());
+    let route = match options.custom_route {
+        // inline routes skip the lookup, but are held to the same rules as routes set by the admin
+        Some(custom_steps) => {
+            ensure!(config.allow_custom_routes, ContractError::CustomRoutesDisabled {});
+            let route = SwapRoute {
+                steps: custom_steps,
+                source_denom: source_denom.to_owned(),
+                target_denom: target_denom.to_owned(),
+                enabled: true,
+                fee_bps: None,
+            };
+            validate_route(deps.as_ref(), &route)?;
+            route
+        }
+        None => match find_swap_route(deps.as_ref(), source_denom, &target_denom) {
+            Ok(route) => route,
+            Err(err) => {
+                // the target may be routed from other denoms, which the sender most likely meant to attach
+                let expected = get_reachable_targets(deps.storage, &target_denom)?;
+                ensure!(
+                    expected.is_empty(),
+                    ContractError::WrongInputDenom {
+                        expected: expected.join(","),
+                        got: source_denom.to_owned(),
+                    }
+                );
+                return Err(err.into());
+            }
+        },
+    };
+    ensure_route_executable(&route)?;
+    let config = config.with_route_fee(route.fee_bps);
+    let mut steps = route.steps_from(source_denom);
+
+    if let Some(preferred_denom) = options.auto_convert_to.filter(|denom| *denom != target_denom) {
+        let conversion_route = find_swap_route(deps.as_ref(), &target_denom, &preferred_denom)?;
+        ensure_route_executable(&conversion_route)?;
+        steps.extend(conversion_route.steps_from(&target_denom));
+    }
+
+    let (swap_quantity_mode, quantity) = match swap_quantity_mode {
+        SwapQuantityMode::ExactOutputQuantity(requested) => {
+            let aligned = align_exact_output_quantity(deps.as_ref(), &steps, &target_denom, requested, &config)?;
+            (SwapQuantityMode::ExactOutputQuantity(aligned), aligned)
+        }
+        min_output_mode => (min_output_mode, quantity),
+    };
 
     let sender_address = info.sender;
-    let coin_provided = &info.funds[0];
+    let coin_provided = &input_funds;
 
     let mut current_balance = coin_provided.to_owned().into();
 
-    let refund_amount = if matches!(swap_quantity_mode, SwapQuantityMode::ExactOutputQuantity(..)) {
+    let (refund_amount, fee_amount, exact_output_input) = if matches!(swap_quantity_mode, SwapQuantityMode::ExactOutputQuantity(..)) {
         let target_output_quantity = quantity;
 
-        let estimation = estimate_swap_result(
-            deps.as_ref(),
-            &env,
-            source_denom.to_owned(),
-            target_denom,
-            SwapQuantity::OutputQuantity(target_output_quantity),
-        )?;
-
-        let querier = InjectiveQuerier::new(&deps.querier);
-        let first_market_id = steps[0].to_owned();
-        let first_market = querier.query_spot_market(&first_market_id)?.market.expect("market should be available");
+        let (estimation, required_input) = estimate_required_input(deps.as_ref(), &env, source_denom, &target_denom, target_output_quantity)?;
 
-        let is_input_quote = first_market.quote_denom == *source_denom;
+        // contract fee is charged on top of the required input
+        let fee_amount = config.swap_fee_for(&sender_address, required_input.into());
+        let required_funds = required_input + FPDecimal::from(fee_amount);
 
-        let required_input = if is_input_quote {
-            estimation.result_quantity.int() + FPDecimal::ONE
-        } else {
-            round_up_to_min_tick(estimation.result_quantity, first_market.min_quantity_tick_size)
-        };
+        if let Some(max_input) = options.max_input {
+            ensure!(
+                required_funds <= FPDecimal::from(max_input),
+                ContractError::InputExceedsMax {
+                    required: required_funds,
+                    max: max_input,
+                }
+            );
+        }
 
         let fp_coins: FPDecimal = coin_provided.amount.into();
 
-        if required_input > fp_coins {
-            return Err(ContractError::InsufficientFundsProvided(fp_coins, required_input));
+        if required_funds > fp_coins {
+            return Err(ContractError::InsufficientFundsProvided(fp_coins, required_funds));
         }
 
         current_balance = FPCoin {
@@ -83,23 +208,253 @@ pub fn start_swap_flow(
             denom: source_denom.to_owned(),
         };
 
-        FPDecimal::from(coin_provided.amount) - estimation.result_quantity
+        (
+            compute_refund(coin_provided.amount, required_funds),
+            fee_amount,
+            Some(ExactOutputInput {
+                estimated: estimation.result_quantity,
+                required: required_input,
+            }),
+        )
     } else {
-        FPDecimal::ZERO
+        // only the requested fraction of the input is swapped, the rest is refunded on finalize
+        let swapped_amount = options.input_fraction_bps.map_or(coin_provided.amount, |fraction_bps| {
+            coin_provided.amount.multiply_ratio(fraction_bps, MAX_BPS)
+        });
+
+        // contract fee is deducted from the swapped input, unless it is taken from the output on finalize
+        let fee_amount = if config.fee_in_output_denom {
+            Uint128::zero()
+        } else {
+            config.swap_fee_for(&sender_address, swapped_amount)
+        };
+
+        current_balance = FPCoin {
+            amount: FPDecimal::from(swapped_amount - fee_amount),
+            denom: source_denom.to_owned(),
+        };
+
+        (compute_refund(coin_provided.amount, FPDecimal::from(swapped_amount)), fee_amount, None)
+    };
+
+    // the estimate is only needed to bound the slippage or to share an execution better than it
+    let estimated_output = match &swap_quantity_mode {
+        SwapQuantityMode::MinOutputQuantity(min_output_quantity) if config.max_allowed_slippage_bps.is_some() || config.positive_slippage_bps > 0 => {
+            let estimated_output = estimate_steps_output(deps.as_ref(), &env, &steps, current_balance.to_owned())?.amount;
+            if let Some(max_slippage_bps) = config.max_allowed_slippage_bps {
+                verify_slippage(estimated_output, *min_output_quantity, max_slippage_bps)?;
+            }
+            Some(estimated_output)
+        }
+        _ => None,
+    };
+
+    if config.high_impact_threshold_bps > 0 && !options.acknowledge_high_impact {
+        let impact_bps = estimate_price_impact(deps.as_ref(), &env, &steps, current_balance.to_owned())? * FPDecimal::from(u128::from(MAX_BPS));
+        ensure!(
+            impact_bps <= FPDecimal::from(u128::from(config.high_impact_threshold_bps)),
+            ContractError::HighImpactNotAcknowledged {
+                impact_bps,
+                threshold_bps: config.high_impact_threshold_bps,
+            }
+        );
+    }
+
+    // the first slice is placed right away, the last one carries the rounding remainder of the split
+    let slices = match options.slices {
+        Some(slices_count) => {
+            let first_market =
+                InjectiveQuerier::new(&deps.querier)
+                    .query_spot_market(&steps[0])?
+                    .market
+                    .ok_or_else(|| ContractError::MarketNotFound {
+                        market_id: steps[0].to_owned(),
+                    })?;
+            // sold quantities have to be multiples of the min quantity tick, the quote spent on a buy has no tick
+            let slice_tick = if is_buy_step(&first_market, &source_denom) {
+                FPDecimal::ONE
+            } else {
+                first_market.min_quantity_tick_size
+            };
+            let (first_slice, remaining) = split_into_slices(floor_to_uint(current_balance.amount), slices_count, slice_tick)?;
+            current_balance.amount = first_slice.into();
+            Some(SwapSlices {
+                remaining,
+                output: FPCoin {
+                    amount: FPDecimal::ZERO,
+                    denom: output_denom,
+                },
+            })
+        }
+        None => None,
     };
 
     let swap_operation = CurrentSwapOperation {
-        sender_address,
+        sender_address: sender_address.to_owned(),
         swap_steps: steps,
         swap_quantity_mode,
         refund: Coin::new(refund_amount, source_denom.to_owned()),
+        fee: Coin::new(fee_amount, source_denom.to_owned()),
         input_funds: coin_provided.to_owned(),
+        compound_refund: options.compound_refund,
+        memo: options.memo,
+        started_at: env.block.time,
+        recipients: options.recipients,
+        min_output_value: options.min_output_value,
+        slices,
+        estimated_output: estimated_output.filter(|_| config.positive_slippage_bps > 0),
+        exact_output_input,
+        portfolio: options.portfolio,
+        route_fee_bps: route.fee_bps,
     };
 
     SWAP_RESULTS.save(deps.storage, &Vec::new())?;
     SWAP_OPERATION_STATE.save(deps.storage, &swap_operation)?;
 
-    execute_swap_step(deps, env, swap_operation, 0, current_balance).map_err(ContractError::Std)
+    let mut response = execute_swap_step(deps, env, swap_operation, 0, current_balance)?;
+
+    // funds not taking part in the swap are given back before any order is placed
+    if !ignored_funds.is_empty() {
+        let return_message = SubMsg::new(BankMsg::Send {
+            to_address: sender_address.to_string(),
+            amount: ignored_funds,
+        });
+        response.messages.insert(0, return_message);
+    }
+
+    Ok(response)
+}
+
+/// Aligns the quantity of an exact output swap ending in a buy, whose output is the base quantity of the order, to the
+/// quantity tick of the last market. Unaligned quantities are rounded up, or rejected with `reject_unaligned_exact_output`.
+/// Quantities below a single tick are rejected with `reject_sub_tick_amounts` as rounding them up at least doubles them.
+fn align_exact_output_quantity(
+    deps: Deps<InjectiveQueryWrapper>,
+    steps: &[MarketId],
+    target_denom: &str,
+    quantity: FPDecimal,
+    config: &Config,
+) -> Result<FPDecimal, ContractError> {
+    let Some(last_market_id) = steps.last() else {
+        return Ok(quantity);
+    };
+    let market = InjectiveQuerier::new(&deps.querier)
+        .query_spot_market(last_market_id)?
+        .market
+        .ok_or_else(|| ContractError::MarketNotFound {
+            market_id: last_market_id.to_owned(),
+        })?;
+    if market.base_denom != target_denom {
+        return Ok(quantity);
+    }
+
+    ensure!(
+        quantity >= market.min_quantity_tick_size || !config.reject_sub_tick_amounts,
+        ContractError::AmountBelowTick {
+            amount: quantity,
+            min_quantity_tick_size: market.min_quantity_tick_size,
+        }
+    );
+    let aligned = round_up_to_min_tick(quantity, market.min_quantity_tick_size);
+    ensure!(
+        aligned == quantity || !config.reject_unaligned_exact_output,
+        ContractError::UnalignedOutputQuantity {
+            quantity,
+            min_quantity_tick_size: market.min_quantity_tick_size,
+        }
+    );
+    Ok(aligned)
+}
+
+/// Splits `amount` into `slices_count` slices rounded down to `min_quantity_tick_size`, returning the first one and
+/// the others. The last slice gets the remainder of the split.
+fn split_into_slices(amount: Uint128, slices_count: u8, min_quantity_tick_size: FPDecimal) -> Result<(Uint128, Vec<Uint128>), ContractError> {
+    let even_slice = FPDecimal::from(amount) / FPDecimal::from(u128::from(slices_count));
+    let slice = floor_to_uint(round_to_min_tick(even_slice, min_quantity_tick_size));
+    ensure!(
+        !slice.is_zero(),
+        ContractError::AmountBelowTick {
+            amount: even_slice,
+            min_quantity_tick_size,
+        }
+    );
+    let mut slices = vec![slice; usize::from(slices_count)];
+    slices[usize::from(slices_count) - 1] = amount - slice * Uint128::from(slices_count - 1);
+    let first_slice = slices.remove(0);
+    Ok((first_slice, slices))
+}
+
+/// The state of the swap in flight is global, starting another swap would overwrite it.
+fn ensure_no_swap_in_flight(storage: &dyn Storage) -> Result<(), ContractError> {
+    ensure!(
+        !SWAP_OPERATION_STATE.exists(storage) && !STEP_STATE.exists(storage),
+        ContractError::SwapInFlight {}
+    );
+    Ok(())
+}
+
+/// Swaps of more than the large swap threshold of their input denom have to be committed first and executed in a later block.
+fn ensure_below_large_swap_threshold(storage: &dyn Storage, input: &Coin) -> Result<(), ContractError> {
+    if let Some(threshold) = LARGE_SWAP_THRESHOLDS.may_load(storage, input.denom.to_owned())? {
+        ensure!(
+            input.amount <= threshold,
+            ContractError::LargeSwapRequiresCommit {
+                amount: input.amount,
+                threshold,
+            }
+        );
+    }
+    Ok(())
+}
+
+fn verify_slippage(estimated_output: FPDecimal, min_output_quantity: FPDecimal, max_slippage_bps: u16) -> Result<(), ContractError> {
+    if min_output_quantity >= estimated_output {
+        return Ok(());
+    }
+
+    let requested_bps = (estimated_output - min_output_quantity) / estimated_output * FPDecimal::from(u128::from(MAX_BPS));
+    ensure!(
+        requested_bps <= FPDecimal::from(u128::from(max_slippage_bps)),
+        ContractError::SlippageTooLoose {
+            requested_bps,
+            max_bps: max_slippage_bps,
+        }
+    );
+    Ok(())
+}
+
+/// Picks the coin to swap from the attached funds. With `strict_funds` exactly one denom must be attached,
+/// otherwise the only attached denom with a route to `target_denom` is swapped and the others are ignored.
+fn select_input_funds(
+    deps: Deps<InjectiveQueryWrapper>,
+    strict_funds: bool,
+    funds: &[Coin],
+    target_denom: &str,
+) -> Result<(Coin, Vec<Coin>), ContractError> {
+    if funds.len() == 1 {
+        return Ok((funds[0].to_owned(), vec![]));
+    }
+
+    if strict_funds || funds.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "Only one denom can be passed in funds".to_string(),
+        });
+    }
+
+    let (swappable_funds, ignored_funds): (Vec<Coin>, Vec<Coin>) = funds
+        .iter()
+        .cloned()
+        .partition(|coin| coin.denom != target_denom && find_swap_route(deps, &coin.denom, target_denom).is_ok());
+
+    match swappable_funds.as_slice() {
+        [input_funds] => Ok((input_funds.to_owned(), ignored_funds)),
+        [] => Err(ContractError::CustomError {
+            val: format!("None of the provided denoms can be swapped to {target_denom}"),
+        }),
+        _ => Err(ContractError::CustomError {
+            val: format!("More than one of the provided denoms can be swapped to {target_denom}"),
+        }),
+    }
 }
 
 pub fn execute_swap_step(
@@ -108,6 +463,22 @@ pub fn execute_swap_step(
     swap_operation: CurrentSwapOperation,
     step_idx: u16,
     current_balance: FPCoin,
+) -> StdResult<Response<InjectiveMsgWrapper>> {
+    place_step_order(deps, env, swap_operation, step_idx, current_balance, 0)
+}
+
+/// Places the order of a step. Orders placed again after failing (`retry_count` above zero) accept a worst price
+/// widened by the configured band per retry, but not past the price at which the step could no longer keep the
+/// bounds of the swap: a buy can't cost more than the input of its step, and a sell on the last step has to deliver
+/// the min output. An earlier sell is only held to the min output by the check on settlement. While a retry is
+/// left, the reply is also requested on failure.
+fn place_step_order(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    swap_operation: CurrentSwapOperation,
+    step_idx: u16,
+    current_balance: FPCoin,
+    retry_count: u8,
 ) -> StdResult<Response<InjectiveMsgWrapper>> {
     let market_id = swap_operation.swap_steps[usize::from(step_idx)].clone();
     let contract = &env.contract.address;
@@ -119,12 +490,61 @@ pub fn execute_swap_step(
         &market_id,
         SwapEstimationAmount::InputQuantity(current_balance.clone()),
         false,
+        false,
     )?;
 
-    let fee_recipient = &CONFIG.load(deps.storage)?.fee_recipient;
+    let config = CONFIG.load(deps.storage)?;
+    let fee_recipient = &config.fee_recipient;
+
+    let worst_price = if retry_count > 0 {
+        let querier = InjectiveQuerier::new(&deps.querier);
+        let market = querier
+            .query_spot_market(&market_id)?
+            .market
+            .ok_or_else(|| StdError::generic_err(format!("Market {} not found", market_id.as_str())))?;
+        // atomic orders pay a multiple of the taker fee, relayer discounts are left out to keep the limits on the safe side
+        let fee_rate = market.taker_fee_rate * querier.query_market_atomic_execution_fee_multiplier(&market_id)?.multiplier;
+        let price_band =
+            FPDecimal::from(u128::from(config.hop_retry_price_band_bps) * u128::from(retry_count)) / FPDecimal::from(u128::from(MAX_BPS));
+        if estimation.is_buy_order {
+            let widened_price = round_up_to_min_tick(estimation.worst_price * (FPDecimal::ONE + price_band), market.min_price_tick_size);
+            let max_price = round_to_min_tick(
+                current_balance.amount / (estimation.result_quantity * (FPDecimal::ONE + fee_rate)),
+                market.min_price_tick_size,
+            );
+            // a limit tighter than the price of the first attempt would only fail the order again
+            if widened_price <= max_price {
+                widened_price
+            } else if max_price > estimation.worst_price {
+                max_price
+            } else {
+                estimation.worst_price
+            }
+        } else {
+            let widened_price = round_to_min_tick(estimation.worst_price * (FPDecimal::ONE - price_band), market.min_price_tick_size);
+            let min_price = if usize::from(step_idx) + 1 == swap_operation.swap_steps.len() {
+                let min_output = required_output(&swap_operation.swap_quantity_mode, config.exact_output_tolerance_bps);
+                round_up_to_min_tick(
+                    min_output / (current_balance.amount * (FPDecimal::ONE - fee_rate)),
+                    market.min_price_tick_size,
+                )
+            } else {
+                FPDecimal::ZERO
+            };
+            if widened_price >= min_price {
+                widened_price
+            } else if min_price < estimation.worst_price {
+                min_price
+            } else {
+                estimation.worst_price
+            }
+        }
+    } else {
+        estimation.worst_price
+    };
 
     let order = SpotOrder::new(
-        estimation.worst_price,
+        worst_price,
         if estimation.is_buy_order {
             estimation.result_quantity
         } else {
@@ -141,13 +561,19 @@ pub fn execute_swap_step(
         None,
     );
 
-    let order_message = SubMsg::reply_on_success(create_spot_market_order_msg(contract.to_owned(), order), ATOMIC_ORDER_REPLY_ID);
+    let order_msg = create_spot_market_order_msg(contract.to_owned(), order);
+    let order_message = if config.hop_retry_price_band_bps > 0 && retry_count < MAX_HOP_RETRIES {
+        SubMsg::reply_always(order_msg, ATOMIC_ORDER_REPLY_ID)
+    } else {
+        SubMsg::reply_on_success(order_msg, ATOMIC_ORDER_REPLY_ID)
+    };
 
     let current_step = CurrentSwapStep {
         step_idx,
         current_balance,
         step_target_denom: estimation.result_denom,
         is_buy: estimation.is_buy_order,
+        retry_count,
     };
     STEP_STATE.save(deps.storage, &current_step)?;
 
@@ -156,40 +582,34 @@ pub fn execute_swap_step(
 }
 
 pub fn handle_atomic_order_reply(deps: DepsMut<InjectiveQueryWrapper>, env: Env, msg: Reply) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
-    let dec_scale_factor = dec_scale_factor(); // protobuf serializes Dec values with extra 10^18 factor
-
-    let order_response = parse_market_order_response(msg)?;
-
-    let trade_data = match order_response.results {
-        Some(trade_data) => Ok(trade_data),
-        None => Err(ContractError::CustomError {
-            val: "No trade data in order response".to_string(),
-        }),
-    }?;
+    if let SubMsgResult::Err(err) = msg.result {
+        return retry_failed_step(deps, env, err);
+    }
 
-    // need to remove protobuf scale factor to get real values
-    let average_price = FPDecimal::from_str(&trade_data.price)? / dec_scale_factor;
-    let quantity = FPDecimal::from_str(&trade_data.quantity)? / dec_scale_factor;
-    let fee = FPDecimal::from_str(&trade_data.fee)? / dec_scale_factor;
+    let order_fill = parse_order_fill(msg)?;
 
     let mut swap_results = SWAP_RESULTS.load(deps.storage)?;
 
     let current_step = STEP_STATE.load(deps.storage).map_err(ContractError::Std)?;
 
-    let new_quantity = if current_step.is_buy { quantity } else { quantity * average_price - fee };
+    let new_quantity = order_fill.output_quantity(current_step.is_buy);
 
-    let swap = SWAP_OPERATION_STATE.load(deps.storage)?;
+    let mut swap = SWAP_OPERATION_STATE.load(deps.storage)?;
 
     let has_next_market = swap.swap_steps.len() > (current_step.step_idx + 1) as usize;
 
     let new_rounded_quantity = if has_next_market {
         let querier = InjectiveQuerier::new(&deps.querier);
         let next_market_id = swap.swap_steps[(current_step.step_idx + 1) as usize].to_owned();
-        let next_market = querier.query_spot_market(&next_market_id)?.market.expect("market should be available");
-
-        let is_next_swap_sell = next_market.base_denom == current_step.step_target_denom;
+        let next_market = querier
+            .query_spot_market(&next_market_id)?
+            .market
+            .ok_or_else(|| ContractError::MarketNotFound {
+                market_id: next_market_id.to_owned(),
+            })?;
 
-        if is_next_swap_sell {
+        // sells commit the base quantity as is, so it has to be a multiple of the min quantity tick
+        if !is_buy_step(&next_market, &current_step.step_target_denom) {
             round_to_min_tick(new_quantity, next_market.min_quantity_tick_size)
         } else {
             new_quantity
@@ -198,75 +618,823 @@ pub fn handle_atomic_order_reply(deps: DepsMut<InjectiveQueryWrapper>, env: Env,
         new_quantity
     };
 
-    let new_balance = FPCoin {
+    let mut new_balance = FPCoin {
         amount: new_rounded_quantity,
         denom: current_step.step_target_denom,
     };
 
     swap_results.push(SwapResults {
         market_id: swap.swap_steps[(current_step.step_idx) as usize].to_owned(),
-        price: average_price,
+        price: order_fill.price,
         quantity: new_rounded_quantity,
-        fee,
+        fee: order_fill.fee,
+        block_height: env.block.height,
+        timestamp: env.block.time,
     });
 
-    if current_step.step_idx < (swap.swap_steps.len() - 1) as u16 {
+    // only the last step delivers: a route may pass through its target denom before reaching it for good
+    if has_next_market {
         SWAP_RESULTS.save(deps.storage, &swap_results)?;
         return execute_swap_step(deps, env, swap, current_step.step_idx + 1, new_balance).map_err(ContractError::Std);
     }
 
-    let min_output_quantity = match swap.swap_quantity_mode {
-        SwapQuantityMode::MinOutputQuantity(q) => q,
-        SwapQuantityMode::ExactOutputQuantity(q) => q,
-    };
+    // a sliced swap goes along the whole route once per slice, its output is only settled after the last one
+    if let Some(mut slices) = swap.slices.take() {
+        slices.output.amount += new_balance.amount;
+        if !slices.remaining.is_empty() {
+            let next_slice = FPCoin {
+                amount: slices.remaining.remove(0).into(),
+                denom: swap.input_funds.denom.to_owned(),
+            };
+            swap.slices = Some(slices);
+            SWAP_RESULTS.save(deps.storage, &swap_results)?;
+            SWAP_OPERATION_STATE.save(deps.storage, &swap)?;
+            return execute_swap_step(deps, env, swap, 0, next_slice).map_err(ContractError::Std);
+        }
+        new_balance.amount = slices.output.amount;
+    }
+    let received_output = new_balance.amount;
+
+    let config = CONFIG.load(deps.storage)?.with_route_fee(swap.route_fee_bps);
+    let min_output_quantity = required_output(&swap.swap_quantity_mode, config.exact_output_tolerance_bps);
+
+    if config.fee_in_output_denom && matches!(swap.swap_quantity_mode, SwapQuantityMode::MinOutputQuantity(..)) {
+        let fee_amount = config.swap_fee_for(&swap.sender_address, new_balance.amount.into());
+        new_balance.amount -= FPDecimal::from(fee_amount);
+        swap.fee = Coin::new(fee_amount, new_balance.denom.to_owned());
+    }
 
     if new_balance.amount < min_output_quantity {
         return Err(ContractError::MinOutputAmountNotReached(min_output_quantity));
     }
 
+    if let Some(min_output_value) = &swap.min_output_value {
+        verify_output_value(deps.as_ref(), new_balance.amount, min_output_value)?;
+    }
+
+    // a min output swap executing better than estimated shares the surplus with the protocol, which keeps it,
+    // but never so much of it that the sender gets less than the min output
+    let positive_slippage_take = match swap.estimated_output {
+        Some(estimated_output) if config.positive_slippage_bps > 0 && new_balance.amount > estimated_output => {
+            let take = (new_balance.amount - estimated_output) * FPDecimal::from(u128::from(config.positive_slippage_bps))
+                / FPDecimal::from(u128::from(MAX_BPS));
+            let max_take = new_balance.amount - min_output_quantity;
+            let take = if take > max_take { max_take } else { take };
+            new_balance.amount -= take;
+            take
+        }
+        _ => FPDecimal::ZERO,
+    };
+
+    // rounding can make an exact output swap receive more than requested, the excess is kept unless it goes to the sender
+    let retained_excess = match swap.swap_quantity_mode {
+        SwapQuantityMode::ExactOutputQuantity(requested)
+            if config.over_delivery_policy == OverDeliveryPolicy::KeepAsRevenue && new_balance.amount > requested =>
+        {
+            let excess = new_balance.amount - requested;
+            new_balance.amount = requested;
+            excess
+        }
+        _ => FPDecimal::ZERO,
+    };
+
+    // the output is sent in whole units, so anything below one would be truncated to nothing
+    if new_balance.amount < FPDecimal::ONE {
+        ensure!(
+            config.dust_policy == DustPolicy::DeliverZero,
+            ContractError::OutputIsDust {
+                amount: new_balance.amount,
+                denom: new_balance.denom,
+            }
+        );
+    }
+
+    // bank sends are in whole units, the output is rounded down and the fraction left over stays with the contract
+    let delivered_amount = FPDecimal::from(floor_to_uint(new_balance.amount));
+    let truncated_output = new_balance.amount - delivered_amount;
+    new_balance.amount = delivered_amount;
+
+    // defensive programming: we should never give back more than the sender provided
+    ensure!(
+        swap.refund.amount <= swap.input_funds.amount,
+        ContractError::RefundExceedsInput {
+            refund: swap.refund.amount,
+            input: swap.input_funds.amount,
+        }
+    );
+
     // last step, finalize and send back funds to a caller
-    let send_message = BankMsg::Send {
-        to_address: swap.sender_address.to_string(),
-        amount: vec![new_balance.clone().into()],
+    let swapped_input_amount = swap.input_funds.amount.saturating_sub(swap.refund.amount);
+    // the fee of an exact output swap is charged on top of its input, what is left went into the orders
+    let actual_consumed = swapped_input_amount.saturating_sub(swap.fee.amount);
+    // output per unit of input the orders executed at, a compounded refund has to be swapped at that rate too
+    let executed_rate = if actual_consumed.is_zero() {
+        FPDecimal::ZERO
+    } else {
+        received_output / FPDecimal::from(actual_consumed)
     };
+    increase_swap_volume(deps.storage, &swap.input_funds.denom, swapped_input_amount)?;
+
+    // refunds above the dust threshold are swapped along the same route instead of being given back
+    let compounded_refund = if swap.compound_refund && swap.refund.amount > config.refund_dust_threshold {
+        Some(std::mem::replace(&mut swap.refund, Coin::new(0u128, swap.input_funds.denom.to_owned())))
+    } else {
+        None
+    };
+
+    // refunds too small to be worth a bank send are kept by the contract
+    let skipped_refund_amount = if swap.refund.amount < config.min_refund_amount {
+        std::mem::replace(&mut swap.refund.amount, Uint128::zero())
+    } else {
+        Uint128::zero()
+    };
+
+    record_swap_accounting(deps.storage, &swap.input_funds.denom, &swap.fee, &swap.refund)?;
+    record_market_fees(deps.storage, &swap_results)?;
+    increase_user_swap_count(deps.storage, &swap.sender_address)?;
+    credit_truncated_output(deps.storage, &new_balance.denom, truncated_output)?;
+
+    // a timelocked output is held back as claims of its recipients, only the fee and refund are sent right away
+    let output_claimable_at = if config.output_timelock_seconds > 0 {
+        let claimable_at = env.block.time.plus_seconds(config.output_timelock_seconds).seconds();
+        for (recipient, coin) in output_sends(&swap, new_balance.clone().into()) {
+            lock_output(deps.storage, &recipient, claimable_at, coin)?;
+        }
+        Some(claimable_at)
+    } else {
+        None
+    };
+
+    let settlement_messages = plan_settlement_messages(deps.as_ref(), &config, &env.contract.address, &swap, new_balance.clone().into())?;
+    verify_balance_covers(deps.as_ref(), &env.contract.address, &settlement_messages)?;
+
+    // the whole path in one attribute, so indexers don't have to follow the steps through their markets
+    let route_path = get_route_denoms(
+        deps.as_ref(),
+        &SwapRoute {
+            steps: swap.swap_steps.to_owned(),
+            source_denom: swap.input_funds.denom.to_owned(),
+            target_denom: new_balance.denom.to_owned(),
+            enabled: true,
+            fee_bps: None,
+        },
+    )?
+    .join(">");
 
     let swap_results_json = serde_json_wasm::to_string(&swap_results).unwrap();
-    let swap_event = Event::new("atomic_swap_execution")
+    let mut swap_event = Event::new("atomic_swap_execution")
         .add_attribute("sender", swap.sender_address.to_owned())
         .add_attribute("swap_input_amount", swap.input_funds.amount)
-        .add_attribute("swap_input_denom", swap.input_funds.denom)
+        .add_attribute("swap_input_denom", swap.input_funds.denom.to_owned())
         .add_attribute("refund_amount", swap.refund.amount.to_owned())
+        .add_attribute(
+            "compounded_refund_amount",
+            compounded_refund.as_ref().map_or(Uint128::zero(), |refund| refund.amount),
+        )
+        .add_attribute("skipped_refund_amount", skipped_refund_amount)
+        .add_attribute("fee_amount", swap.fee.amount.to_owned())
+        .add_attribute("fee_denom", swap.fee.denom.to_owned())
         .add_attribute("swap_final_amount", new_balance.amount.to_string())
         .add_attribute("swap_final_denom", new_balance.denom)
+        .add_attribute("route_path", route_path)
+        .add_attribute("retained_excess_amount", retained_excess.to_string())
+        .add_attribute("positive_slippage_take", positive_slippage_take.to_string())
+        .add_attribute("truncated_output_amount", truncated_output.to_string())
         .add_attribute("swap_results", swap_results_json);
+    if let Some(exact_output_input) = &swap.exact_output_input {
+        swap_event = swap_event
+            .add_attribute("estimated_input", exact_output_input.estimated.to_string())
+            .add_attribute("required_input", exact_output_input.required.to_string())
+            .add_attribute("actual_consumed", actual_consumed);
+    }
+    if let Some(claimable_at) = output_claimable_at {
+        swap_event = swap_event.add_attribute("output_claimable_at", claimable_at.to_string());
+    }
+    if let Some(memo) = &swap.memo {
+        swap_event = swap_event.add_attribute("memo", memo);
+    }
 
     SWAP_OPERATION_STATE.remove(deps.storage);
     STEP_STATE.remove(deps.storage);
     SWAP_RESULTS.remove(deps.storage);
 
-    let mut response = Response::new().add_message(send_message).add_event(swap_event);
+    let response = Response::new().add_messages(settlement_messages).add_event(swap_event);
+    if !swap.portfolio.is_empty() {
+        let mut remaining_legs = swap.portfolio;
+        let next_leg = remaining_legs.remove(0);
+        let next_leg_response = start_portfolio_leg(deps, env, swap.sender_address, next_leg, remaining_legs)?;
+        return Ok(response.add_submessages(next_leg_response.messages));
+    }
+    match compounded_refund {
+        Some(refund) => {
+            let refund_swap_response = start_refund_swap(deps, env, swap, refund, executed_rate)?;
+            Ok(response.add_submessages(refund_swap_response.messages))
+        }
+        None => Ok(response),
+    }
+}
+
+/// The least output a swap has to deliver. Tick rounding can leave an exact output slightly short, which is accepted
+/// within the configured tolerance.
+fn required_output(swap_quantity_mode: &SwapQuantityMode, exact_output_tolerance_bps: u16) -> FPDecimal {
+    match swap_quantity_mode {
+        SwapQuantityMode::MinOutputQuantity(q) => *q,
+        SwapQuantityMode::ExactOutputQuantity(q) => {
+            *q - *q * FPDecimal::from(u128::from(exact_output_tolerance_bps)) / FPDecimal::from(u128::from(MAX_BPS))
+        }
+    }
+}
+
+/// Places the order of the current step once more after it failed. Failures are only replied to while a retry
+/// is left, the final min output check still bounds the slippage of the whole swap. Only an order failing on its
+/// worst price is retried, any other failure would not be fixed by a wider price.
+fn retry_failed_step(deps: DepsMut<InjectiveQueryWrapper>, env: Env, err: String) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let current_step = STEP_STATE.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    ensure!(
+        config.hop_retry_price_band_bps > 0 && current_step.retry_count < MAX_HOP_RETRIES && is_price_failure(&err),
+        ContractError::SubMsgFailure(err)
+    );
 
-    if !swap.refund.amount.is_zero() {
-        let refund_message = BankMsg::Send {
-            to_address: swap.sender_address.to_string(),
-            amount: vec![swap.refund],
-        };
-        response = response.add_message(refund_message)
+    let swap = SWAP_OPERATION_STATE.load(deps.storage)?;
+    let response = place_step_order(
+        deps,
+        env,
+        swap,
+        current_step.step_idx,
+        current_step.current_balance,
+        current_step.retry_count + 1,
+    )?;
+    Ok(response.add_attribute("retried_step", current_step.step_idx.to_string()))
+}
+
+/// Whether an atomic order failed because the price moved past its worst price. The chain redacts the errors of
+/// submessages to `codespace: <codespace>, code: <code>`, so the failure is told by the code the exchange module
+/// registered "slippage exceeds worst price" with.
+fn is_price_failure(err: &str) -> bool {
+    err.trim() == format!("codespace: {EXCHANGE_CODESPACE}, code: {SLIPPAGE_EXCEEDS_WORST_PRICE_CODE}")
+}
+
+/// Values `output` at the oracle price of the reference pair and fails when it is worth less than the minimum.
+fn verify_output_value(deps: Deps<InjectiveQueryWrapper>, output: FPDecimal, min_output_value: &MinOutputValue) -> Result<(), ContractError> {
+    let querier = InjectiveQuerier::new(&deps.querier);
+    let price_pair_state = querier
+        .query_oracle_price(&min_output_value.oracle_type, &min_output_value.base, &min_output_value.quote)?
+        .price_pair_state
+        .ok_or_else(|| ContractError::CustomError {
+            val: format!("No oracle price for {}/{}", min_output_value.base, min_output_value.quote),
+        })?;
+
+    let value = output * price_pair_state.pair_price;
+    ensure!(
+        value >= min_output_value.value,
+        ContractError::OutputValueNotReached {
+            value,
+            min_value: min_output_value.value,
+        }
+    );
+    Ok(())
+}
+
+/// Routes are validated when set, but one stored without steps has no market to place the first order on,
+/// so it is rejected before anything is swapped along it.
+fn ensure_route_executable(route: &SwapRoute) -> Result<(), ContractError> {
+    ensure!(
+        !route.steps.is_empty(),
+        ContractError::EmptyRoute {
+            source_denom: route.source_denom.to_owned(),
+            target_denom: route.target_denom.to_owned(),
+        }
+    );
+    ensure!(
+        route.enabled,
+        ContractError::RouteDisabled {
+            source_denom: route.source_denom.to_owned(),
+            target_denom: route.target_denom.to_owned(),
+        }
+    );
+    Ok(())
+}
+
+/// Swaps a compounded refund along the route of the finished `swap` it was left over from, its output is sent
+/// to the sender like the output of any other swap. The refund swap is charged the contract fee like any other swap
+/// and has to deliver at the `executed_rate` of `swap`, within the tolerance exact output swaps are held to.
+fn start_refund_swap(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    swap: CurrentSwapOperation,
+    refund: Coin,
+    executed_rate: FPDecimal,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let config = CONFIG.load(deps.storage)?.with_route_fee(swap.route_fee_bps);
+    let fee_amount = if config.fee_in_output_denom {
+        Uint128::zero()
+    } else {
+        config.swap_fee_for(&swap.sender_address, refund.amount)
+    };
+    let swapped_amount = FPDecimal::from(refund.amount - fee_amount);
+
+    let mut expected_output = swapped_amount * executed_rate;
+    if config.fee_in_output_denom {
+        expected_output -= FPDecimal::from(config.swap_fee_for(&swap.sender_address, expected_output.into()));
+    }
+    let min_output_quantity =
+        expected_output - expected_output * FPDecimal::from(u128::from(config.exact_output_tolerance_bps)) / FPDecimal::from(u128::from(MAX_BPS));
+
+    let swap_operation = CurrentSwapOperation {
+        sender_address: swap.sender_address,
+        swap_steps: swap.swap_steps,
+        swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(min_output_quantity),
+        input_funds: refund.to_owned(),
+        refund: Coin::new(0u128, refund.denom.to_owned()),
+        fee: Coin::new(fee_amount, refund.denom.to_owned()),
+        compound_refund: false,
+        memo: swap.memo,
+        started_at: env.block.time,
+        recipients: vec![],
+        min_output_value: None,
+        slices: None,
+        estimated_output: None,
+        exact_output_input: None,
+        portfolio: vec![],
+        route_fee_bps: swap.route_fee_bps,
+    };
+
+    SWAP_RESULTS.save(deps.storage, &Vec::new())?;
+    SWAP_OPERATION_STATE.save(deps.storage, &swap_operation)?;
+
+    let current_balance = FPCoin {
+        amount: swapped_amount,
+        denom: refund.denom,
+    };
+    Ok(execute_swap_step(deps, env, swap_operation, 0, current_balance)?)
+}
+
+/// Splits the attached `source_denom` across `targets` by their bps weights and swaps each part along its own route.
+/// Only one swap can be in flight at a time, so the parts are swapped one after another, each started when the one
+/// before it settled. Each part has to deliver the min output of its target, and the whole input is held to the
+/// large swap threshold, so that splitting it does not get around committing it.
+pub fn swap_to_portfolio(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    source_denom: String,
+    targets: Vec<PortfolioTarget>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let total_weight: u32 = targets.iter().map(|target| u32::from(target.weight_bps)).sum();
+    ensure!(
+        total_weight == u32::from(MAX_BPS),
+        ContractError::InvalidPortfolioWeights { total_bps: total_weight }
+    );
+    let input = match info.funds.as_slice() {
+        [input] if input.denom == source_denom => input.to_owned(),
+        _ => {
+            return Err(ContractError::CustomError {
+                val: format!("Only {source_denom} can be passed in funds"),
+            })
+        }
+    };
+    ensure_below_large_swap_threshold(deps.storage, &input)?;
+
+    // parts too small to be swapped are left out, rounding dust goes to the last target like with distributed outputs
+    let weighted_targets = targets
+        .into_iter()
+        .map(|target| ((target.denom, target.min_output_quantity), target.weight_bps))
+        .collect::<Vec<_>>();
+    let mut legs = distribute_output(input, &weighted_targets)
+        .into_iter()
+        .filter(|(_, part)| !part.amount.is_zero())
+        .map(|((target_denom, min_output_quantity), part)| PortfolioLeg {
+            target_denom,
+            input: part,
+            min_output_quantity,
+        })
+        .collect::<Vec<PortfolioLeg>>();
+    ensure!(
+        !legs.is_empty(),
+        ContractError::CustomError {
+            val: "Nothing to swap".to_string(),
+        }
+    );
+    let first_leg = legs.remove(0);
+    start_portfolio_leg(deps, env, info.sender, first_leg, legs)
+}
+
+fn start_portfolio_leg(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    sender: Addr,
+    leg: PortfolioLeg,
+    remaining_legs: Vec<PortfolioLeg>,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let info = MessageInfo {
+        sender,
+        funds: vec![leg.input],
+    };
+    start_swap_flow(
+        deps,
+        env,
+        info,
+        leg.target_denom,
+        SwapQuantityMode::MinOutputQuantity(leg.min_output_quantity),
+        SwapOptions {
+            portfolio: remaining_legs,
+            portfolio_leg: true,
+            ..SwapOptions::default()
+        },
+    )
+}
+
+/// Aborts the in-flight swap of `sender` and gives back the funds the contract holds for it. Orders reply
+/// within the transaction that placed them, so a swap started in the current block can not be cancelled,
+/// its reply may still be pending.
+pub fn cancel_swap(deps: DepsMut<InjectiveQueryWrapper>, env: Env, sender: Addr) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let swap = SWAP_OPERATION_STATE.may_load(deps.storage)?.ok_or(ContractError::NoPendingSwap {})?;
+    ensure!(swap.sender_address == sender, ContractError::Unauthorized {});
+    ensure!(swap.started_at < env.block.time, ContractError::SwapStartedInThisBlock {});
+
+    let funds = take_in_flight_swap_funds(deps.storage, swap)?;
+
+    let mut response = Response::new().add_attribute("method", "cancel_swap");
+    if !funds.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: sender.to_string(),
+            amount: funds.into_vec(),
+        });
+    }
+    Ok(response)
+}
+
+/// Clears the state of the in-flight `swap` and returns the funds the contract holds for it.
+fn take_in_flight_swap_funds(storage: &mut dyn Storage, swap: CurrentSwapOperation) -> StdResult<Coins> {
+    // before the first order fills, the whole input is still held as provided
+    let mut funds = Coins::default();
+    match STEP_STATE.may_load(storage)? {
+        Some(current_step) if current_step.step_idx > 0 || swap.slices.is_some() => {
+            funds.add(current_step.current_balance.into())?;
+            funds.add(swap.refund)?;
+            funds.add(swap.fee)?;
+            // slices not placed yet are still held in the input denom, the finished ones in the output denom
+            if let Some(slices) = swap.slices {
+                for slice in slices.remaining {
+                    funds.add(Coin::new(slice, swap.input_funds.denom.to_owned()))?;
+                }
+                funds.add(slices.output.into())?;
+            }
+        }
+        _ => funds.add(swap.input_funds)?,
+    }
+    // parts of a portfolio swap not started yet are still held as provided
+    for leg in swap.portfolio {
+        funds.add(leg.input)?;
+    }
+
+    SWAP_OPERATION_STATE.remove(storage);
+    STEP_STATE.remove(storage);
+    SWAP_RESULTS.remove(storage);
+    Ok(funds)
+}
+
+/// Emergency unwind by the admin: gives every sender back the funds the contract holds for them, the
+/// stuck in-flight swap (unless started in this block, like with `cancel_swap`) and the committed large swaps.
+/// At most `REFUND_ALL_BATCH_SIZE` commits are refunded per call, `has_more` tells whether to call again.
+pub fn refund_all_in_flight(deps: DepsMut<InjectiveQueryWrapper>, env: Env, sender: &Addr) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    verify_sender_is_admin(deps.as_ref(), sender)?;
+
+    let mut refunds: Vec<(Addr, Vec<Coin>)> = vec![];
+    if let Some(swap) = SWAP_OPERATION_STATE.may_load(deps.storage)? {
+        if swap.started_at < env.block.time {
+            let sender_address = swap.sender_address.to_owned();
+            refunds.push((sender_address, take_in_flight_swap_funds(deps.storage, swap)?.into_vec()));
+        }
+    }
+
+    let commits = LARGE_SWAP_COMMITS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(REFUND_ALL_BATCH_SIZE + 1)
+        .collect::<StdResult<Vec<(Addr, LargeSwapCommit)>>>()?;
+    let has_more = commits.len() > REFUND_ALL_BATCH_SIZE;
+    for (committer, commit) in commits.into_iter().take(REFUND_ALL_BATCH_SIZE) {
+        LARGE_SWAP_COMMITS.remove(deps.storage, &committer);
+        refunds.push((committer, commit.funds));
     }
 
+    let mut response = Response::new()
+        .add_attribute("method", "refund_all_in_flight")
+        .add_attribute("refunded_senders", refunds.len().to_string())
+        .add_attribute("has_more", has_more.to_string());
+    for (to_address, amount) in refunds {
+        if !amount.is_empty() {
+            response = response.add_message(BankMsg::Send {
+                to_address: to_address.to_string(),
+                amount,
+            });
+        }
+    }
     Ok(response)
 }
 
-pub fn parse_market_order_response(msg: Reply) -> StdResult<MsgCreateSpotMarketOrderResponse> {
-    let binding = msg.result.into_result().map_err(ContractError::SubMsgFailure).unwrap();
+/// Holds the attached funds of `info.sender` for a swap to be executed with `execute_large_swap` in a later block,
+/// so that the order can't be targeted within the block the swap is announced in.
+pub fn commit_large_swap(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    target_denom: String,
+    swap_quantity_mode: SwapQuantityMode,
+    acknowledge_high_impact: bool,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure!(!config.paused, ContractError::Paused {});
+    ensure!(
+        !info.funds.is_empty(),
+        ContractError::CustomError {
+            val: "Funds must be provided to commit a swap".to_string(),
+        }
+    );
+    ensure!(
+        !LARGE_SWAP_COMMITS.has(deps.storage, &info.sender),
+        ContractError::LargeSwapAlreadyCommitted {}
+    );
+    ensure_no_swap_in_flight(deps.storage)?;
+
+    let commit = LargeSwapCommit {
+        target_denom,
+        swap_quantity_mode,
+        funds: info.funds,
+        committed_at_height: env.block.height,
+        acknowledge_high_impact,
+    };
+    LARGE_SWAP_COMMITS.save(deps.storage, &info.sender, &commit)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "commit_large_swap")
+        .add_attribute("sender", info.sender)
+        .add_attribute("ready_at_height", large_swap_ready_at(&config, &commit).to_string()))
+}
+
+/// Starts the swap committed by `sender`. The committed funds are already held by the contract and are swapped as
+/// if attached to the call.
+pub fn execute_large_swap(deps: DepsMut<InjectiveQueryWrapper>, env: Env, sender: Addr) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let commit = LARGE_SWAP_COMMITS
+        .may_load(deps.storage, &sender)?
+        .ok_or(ContractError::NoCommittedLargeSwap {})?;
+    let ready_at_height = large_swap_ready_at(&config, &commit);
+    ensure!(env.block.height >= ready_at_height, ContractError::LargeSwapNotReady { ready_at_height });
+
+    LARGE_SWAP_COMMITS.remove(deps.storage, &sender);
+    let info = MessageInfo { sender, funds: commit.funds };
+    start_swap_flow(
+        deps,
+        env,
+        info,
+        commit.target_denom,
+        commit.swap_quantity_mode,
+        SwapOptions {
+            committed: true,
+            acknowledge_high_impact: commit.acknowledge_high_impact,
+            ..SwapOptions::default()
+        },
+    )
+}
+
+/// Gives back the funds of the swap committed by `sender`.
+pub fn cancel_large_swap(deps: DepsMut<InjectiveQueryWrapper>, sender: Addr) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let commit = LARGE_SWAP_COMMITS
+        .may_load(deps.storage, &sender)?
+        .ok_or(ContractError::NoCommittedLargeSwap {})?;
+    LARGE_SWAP_COMMITS.remove(deps.storage, &sender);
+
+    Ok(Response::new().add_attribute("method", "cancel_large_swap").add_message(BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: commit.funds,
+    }))
+}
+
+/// Sends `sender` their outputs held back by the output timelock that can be claimed by now.
+pub fn claim_output(deps: DepsMut<InjectiveQueryWrapper>, env: Env, sender: Addr) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    let claimable = OUTPUT_CLAIMS
+        .prefix(&sender)
+        .range(deps.storage, None, Some(Bound::inclusive(env.block.time.seconds())), Order::Ascending)
+        .collect::<StdResult<Vec<(u64, Coin)>>>()?;
+    if claimable.is_empty() {
+        let next_claim = OUTPUT_CLAIMS
+            .prefix(&sender)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next()
+            .transpose()?;
+        return Err(match next_claim {
+            Some(claimable_at) => ContractError::OutputStillLocked { claimable_at },
+            None => ContractError::NoOutputToClaim {},
+        });
+    }
+
+    let mut response = Response::new().add_attribute("method", "claim_output");
+    for (claimable_at, coin) in claimable {
+        OUTPUT_CLAIMS.remove(deps.storage, (&sender, claimable_at));
+        let output_cw20_adapter = CW20_OUTPUT_ADAPTERS.may_load(deps.storage, coin.denom.to_owned())?;
+        response = response.add_message(output_delivery_message(&sender, coin, output_cw20_adapter.as_ref())?);
+    }
+    Ok(response)
+}
+
+// a committed swap is never executed in the block it was committed in, even without a configured delay
+fn large_swap_ready_at(config: &Config, commit: &LargeSwapCommit) -> u64 {
+    commit.committed_at_height + config.large_swap_delay_blocks.max(1)
+}
+
+/// Messages settling `swap` once its last step delivered `output`, as sent by `finalize_swap` and planned by
+/// the queries. A timelocked output is not sent, it is locked for its recipients instead.
+pub(crate) fn plan_settlement_messages(
+    deps: Deps<InjectiveQueryWrapper>,
+    config: &Config,
+    contract_address: &Addr,
+    swap: &CurrentSwapOperation,
+    mut output: Coin,
+) -> StdResult<Vec<CosmosMsg<InjectiveMsgWrapper>>> {
+    if config.output_timelock_seconds > 0 {
+        output.amount = Uint128::zero();
+    }
+    let output_cw20_adapter = CW20_OUTPUT_ADAPTERS.may_load(deps.storage, output.denom.to_owned())?;
+    let fee_recipient = fee_recipient_for(deps.storage, config, &swap.fee.denom)?;
+    build_settlement_messages(swap, output, output_cw20_adapter.as_ref(), config, &fee_recipient, contract_address)
+}
+
+/// Builds the bank messages settling a finished swap. The order is fixed so that indexers and
+/// auditors can rely on it: the swap output first (unless it is dust, split across the recipients
+/// if any), then the contract fee (if any, its burned share first), then the refund of unused input (if any). This is the only place the contract fee
+/// leaves the contract, so a swap failing at any earlier hop reverts with both the input and the fee back with the sender.
+/// With `output_cw20_adapter` set, the output is redeemed through the adapter and arrives as its CW20 token instead.
+fn build_settlement_messages(
+    swap: &CurrentSwapOperation,
+    output: Coin,
+    output_cw20_adapter: Option<&Addr>,
+    config: &Config,
+    fee_recipient: &Addr,
+    contract_address: &Addr,
+) -> StdResult<Vec<CosmosMsg<InjectiveMsgWrapper>>> {
+    let mut messages = vec![];
+    for (recipient, coin) in output_sends(swap, output).into_iter().filter(|(_, coin)| !coin.amount.is_zero()) {
+        messages.push(output_delivery_message(&recipient, coin, output_cw20_adapter)?);
+    }
+
+    // a fee going back to the sender is netted into what they receive anyway instead of sent on its own
+    let mut refund = swap.refund.clone();
+    for (recipient, coin) in config.fee_sends(&swap.fee, fee_recipient, contract_address) {
+        if recipient == swap.sender_address {
+            if coin.denom == refund.denom {
+                refund.amount += coin.amount;
+                continue;
+            }
+            if let Some(sent) = messages
+                .iter_mut()
+                .find_map(|message| bank_send_coin_to(message, &recipient, &coin.denom))
+            {
+                sent.amount += coin.amount;
+                continue;
+            }
+        }
+        messages.push(bank_send(&recipient, coin));
+    }
+
+    if !refund.amount.is_zero() {
+        messages.push(bank_send(&swap.sender_address, refund));
+    }
+
+    Ok(messages)
+}
+
+/// Shares of `output` going to the sender or, for distributed swaps, to each of the recipients.
+fn output_sends(swap: &CurrentSwapOperation, output: Coin) -> Vec<(Addr, Coin)> {
+    if swap.recipients.is_empty() {
+        vec![(swap.sender_address.to_owned(), output)]
+    } else {
+        distribute_output(output, &swap.recipients)
+    }
+}
+
+/// Delivers `coin` of the output to `recipient`, as the CW20 token of `output_cw20_adapter` if one is set.
+fn output_delivery_message(recipient: &Addr, coin: Coin, output_cw20_adapter: Option<&Addr>) -> StdResult<CosmosMsg<InjectiveMsgWrapper>> {
+    Ok(match output_cw20_adapter {
+        Some(adapter) => WasmMsg::Execute {
+            contract_addr: adapter.to_string(),
+            msg: to_json_binary(&Cw20AdapterMsg::RedeemAndTransfer {
+                recipient: Some(recipient.to_string()),
+            })?,
+            funds: vec![coin],
+        }
+        .into(),
+        None => bank_send(recipient, coin),
+    })
+}
 
-    let first_message = binding.msg_responses.first();
-    let order_response = MsgCreateSpotMarketOrderResponse::decode(first_message.unwrap().value.as_slice())
-        .map_err(|err| ContractError::ReplyParseFailure {
-            id: msg.id,
-            err: err.to_string(),
+/// The coin in `denom` a bank send `message` already transfers to `recipient`, if it is one.
+fn bank_send_coin_to<'a>(message: &'a mut CosmosMsg<InjectiveMsgWrapper>, recipient: &Addr, denom: &str) -> Option<&'a mut Coin> {
+    match message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) if to_address.as_str() == recipient.as_str() => {
+            amount.iter_mut().find(|coin| coin.denom == denom)
+        }
+        _ => None,
+    }
+}
+
+fn bank_send(recipient: &Addr, coin: Coin) -> CosmosMsg<InjectiveMsgWrapper> {
+    BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![coin],
+    }
+    .into()
+}
+
+/// Guards against the state drifting from the actual balance: the contract has to hold everything the settlement
+/// sends, per denom.
+fn verify_balance_covers(
+    deps: Deps<InjectiveQueryWrapper>,
+    contract_address: &Addr,
+    messages: &[CosmosMsg<InjectiveMsgWrapper>],
+) -> Result<(), ContractError> {
+    let mut required = Coins::default();
+    for message in messages {
+        let sent = match message {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => amount,
+            CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => funds,
+            _ => continue,
+        };
+        for coin in sent {
+            required.add(coin.to_owned())?;
+        }
+    }
+
+    for coin in required {
+        let available = deps.querier.query_balance(contract_address, &coin.denom)?.amount;
+        ensure!(
+            available >= coin.amount,
+            ContractError::BalanceDrift {
+                denom: coin.denom,
+                required: coin.amount,
+                available,
+            }
+        );
+
+        let remaining = available - coin.amount;
+        if let Some(reserve) = RESERVES.may_load(deps.storage, coin.denom.to_owned())? {
+            ensure!(
+                remaining >= reserve,
+                ContractError::ReserveProtected {
+                    denom: coin.denom,
+                    reserve,
+                    remaining,
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Splits `output` across `recipients` by their bps weights. The rounding dust goes to the last recipient,
+/// so that the whole output is delivered.
+fn distribute_output<R: Clone>(output: Coin, recipients: &[(R, u16)]) -> Vec<(R, Coin)> {
+    let mut remaining = output.amount;
+    let mut shares: Vec<(R, Coin)> = recipients
+        .iter()
+        .map(|(recipient, weight_bps)| {
+            let amount = output.amount.multiply_ratio(*weight_bps, MAX_BPS);
+            remaining -= amount;
+            (recipient.to_owned(), Coin::new(amount, output.denom.to_owned()))
         })
-        .unwrap();
+        .collect();
+
+    if let Some((_, last_share)) = shares.last_mut() {
+        last_share.amount += remaining;
+    }
+    shares
+}
+
+pub fn parse_market_order_response(msg: Reply) -> Result<MsgCreateSpotMarketOrderResponse, ContractError> {
+    let id = msg.id;
+    let reply_parse_failure = |err: String| ContractError::ReplyParseFailure { id, err };
+
+    let binding = msg.result.into_result().map_err(ContractError::SubMsgFailure)?;
+    let first_message = binding
+        .msg_responses
+        .first()
+        .ok_or_else(|| reply_parse_failure("no message response".to_string()))?;
+
+    MsgCreateSpotMarketOrderResponse::decode(first_message.value.as_slice()).map_err(|err| reply_parse_failure(err.to_string()))
+}
+
+/// Decodes the fill of an atomic market order from its reply.
+pub fn parse_order_fill(msg: Reply) -> Result<OrderFill, ContractError> {
+    let trade_data = parse_market_order_response(msg)?.results.ok_or_else(|| ContractError::CustomError {
+        val: "No trade data in order response".to_string(),
+    })?;
 
-    Ok(order_response)
+    // protobuf serializes Dec values with extra 10^18 factor
+    let dec_scale_factor = dec_scale_factor();
+    Ok(OrderFill {
+        quantity: FPDecimal::from_str(&trade_data.quantity)? / dec_scale_factor,
+        price: FPDecimal::from_str(&trade_data.price)? / dec_scale_factor,
+        fee: FPDecimal::from_str(&trade_data.fee)? / dec_scale_factor,
+    })
 }
@@ -1,24 +1,24 @@
 use crate::{
-    contract::execute,
-    msg::{ExecuteMsg, FeeRecipient},
-    state::CONFIG,
-    testing::test_utils::{TEST_CONTRACT_ADDR, TEST_USER_ADDR},
-    types::Config,
+    admin::{update_config, ConfigUpdate},
+    contract::{execute, instantiate, query},
+    msg::{ExecuteMsg, FeeRecipient, InstantiateMsg, QueryMsg},
+    state::{CONFIG, LARGE_SWAP_THRESHOLDS},
+    testing::test_utils::{mock_deps_eth_inj, MultiplierQueryBehavior, TEST_CONTRACT_ADDR, TEST_USER_ADDR},
+    types::{Config, SwapRoute, SwapRouteResponse},
+    ContractError,
 };
 
 use cosmwasm_std::testing::{message_info, mock_env};
-use cosmwasm_std::{coins, Addr};
+use cosmwasm_std::{coins, from_json, Addr, Coin, Uint128};
 
-use injective_cosmwasm::{inj_mock_deps, OwnedDepsExt};
+use injective_cosmwasm::{inj_mock_deps, MarketId, OwnedDepsExt, TEST_MARKET_ID_1, TEST_MARKET_ID_2};
+use injective_math::FPDecimal;
 
 #[test]
 pub fn admin_can_update_config() {
     let mut deps = inj_mock_deps(|_| {});
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_CONTRACT_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let new_admin = Addr::unchecked("new_admin");
@@ -29,6 +29,29 @@ pub fn admin_can_update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         admin: Some(new_admin.clone()),
         fee_recipient: Some(FeeRecipient::Address(new_fee_recipient.clone())),
+        paused: None,
+        swap_fee_bps: None,
+        allow_direct_fallback: None,
+        strict_funds: None,
+        fee_in_output_denom: None,
+        refund_dust_threshold: None,
+        dust_policy: None,
+        min_fee: None,
+        max_fee: None,
+        over_delivery_policy: None,
+        admin_fee_exempt: None,
+        hop_retry_price_band_bps: None,
+        fee_burn_bps: None,
+        burn_address: None,
+        allow_custom_routes: None,
+        large_swap_delay_blocks: None,
+        reject_unaligned_exact_output: None,
+        exact_output_tolerance_bps: None,
+        min_refund_amount: None,
+        positive_slippage_bps: None,
+        high_impact_threshold_bps: None,
+        reject_sub_tick_amounts: None,
+        output_timelock_seconds: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -61,10 +84,7 @@ pub fn admin_can_update_config() {
 pub fn non_admin_cannot_update_config() {
     let mut deps = inj_mock_deps(|_| {});
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_CONTRACT_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let new_admin = Addr::unchecked("new_admin");
@@ -75,8 +95,509 @@ pub fn non_admin_cannot_update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         admin: Some(new_admin),
         fee_recipient: Some(FeeRecipient::Address(new_fee_recipient)),
+        paused: None,
+        swap_fee_bps: None,
+        allow_direct_fallback: None,
+        strict_funds: None,
+        fee_in_output_denom: None,
+        refund_dust_threshold: None,
+        dust_policy: None,
+        min_fee: None,
+        max_fee: None,
+        over_delivery_policy: None,
+        admin_fee_exempt: None,
+        hop_retry_price_band_bps: None,
+        fee_burn_bps: None,
+        burn_address: None,
+        allow_custom_routes: None,
+        large_swap_delay_blocks: None,
+        reject_unaligned_exact_output: None,
+        exact_output_tolerance_bps: None,
+        min_refund_amount: None,
+        positive_slippage_bps: None,
+        high_impact_threshold_bps: None,
+        reject_sub_tick_amounts: None,
+        output_timelock_seconds: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
     assert!(res.is_err(), "expected error on non-admin update config");
 }
+
+#[test]
+pub fn instantiate_rejects_invalid_admin_address() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    for invalid_admin in ["", "not_a_bech32_address"] {
+        let res = instantiate(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+            InstantiateMsg {
+                fee_recipient: FeeRecipient::Address(Addr::unchecked(TEST_USER_ADDR)),
+                admin: Addr::unchecked(invalid_admin),
+                initial_routes: vec![],
+            },
+        );
+
+        match res {
+            Err(ContractError::InvalidAddress { field, .. }) => assert_eq!(field, "admin", "wrong field reported"),
+            _ => panic!("expected invalid admin address error for '{invalid_admin}'"),
+        }
+    }
+
+    assert!(
+        CONFIG.may_load(deps.as_mut_deps().storage).unwrap().is_none(),
+        "config should not be stored"
+    );
+}
+
+#[test]
+pub fn instantiate_rejects_invalid_fee_recipient_address() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let res = instantiate(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        InstantiateMsg {
+            fee_recipient: FeeRecipient::Address(Addr::unchecked("junk")),
+            admin: Addr::unchecked(TEST_USER_ADDR),
+            initial_routes: vec![],
+        },
+    );
+
+    match res {
+        Err(ContractError::InvalidAddress { field, address, .. }) => {
+            assert_eq!(field, "fee_recipient", "wrong field reported");
+            assert_eq!(address, "junk", "wrong address reported");
+        }
+        _ => panic!("expected invalid fee_recipient address error"),
+    }
+}
+
+#[test]
+pub fn instantiate_stores_initial_routes() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let initial_route = |source_denom: &str, market_id: &str, fee_bps: Option<u16>| SwapRoute {
+        steps: vec![MarketId::unchecked(market_id)],
+        source_denom: source_denom.to_string(),
+        target_denom: "usdt".to_string(),
+        enabled: true,
+        fee_bps,
+    };
+
+    instantiate(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        InstantiateMsg {
+            fee_recipient: FeeRecipient::Address(Addr::unchecked(TEST_USER_ADDR)),
+            admin: Addr::unchecked(TEST_USER_ADDR),
+            initial_routes: vec![
+                initial_route("eth", TEST_MARKET_ID_1, None),
+                initial_route("inj", TEST_MARKET_ID_2, Some(10)),
+            ],
+        },
+    )
+    .unwrap();
+
+    for (source_denom, market_id, fee_bps) in [("eth", TEST_MARKET_ID_1, None), ("inj", TEST_MARKET_ID_2, Some(10))] {
+        let route: SwapRouteResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetRoute {
+                    source_denom: source_denom.to_string(),
+                    target_denom: "usdt".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(route.steps, vec![MarketId::unchecked(market_id)], "wrong steps stored for {source_denom}");
+        assert_eq!(route.fee_bps, fee_bps, "wrong fee stored for {source_denom}");
+    }
+}
+
+#[test]
+pub fn admin_can_update_subset_of_config() {
+    let mut deps = inj_mock_deps(|_| {});
+
+    let config = Config {
+        swap_fee_bps: 25,
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let info = message_info(&Addr::unchecked(TEST_USER_ADDR), &[]);
+
+    let msg = ExecuteMsg::UpdateConfig {
+        admin: None,
+        fee_recipient: None,
+        paused: Some(true),
+        swap_fee_bps: None,
+        allow_direct_fallback: None,
+        strict_funds: None,
+        fee_in_output_denom: None,
+        refund_dust_threshold: None,
+        dust_policy: None,
+        min_fee: None,
+        max_fee: None,
+        over_delivery_policy: None,
+        admin_fee_exempt: None,
+        hop_retry_price_band_bps: None,
+        fee_burn_bps: None,
+        burn_address: None,
+        allow_custom_routes: None,
+        large_swap_delay_blocks: None,
+        reject_unaligned_exact_output: None,
+        exact_output_tolerance_bps: None,
+        min_refund_amount: None,
+        positive_slippage_bps: None,
+        high_impact_threshold_bps: None,
+        reject_sub_tick_amounts: None,
+        output_timelock_seconds: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let updated_config = CONFIG.load(deps.as_mut_deps().storage).unwrap();
+    assert!(updated_config.paused, "paused was not updated");
+    assert_eq!(updated_config.admin, config.admin, "admin should be preserved");
+    assert_eq!(updated_config.fee_recipient, config.fee_recipient, "fee_recipient should be preserved");
+    assert_eq!(updated_config.swap_fee_bps, 25, "swap_fee_bps should be preserved");
+
+    let event = res
+        .events
+        .iter()
+        .find(|e| e.ty == "config_updated")
+        .expect("update_config event expected");
+    assert_eq!(event.attributes.len(), 1, "only updated fields should be reported");
+    assert!(
+        event.attributes.iter().any(|a| a.key == "paused" && a.value == "true"),
+        "paused attribute expected"
+    );
+}
+
+#[test]
+pub fn admin_cannot_set_swap_fee_above_max_bps() {
+    let mut deps = inj_mock_deps(|_| {});
+
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let info = message_info(&Addr::unchecked(TEST_USER_ADDR), &[]);
+
+    let msg = ExecuteMsg::UpdateConfig {
+        admin: None,
+        fee_recipient: None,
+        paused: None,
+        swap_fee_bps: Some(10_001),
+        allow_direct_fallback: None,
+        strict_funds: None,
+        fee_in_output_denom: None,
+        refund_dust_threshold: None,
+        dust_policy: None,
+        min_fee: None,
+        max_fee: None,
+        over_delivery_policy: None,
+        admin_fee_exempt: None,
+        hop_retry_price_band_bps: None,
+        fee_burn_bps: None,
+        burn_address: None,
+        allow_custom_routes: None,
+        large_swap_delay_blocks: None,
+        reject_unaligned_exact_output: None,
+        exact_output_tolerance_bps: None,
+        min_refund_amount: None,
+        positive_slippage_bps: None,
+        high_impact_threshold_bps: None,
+        reject_sub_tick_amounts: None,
+        output_timelock_seconds: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    assert!(res.is_err(), "expected error on swap_fee_bps above 10000");
+
+    let stored_config = CONFIG.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(stored_config.swap_fee_bps, 0, "swap_fee_bps should not be updated");
+}
+
+#[test]
+pub fn every_bps_setter_rejects_values_above_max_bps() {
+    let mut deps = inj_mock_deps(|_| {});
+
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let updates = [
+        (
+            "swap_fee_bps",
+            ConfigUpdate {
+                swap_fee_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+        (
+            "fee_burn_bps",
+            ConfigUpdate {
+                fee_burn_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+        (
+            "hop_retry_price_band_bps",
+            ConfigUpdate {
+                hop_retry_price_band_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+        (
+            "exact_output_tolerance_bps",
+            ConfigUpdate {
+                exact_output_tolerance_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+        (
+            "positive_slippage_bps",
+            ConfigUpdate {
+                positive_slippage_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+        (
+            "high_impact_threshold_bps",
+            ConfigUpdate {
+                high_impact_threshold_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ),
+    ];
+    for (expected_field, update) in updates {
+        let err = update_config(deps.as_mut(), mock_env(), Addr::unchecked(TEST_USER_ADDR), update).unwrap_err();
+        assert!(
+            matches!(&err, ContractError::InvalidBps { field, bps: 10_001 } if field == expected_field),
+            "wrong error for {expected_field}: {err}"
+        );
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::SetMaxSlippage {
+            max_slippage_bps: Some(10_001),
+        },
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&err, ContractError::InvalidBps { field, bps: 10_001 } if field == "max_allowed_slippage_bps"),
+        "wrong error for max_allowed_slippage_bps: {err}"
+    );
+
+    assert_eq!(CONFIG.load(deps.as_mut_deps().storage).unwrap(), config, "config should not be updated");
+}
+
+#[test]
+pub fn swaps_are_rejected_when_paused() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let config = Config {
+        paused: true,
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let info = message_info(&Addr::unchecked(TEST_USER_ADDR), &coins(12, "eth"));
+
+    let msg = ExecuteMsg::SwapMinOutput {
+        target_denom: "inj".to_string(),
+        min_output_quantity: FPDecimal::ONE,
+        auto_convert_to: None,
+        memo: None,
+        input_fraction_bps: None,
+        min_output_value: None,
+        slices: None,
+        acknowledge_high_impact: false,
+    };
+
+    let res = execute(deps.as_mut_deps(), mock_env(), info, msg);
+    assert!(matches!(res, Err(ContractError::Paused {})), "expected paused error");
+}
+
+#[test]
+pub fn it_returns_stored_admin() {
+    let mut deps = inj_mock_deps(|_| {});
+
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let admin: Addr = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetAdmin {}).unwrap()).unwrap();
+    assert_eq!(admin, Addr::unchecked(TEST_USER_ADDR), "wrong admin returned");
+}
+
+#[test]
+pub fn swap_fee_is_clamped_up_to_min_fee() {
+    let config = Config {
+        swap_fee_bps: 10,
+        min_fee: Uint128::new(5),
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+
+    assert_eq!(
+        config.swap_fee(Uint128::new(1000)),
+        Uint128::new(5),
+        "fee of 1 should be raised to the floor"
+    );
+    assert_eq!(
+        config.swap_fee(Uint128::new(10000)),
+        Uint128::new(10),
+        "fee above the floor should be kept"
+    );
+    assert_eq!(config.swap_fee(Uint128::new(3)), Uint128::new(3), "fee should not exceed the amount");
+}
+
+#[test]
+pub fn swap_fee_rounds_down_to_zero_on_tiny_amounts_without_min_fee() {
+    let config = Config {
+        swap_fee_bps: 30,
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+
+    assert_eq!(
+        config.swap_fee(Uint128::new(333)),
+        Uint128::zero(),
+        "fee below one unit should round down"
+    );
+    assert_eq!(
+        config.swap_fee(Uint128::new(334)),
+        Uint128::one(),
+        "fee should be charged once it reaches one unit"
+    );
+
+    let config = Config {
+        min_fee: Uint128::one(),
+        ..config
+    };
+    assert_eq!(config.swap_fee(Uint128::new(1)), Uint128::one(), "min_fee should charge the tiniest swap");
+    assert_eq!(config.swap_fee(Uint128::zero()), Uint128::zero(), "nothing swapped should pay nothing");
+}
+
+#[test]
+pub fn swap_fee_is_clamped_down_to_max_fee() {
+    let config = Config {
+        swap_fee_bps: 100,
+        max_fee: Some(Uint128::new(500)),
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+
+    assert_eq!(
+        config.swap_fee(Uint128::new(1_000_000)),
+        Uint128::new(500),
+        "fee of 10000 should be lowered to the ceiling"
+    );
+    assert_eq!(
+        config.swap_fee(Uint128::new(1000)),
+        Uint128::new(10),
+        "fee below the ceiling should be kept"
+    );
+}
+
+#[test]
+pub fn admin_can_clear_the_max_fee() {
+    let mut deps = inj_mock_deps(|_| {});
+    let config = Config {
+        max_fee: Some(Uint128::new(500)),
+        ..Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR))
+    };
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+    let info = message_info(&Addr::unchecked(TEST_USER_ADDR), &[]);
+
+    let msg: ExecuteMsg = from_json(r#"{"update_config":{"paused":false}}"#).unwrap();
+    execute(deps.as_mut(), mock_env(), info.to_owned(), msg).unwrap();
+    assert_eq!(
+        CONFIG.load(deps.as_mut_deps().storage).unwrap().max_fee,
+        Some(Uint128::new(500)),
+        "max_fee left out should be kept"
+    );
+
+    let msg: ExecuteMsg = from_json(r#"{"update_config":{"max_fee":null}}"#).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        CONFIG.load(deps.as_mut_deps().storage).unwrap().max_fee,
+        None,
+        "max_fee should be cleared"
+    );
+    assert!(
+        res.events[0].attributes.iter().any(|a| a.key == "max_fee" && a.value == "none"),
+        "cleared max_fee should be emitted"
+    );
+}
+
+#[test]
+pub fn admin_can_clear_the_burn_address() {
+    let mut deps = inj_mock_deps(|_| {});
+    let config = Config {
+        fee_burn_bps: 5000,
+        burn_address: Some(Addr::unchecked("burn")),
+        ..Config::new(Addr::unchecked("fee_recipient"), Addr::unchecked(TEST_USER_ADDR))
+    };
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let msg: ExecuteMsg = from_json(r#"{"update_config":{"burn_address":null}}"#).unwrap();
+    execute(deps.as_mut(), mock_env(), message_info(&Addr::unchecked(TEST_USER_ADDR), &[]), msg).unwrap();
+
+    let config = CONFIG.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(config.burn_address, None, "burn_address should be cleared");
+    assert_eq!(
+        config.fee_sends(&Coin::new(10u128, "eth"), &config.fee_recipient, &Addr::unchecked(TEST_CONTRACT_ADDR)),
+        vec![(config.fee_recipient.to_owned(), Coin::new(10u128, "eth"))],
+        "the whole fee should go to the fee recipient again"
+    );
+}
+
+#[test]
+pub fn admin_can_set_and_clear_large_swap_thresholds_per_denom() {
+    let mut deps = inj_mock_deps(|_| {});
+    let config = Config::new(Addr::unchecked(TEST_CONTRACT_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+    let set_threshold = |denom: &str, threshold: Option<Uint128>| ExecuteMsg::SetLargeSwapThreshold {
+        denom: denom.to_string(),
+        threshold,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&Addr::unchecked("other_user"), &[]),
+        set_threshold("eth", Some(Uint128::one())),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}), "wrong error: {err}");
+
+    let admin = message_info(&Addr::unchecked(TEST_USER_ADDR), &[]);
+    execute(deps.as_mut(), mock_env(), admin.to_owned(), set_threshold("eth", Some(Uint128::one()))).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.to_owned(),
+        set_threshold("usdt", Some(Uint128::new(1000000))),
+    )
+    .unwrap();
+    assert_eq!(
+        LARGE_SWAP_THRESHOLDS.load(deps.as_mut_deps().storage, "eth".to_string()).unwrap(),
+        Uint128::one()
+    );
+
+    execute(deps.as_mut(), mock_env(), admin, set_threshold("eth", None)).unwrap();
+    assert!(
+        !LARGE_SWAP_THRESHOLDS.has(deps.as_mut_deps().storage, "eth".to_string()),
+        "the eth threshold should be cleared"
+    );
+    assert!(
+        LARGE_SWAP_THRESHOLDS.has(deps.as_mut_deps().storage, "usdt".to_string()),
+        "other denoms should keep their threshold"
+    );
+}
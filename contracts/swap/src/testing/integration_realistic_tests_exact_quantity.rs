@@ -157,6 +157,10 @@ fn it_correctly_swaps_eth_to_get_very_high_exact_amount_of_atom() {
         &ExecuteMsg::SwapExactOutput {
             target_denom: ATOM.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -332,6 +336,10 @@ fn it_correctly_swaps_inj_to_get_very_high_exact_amount_of_atom() {
         &ExecuteMsg::SwapExactOutput {
             target_denom: ATOM.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -499,6 +507,10 @@ fn it_swaps_inj_to_get_very_high_exact_amount_of_eth() {
         &ExecuteMsg::SwapExactOutput {
             target_denom: ETH.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -652,6 +664,10 @@ fn it_correctly_swaps_between_markets_using_different_quote_assets_self_relaying
         &ExecuteMsg::SwapExactOutput {
             target_denom: USDC.to_string(),
             target_output_quantity: to_output_quantity,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -789,6 +805,10 @@ fn it_correctly_swaps_between_markets_using_different_quote_assets_self_relaying
         &ExecuteMsg::SwapExactOutput {
             target_denom: NINJA.to_string(),
             target_output_quantity: to_output_quantity,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(usdt_to_swap, USDT, Decimals::Six)],
         &swapper,
@@ -889,6 +909,10 @@ fn it_doesnt_lose_buffer_if_exact_swap_of_eth_to_atom_is_executed_multiple_times
             &ExecuteMsg::SwapExactOutput {
                 target_denom: ATOM.to_string(),
                 target_output_quantity: human_to_dec("906", Decimals::Six),
+                max_input: None,
+                compound_refund: false,
+                memo: None,
+                acknowledge_high_impact: false,
             },
             &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
             &swapper,
@@ -1000,6 +1024,10 @@ fn it_reverts_when_funds_provided_are_below_required_to_get_exact_amount() {
             &ExecuteMsg::SwapExactOutput {
                 target_denom: ATOM.to_string(),
                 target_output_quantity: exact_quantity_to_receive,
+                max_input: None,
+                compound_refund: false,
+                memo: None,
+                acknowledge_high_impact: false,
             },
             &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
             &swapper,
@@ -1107,6 +1135,10 @@ fn exact_two_hop_eth_atom_swap_test_template(exact_quantity_to_receive: FPDecima
         &ExecuteMsg::SwapExactOutput {
             target_denom: ATOM.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -1234,6 +1266,10 @@ fn exact_two_hop_inj_atom_swap_test_template(exact_quantity_to_receive: FPDecima
         &ExecuteMsg::SwapExactOutput {
             target_denom: ATOM.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -1360,6 +1396,10 @@ fn exact_two_hop_inj_eth_swap_test_template(exact_quantity_to_receive: FPDecimal
         &ExecuteMsg::SwapExactOutput {
             target_denom: ETH.to_string(),
             target_output_quantity: exact_quantity_to_receive,
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -13,6 +13,7 @@ use crate::{
     types::{FPCoin, SwapEstimationResult},
 };
 
+use cosmwasm_std::Coin;
 use injective_math::FPDecimal;
 use injective_test_tube::{Account, Bank, Exchange, InjectiveTestApp, Module, RunnerResult, SigningAccount, Wasm};
 use std::ops::Neg;
@@ -111,6 +112,12 @@ pub fn happy_path_two_hops_test(app: InjectiveTestApp, owner: SigningAccount, co
         &ExecuteMsg::SwapMinOutput {
             target_denom: ATOM.to_string(),
             min_output_quantity: FPDecimal::from(906u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -284,6 +291,12 @@ fn happy_path_two_hops_swap_inj_eth_realistic_values_self_relaying() {
         &ExecuteMsg::SwapMinOutput {
             target_denom: ETH.to_string(),
             min_output_quantity: FPDecimal::from(906u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -433,6 +446,12 @@ fn happy_path_two_hops_swap_inj_atom_realistic_values_self_relaying() {
         &ExecuteMsg::SwapMinOutput {
             target_denom: ATOM.to_string(),
             min_output_quantity: FPDecimal::from(944u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -581,6 +600,12 @@ fn it_executes_swap_between_markets_using_different_quote_assets_self_relaying()
         &ExecuteMsg::SwapMinOutput {
             target_denom: USDC.to_string(),
             min_output_quantity: FPDecimal::from(8u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(inj_to_swap, INJ_2, Decimals::Eighteen)],
         &swapper,
@@ -728,6 +753,12 @@ fn it_doesnt_lose_buffer_if_executed_multiple_times() {
             &ExecuteMsg::SwapMinOutput {
                 target_denom: ATOM.to_string(),
                 min_output_quantity: FPDecimal::from(906u128),
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps: None,
+                min_output_value: None,
+                slices: None,
+                acknowledge_high_impact: false,
             },
             &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
             &swapper,
@@ -851,6 +882,12 @@ fn it_correctly_calculates_required_funds_when_querying_buy_with_minimum_buffer_
         &ExecuteMsg::SwapMinOutput {
             target_denom: ATOM.to_string(),
             min_output_quantity: FPDecimal::from(906u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -963,6 +1000,12 @@ fn it_correctly_calculates_required_funds_when_executing_buy_with_minimum_buffer
         &ExecuteMsg::SwapMinOutput {
             target_denom: ATOM.to_string(),
             min_output_quantity: FPDecimal::from(906u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -1082,6 +1125,12 @@ fn it_returns_all_funds_if_there_is_not_enough_buffer_realistic_values() {
         &ExecuteMsg::SwapMinOutput {
             target_denom: ATOM.to_string(),
             min_output_quantity: FPDecimal::from(906u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
         },
         &[str_coin(eth_to_swap, ETH, Decimals::Eighteen)],
         &swapper,
@@ -1106,3 +1155,45 @@ fn it_returns_all_funds_if_there_is_not_enough_buffer_realistic_values() {
         "contract balance has changed after failed swap"
     );
 }
+
+#[test]
+fn it_pages_through_the_contract_balances_by_denom() {
+    let app = InjectiveTestApp::new();
+    let wasm = Wasm::new(&app);
+
+    let validator = app.get_first_validator_signing_account(INJ.to_string(), 1.2f64).unwrap();
+    let owner = must_init_account_with_funds_and_setting_denoms(
+        &app,
+        &validator,
+        &[
+            initial_coin("1", ETH, Decimals::Eighteen),
+            initial_coin("1", ATOM, Decimals::Six),
+            initial_coin("1_000", USDT, Decimals::Six),
+            initial_coin("10_000", INJ, Decimals::Eighteen),
+        ],
+    );
+    let contract_funds = [
+        str_coin("1", ATOM, Decimals::Six),
+        str_coin("1", ETH, Decimals::Eighteen),
+        str_coin("1_000", USDT, Decimals::Six),
+    ];
+    let contr_addr = init_self_relaying_contract_and_get_address(&wasm, &owner, &contract_funds);
+
+    let contract_balances = |start_after: Option<&str>, limit: Option<u32>| -> Vec<Coin> {
+        wasm.query(
+            &contr_addr,
+            &QueryMsg::GetContractBalances {
+                start_after: start_after.map(str::to_string),
+                limit,
+            },
+        )
+        .unwrap()
+    };
+
+    assert_eq!(contract_balances(None, Some(2)), contract_funds[..2].to_vec());
+    assert_eq!(contract_balances(Some(ETH), Some(2)), contract_funds[2..].to_vec());
+    assert!(
+        contract_balances(Some(USDT), None).is_empty(),
+        "no balances should be left after the last denom"
+    );
+}
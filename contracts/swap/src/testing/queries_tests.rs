@@ -1,22 +1,25 @@
 use crate::{
     admin::set_route,
-    contract::instantiate,
-    helpers::Scaled,
-    msg::{FeeRecipient, InstantiateMsg},
-    queries::{estimate_swap_result, SwapQuantity},
-    state::get_all_swap_routes,
+    contract::{instantiate, query},
+    helpers::{calculate_fee, Scaled},
+    msg::{FeeRecipient, InstantiateMsg, QueryMsg},
+    queries::{estimate_required_input, estimate_single_swap_execution, estimate_swap_result, SwapQuantity},
+    state::{get_all_swap_routes, store_swap_route, CONFIG, LARGE_SWAP_COMMITS, LARGE_SWAP_THRESHOLDS, MAX_MEMO_LENGTH, MAX_SWAP_SLICES, STEP_STATE},
     testing::test_utils::{
-        are_fpdecimals_approximately_equal, human_to_dec, mock_deps_eth_inj, mock_realistic_deps_eth_atom, Decimals, MultiplierQueryBehavior,
-        TEST_USER_ADDR,
+        are_fpdecimals_approximately_equal, create_missing_spot_market_handler, human_to_dec, mock_deps_eth_inj, mock_realistic_deps_eth_atom,
+        Decimals, MultiplierQueryBehavior, TEST_USER_ADDR,
+    },
+    types::{
+        Config, CurrentSwapStep, FPCoin, FeeBudgetResponse, LargeSwapCommit, LimitsResponse, QuoteResponse, SwapBoundsResponse, SwapEstimationAmount,
+        SwapMode, SwapQuantityMode, SwapRoute, SwapRouteResponse, SwappableResponse,
     },
-    types::{FPCoin, SwapRoute},
 };
 use cosmwasm_std::{
-    coin,
+    coin, from_json,
     testing::{message_info, mock_env},
-    Addr,
+    Addr, Deps, DepsMut, StdResult, Uint128,
 };
-use injective_cosmwasm::{OwnedDepsExt, TEST_MARKET_ID_1, TEST_MARKET_ID_2};
+use injective_cosmwasm::{InjectiveQueryWrapper, MarketId, OwnedDepsExt, TEST_MARKET_ID_1, TEST_MARKET_ID_2, TEST_MARKET_ID_3};
 use injective_math::FPDecimal;
 
 use std::ops::Neg;
@@ -39,6 +42,7 @@ fn test_calculate_swap_price_external_fee_recipient_from_source_quantity() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::Address(admin.to_owned()),
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -114,6 +118,7 @@ fn test_calculate_swap_price_external_fee_recipient_from_target_quantity() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::Address(admin.to_owned()),
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -188,6 +193,7 @@ fn test_calculate_swap_price_self_fee_recipient_from_source_quantity() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::SwapContract,
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -259,6 +265,7 @@ fn test_calculate_swap_price_self_fee_recipient_from_target_quantity() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::SwapContract,
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -336,6 +343,7 @@ fn test_calculate_estimate_when_selling_both_quantity_directions_simple() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::Address(admin.to_owned()),
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -440,6 +448,7 @@ fn test_calculate_estimate_when_buying_both_quantity_directions_simple() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::Address(admin.to_owned()),
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -529,6 +538,7 @@ fn get_all_queries_returns_empty_array_if_no_routes_are_set() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::SwapContract,
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -551,6 +561,7 @@ fn get_all_queries_returns_expected_array_if_routes_are_set() {
         InstantiateMsg {
             fee_recipient: FeeRecipient::SwapContract,
             admin: admin.to_owned(),
+            initial_routes: vec![],
         },
     )
     .unwrap();
@@ -589,18 +600,24 @@ fn get_all_queries_returns_expected_array_if_routes_are_set() {
         source_denom: "eth".to_string(),
         target_denom: "inj".to_string(),
         steps: vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+        enabled: true,
+        fee_bps: None,
     };
 
     let eth_usdt_route = SwapRoute {
         source_denom: "eth".to_string(),
         target_denom: "usdt".to_string(),
         steps: vec![TEST_MARKET_ID_1.into()],
+        enabled: true,
+        fee_bps: None,
     };
 
     let usdt_inj_route = SwapRoute {
         source_denom: "usdt".to_string(),
         target_denom: "inj".to_string(),
         steps: vec![TEST_MARKET_ID_2.into()],
+        enabled: true,
+        fee_bps: None,
     };
 
     let all_routes = all_routes_result.unwrap();
@@ -613,3 +630,674 @@ fn get_all_queries_returns_expected_array_if_routes_are_set() {
     let all_routes_result_paginated = get_all_swap_routes(deps.as_ref().storage, None, Some(1u32));
     assert_eq!(all_routes_result_paginated.unwrap().len(), 1);
 }
+
+#[test]
+fn test_break_even_output_is_above_fee_free_estimate_by_fees() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = &Addr::unchecked(TEST_USER_ADDR);
+
+    instantiate(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(admin, &[]),
+        InstantiateMsg {
+            fee_recipient: FeeRecipient::Address(admin.to_owned()),
+            admin: admin.to_owned(),
+            initial_routes: vec![],
+        },
+    )
+    .unwrap();
+    set_route(
+        deps.as_mut_deps(),
+        admin,
+        "eth".to_string(),
+        "usdt".to_string(),
+        vec![TEST_MARKET_ID_1.into()],
+    )
+    .unwrap();
+
+    let estimation = estimate_swap_result(
+        deps.as_ref(),
+        &mock_env(),
+        "eth".to_string(),
+        "usdt".to_string(),
+        SwapQuantity::InputQuantity(FPDecimal::ONE),
+    )
+    .unwrap();
+    let fee = estimation.expected_fees[0].amount;
+    let fee_free_output = estimation.result_quantity + fee;
+    assert_eq!(fee_free_output, FPDecimal::from(201000u128), "fee-free output should use the best bid");
+
+    let break_even_output: FPDecimal = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBreakEvenOutput {
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+                input_amount: FPDecimal::ONE,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        break_even_output,
+        fee_free_output + fee,
+        "break-even output should exceed the fee-free output by the fee"
+    );
+}
+
+fn query_is_swappable(deps: Deps<InjectiveQueryWrapper>, source_denom: &str, target_denom: &str) -> SwappableResponse {
+    from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::IsSwappable {
+                source_denom: source_denom.to_string(),
+                target_denom: target_denom.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+fn instantiate_with_eth_usdt_route(mut deps: DepsMut<InjectiveQueryWrapper>) {
+    let admin = Addr::unchecked(TEST_USER_ADDR);
+
+    instantiate(
+        deps.branch(),
+        mock_env(),
+        message_info(&admin, &[]),
+        InstantiateMsg {
+            fee_recipient: FeeRecipient::Address(admin.to_owned()),
+            admin: admin.to_owned(),
+            initial_routes: vec![],
+        },
+    )
+    .unwrap();
+    set_route(deps, &admin, "eth".to_string(), "usdt".to_string(), vec![TEST_MARKET_ID_1.into()]).unwrap();
+}
+
+#[test]
+fn test_is_swappable_for_pair_with_route() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let response = query_is_swappable(deps.as_ref(), "eth", "usdt");
+
+    assert_eq!(response, SwappableResponse::swappable());
+}
+
+#[test]
+fn test_is_not_swappable_when_paused() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<_> {
+            Ok(Config { paused: true, ..config })
+        })
+        .unwrap();
+
+    let response = query_is_swappable(deps.as_ref(), "eth", "usdt");
+
+    assert_eq!(response, SwappableResponse::not_swappable("Contract is paused"));
+}
+
+#[test]
+fn test_is_not_swappable_without_route() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let response = query_is_swappable(deps.as_ref(), "eth", "inj");
+
+    assert!(!response.swappable, "pair without route should not be swappable");
+    assert!(
+        response.reason.unwrap().contains("No swap route not found from eth to inj"),
+        "wrong reason"
+    );
+}
+
+#[test]
+fn test_min_effective_input_is_the_smallest_input_delivering_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let min_input: Uint128 = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetMinEffectiveInput {
+                source_denom: "usdt".to_string(),
+                target_denom: "eth".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    let delivers_output = |input: Uint128| {
+        estimate_swap_result(
+            deps.as_ref(),
+            &mock_env(),
+            "usdt".to_string(),
+            "eth".to_string(),
+            SwapQuantity::InputQuantity(input.into()),
+        )
+        .map_or(false, |estimation| estimation.result_quantity >= FPDecimal::ONE)
+    };
+    assert!(delivers_output(min_input), "{min_input}usdt should buy some eth");
+    assert!(
+        delivers_output(min_input + Uint128::new(1000)),
+        "more than {min_input}usdt should buy some eth"
+    );
+    assert!(
+        !delivers_output(min_input - Uint128::one()),
+        "less than {min_input}usdt should buy no eth"
+    );
+    assert!(
+        !delivers_output(min_input / Uint128::new(2)),
+        "less than {min_input}usdt should buy no eth"
+    );
+}
+
+#[test]
+fn test_min_effective_input_fails_for_a_route_without_steps() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    store_swap_route(
+        deps.as_mut_deps().storage,
+        &SwapRoute {
+            steps: vec![],
+            source_denom: "usdt".to_string(),
+            target_denom: "eth".to_string(),
+            enabled: true,
+            fee_bps: None,
+        },
+    )
+    .unwrap();
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetMinEffectiveInput {
+            source_denom: "usdt".to_string(),
+            target_denom: "eth".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert!(err.to_string().contains("Route from usdt to eth has no steps"), "wrong error: {err}");
+}
+
+fn query_can_swap(deps: Deps<InjectiveQueryWrapper>, sender: &Addr) -> SwappableResponse {
+    from_json(query(deps, mock_env(), QueryMsg::CanSwap { sender: sender.to_owned() }).unwrap()).unwrap()
+}
+
+#[test]
+fn test_sender_can_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let response = query_can_swap(deps.as_ref(), &Addr::unchecked(TEST_USER_ADDR));
+
+    assert_eq!(response, SwappableResponse::swappable());
+}
+
+#[test]
+fn test_sender_cannot_swap_when_paused() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<_> {
+            Ok(Config { paused: true, ..config })
+        })
+        .unwrap();
+
+    let response = query_can_swap(deps.as_ref(), &Addr::unchecked(TEST_USER_ADDR));
+
+    assert_eq!(response, SwappableResponse::not_swappable("Contract is paused"));
+}
+
+#[test]
+fn test_contract_cannot_swap_on_its_own_behalf() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let response = query_can_swap(deps.as_ref(), &mock_env().contract.address);
+
+    assert_eq!(response, SwappableResponse::not_swappable("Contract cannot swap on its own behalf"));
+}
+
+#[test]
+fn test_sender_cannot_swap_while_another_swap_is_in_flight() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let current_step = CurrentSwapStep {
+        step_idx: 0,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(9u128),
+            denom: "eth".to_string(),
+        },
+        step_target_denom: "usdt".to_string(),
+        is_buy: false,
+        retry_count: 0,
+    };
+    STEP_STATE.save(deps.as_mut_deps().storage, &current_step).unwrap();
+
+    let response = query_can_swap(deps.as_ref(), &Addr::unchecked(TEST_USER_ADDR));
+
+    assert_eq!(response, SwappableResponse::not_swappable("Another swap is in flight"));
+}
+
+#[test]
+fn test_sender_cannot_swap_with_a_pending_large_swap_commit() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let commit = LargeSwapCommit {
+        target_denom: "usdt".to_string(),
+        swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+        funds: vec![coin(10u128, "eth")],
+        committed_at_height: mock_env().block.height,
+        acknowledge_high_impact: false,
+    };
+    LARGE_SWAP_COMMITS
+        .save(deps.as_mut_deps().storage, &Addr::unchecked(TEST_USER_ADDR), &commit)
+        .unwrap();
+
+    let response = query_can_swap(deps.as_ref(), &Addr::unchecked(TEST_USER_ADDR));
+    assert_eq!(response, SwappableResponse::not_swappable("Sender has a pending large swap commit"));
+
+    let response = query_can_swap(deps.as_ref(), &Addr::unchecked("other_user"));
+    assert_eq!(response, SwappableResponse::swappable(), "other senders should still be able to swap");
+}
+
+#[test]
+fn test_broken_routes_lists_routes_through_delisted_markets() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    // the route was set while its market was listed, the mocked querier doesn't know it anymore
+    let broken_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_3)],
+        source_denom: "eth".to_string(),
+        target_denom: "atom".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+    store_swap_route(deps.as_mut_deps().storage, &broken_route).unwrap();
+
+    let broken_routes: Vec<SwapRoute> = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetBrokenRoutes {}).unwrap()).unwrap();
+
+    assert_eq!(
+        broken_routes,
+        vec![broken_route],
+        "only the route through the delisted market should be broken"
+    );
+}
+
+#[test]
+fn test_single_hop_integer_estimate_matches_fpdecimal_estimate_without_dust() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let estimate = |input_quantity: &str, use_integer_math: bool| {
+        estimate_single_swap_execution(
+            &deps.as_ref(),
+            &mock_env(),
+            &MarketId::unchecked(TEST_MARKET_ID_1),
+            SwapEstimationAmount::InputQuantity(FPCoin {
+                amount: FPDecimal::must_from_str(input_quantity),
+                denom: "eth".to_string(),
+            }),
+            true,
+            use_integer_math,
+        )
+        .unwrap()
+    };
+
+    for input_quantity in ["1", "2", "5"] {
+        let integer_estimate = estimate(input_quantity, true);
+        let fpdecimal_estimate = estimate(input_quantity, false);
+        let integer_fee = integer_estimate.fee_estimate.unwrap().amount;
+        let fpdecimal_fee = fpdecimal_estimate.fee_estimate.unwrap().amount;
+
+        assert_eq!(
+            integer_estimate.result_quantity,
+            fpdecimal_estimate.result_quantity.int(),
+            "integer path should drop the dust of selling {input_quantity} eth"
+        );
+        assert_eq!(
+            integer_estimate.result_quantity + integer_fee,
+            fpdecimal_estimate.result_quantity + fpdecimal_fee,
+            "both paths should split the same proceeds of selling {input_quantity} eth"
+        );
+    }
+
+    // fractional quantities fall back to FPDecimal math
+    let integer_estimate = estimate("1.5", true);
+    let fpdecimal_estimate = estimate("1.5", false);
+    assert_eq!(integer_estimate.result_quantity, fpdecimal_estimate.result_quantity);
+
+    let estimation = estimate_swap_result(
+        deps.as_ref(),
+        &mock_env(),
+        "eth".to_string(),
+        "usdt".to_string(),
+        SwapQuantity::InputQuantity(FPDecimal::ONE),
+    )
+    .unwrap();
+    assert_eq!(
+        estimation.result_quantity,
+        FPDecimal::from(200497u128),
+        "single hop should use the integer path"
+    );
+    assert_eq!(estimation.expected_fees[0].amount, FPDecimal::from(503u128));
+}
+
+#[test]
+fn test_gross_input_exceeds_required_input_by_contract_fee() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+            Ok(Config { swap_fee_bps: 100, ..config })
+        })
+        .unwrap();
+
+    let (_, required_input) = estimate_required_input(deps.as_ref(), &mock_env(), "usdt", "eth", FPDecimal::ONE).unwrap();
+
+    let gross_input: FPDecimal = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetGrossInputQuantity {
+                to_quantity: FPDecimal::ONE,
+                source_denom: "usdt".to_string(),
+                target_denom: "eth".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    let fee = FPDecimal::from(calculate_fee(required_input.into(), 100));
+    assert!(!fee.is_zero(), "fee should be charged");
+    assert_eq!(
+        gross_input - required_input,
+        fee,
+        "gross input should exceed the required input by the fee"
+    );
+}
+
+fn query_quote(deps: Deps<InjectiveQueryWrapper>) -> QuoteResponse {
+    from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::GetQuote {
+                from_quantity: FPDecimal::ONE,
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+                sender: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_quote_matches_output_estimate() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let quote = query_quote(deps.as_ref());
+    assert_eq!(quote.result_quantity, Some(FPDecimal::from(200497u128)));
+    assert_eq!(quote.expected_fees.len(), 1);
+    assert!(quote.error.is_none());
+}
+
+#[test]
+fn test_quote_reports_failing_market_query_instead_of_failing() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Fail);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let quote = query_quote(deps.as_ref());
+    assert!(quote.result_quantity.is_none(), "no output expected when estimation fails");
+    assert!(quote.expected_fees.is_empty());
+    let error = quote.error.expect("error should be reported");
+    assert!(error.contains(TEST_MARKET_ID_1), "error should name the failing market: {error}");
+}
+
+#[test]
+fn test_quote_reports_missing_market_instead_of_panicking() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    deps.querier.spot_market_response_handler = create_missing_spot_market_handler();
+
+    let quote = query_quote(deps.as_ref());
+    assert!(quote.result_quantity.is_none(), "no output expected without a market");
+    let error = quote.error.expect("error should be reported");
+    assert!(error.contains(TEST_MARKET_ID_1), "error should name the missing market: {error}");
+}
+
+#[test]
+fn test_output_for_fee_budget_stays_within_budget() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+            Ok(Config {
+                swap_fee_bps: 5000,
+                ..config
+            })
+        })
+        .unwrap();
+    let max_fee = Uint128::one();
+
+    let response: FeeBudgetResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOutputForFeeBudget {
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+                max_fee,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(response.fee <= max_fee, "fee {} exceeds the budget", response.fee);
+    assert_eq!(response.input_quantity, Uint128::new(3), "input is not the largest within the budget");
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert!(
+        config.swap_fee(response.input_quantity + Uint128::one()) > max_fee,
+        "a larger input should exceed the budget"
+    );
+    assert!(response.result_quantity.is_positive(), "output should be estimated");
+}
+
+#[test]
+fn test_swap_bounds_reflect_market_params() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let bounds: SwapBoundsResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetSwapBounds {
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        bounds.min_input,
+        FPDecimal::must_from_str("0.001"),
+        "min input should be one quantity tick"
+    );
+    assert_eq!(bounds.max_input, FPDecimal::from(12u128), "max input should be the buy side depth");
+}
+
+#[test]
+fn test_limits_reflect_config() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<_> {
+            Ok(Config {
+                paused: true,
+                swap_fee_bps: 30,
+                max_fee: Some(Uint128::new(1000)),
+                max_allowed_slippage_bps: Some(500),
+                ..config
+            })
+        })
+        .unwrap();
+    LARGE_SWAP_THRESHOLDS
+        .save(deps.as_mut_deps().storage, "usdt".to_string(), &Uint128::new(1000000))
+        .unwrap();
+
+    let limits: LimitsResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetLimits {}).unwrap()).unwrap();
+
+    assert_eq!(
+        limits,
+        LimitsResponse {
+            paused: true,
+            swap_fee_bps: 30,
+            min_fee: Uint128::zero(),
+            max_fee: Some(Uint128::new(1000)),
+            max_allowed_slippage_bps: Some(500),
+            max_inputs: vec![coin(1000000u128, "usdt")],
+            max_swap_slices: MAX_SWAP_SLICES,
+            max_memo_length: MAX_MEMO_LENGTH,
+        }
+    );
+}
+
+#[test]
+fn test_gas_estimate_grows_with_route_steps() {
+    let deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let query_gas_estimate = |mode: SwapMode, route_steps: u32| -> u64 {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetGasEstimate { mode, route_steps }).unwrap()).unwrap()
+    };
+
+    for mode in [SwapMode::MinOutput, SwapMode::ExactOutput] {
+        let estimates: Vec<u64> = (1..=3).map(|route_steps| query_gas_estimate(mode.to_owned(), route_steps)).collect();
+        assert!(
+            estimates.windows(2).all(|pair| pair[0] < pair[1]),
+            "{mode:?} estimate should grow with the step count: {estimates:?}"
+        );
+    }
+    assert!(
+        query_gas_estimate(SwapMode::ExactOutput, 2) > query_gas_estimate(SwapMode::MinOutput, 2),
+        "exact output swaps should be estimated above min output swaps"
+    );
+}
+
+#[test]
+fn test_routes_include_the_denoms_they_pass_through() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+
+    let route: SwapRouteResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetRoute {
+                source_denom: "eth".to_string(),
+                target_denom: "inj".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(route.denoms, vec!["eth", "usdt", "inj"], "wrong denom chain of the eth -> inj route");
+
+    let routes: Vec<SwapRouteResponse> = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAllRoutes {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let denom_chains: Vec<Vec<String>> = routes.into_iter().map(|route| route.denoms).collect();
+    assert_eq!(
+        denom_chains,
+        vec![vec!["eth", "usdt", "inj"], vec!["eth", "usdt"]],
+        "wrong denom chains of all routes"
+    );
+}
+
+#[test]
+fn test_implied_rate_composes_the_mid_prices_of_each_hop() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let admin = Addr::unchecked(TEST_USER_ADDR);
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "usdt".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+
+    let implied_rate = |source_denom: &str, target_denom: &str| -> FPDecimal {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetImpliedRate {
+                    source_denom: source_denom.to_string(),
+                    target_denom: target_denom.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let eth_usdt_rate = implied_rate("eth", "usdt");
+    let usdt_inj_rate = implied_rate("usdt", "inj");
+    assert_eq!(eth_usdt_rate, FPDecimal::from(201000u128), "selling eth should go at the eth mid price");
+    assert_eq!(
+        usdt_inj_rate,
+        FPDecimal::must_from_str("0.00125"),
+        "buying inj should go at the inverse of the inj mid price"
+    );
+    assert_eq!(
+        implied_rate("eth", "inj"),
+        eth_usdt_rate * usdt_inj_rate,
+        "the multi-hop rate should be the product of the hop rates"
+    );
+    assert_eq!(implied_rate("eth", "inj"), FPDecimal::must_from_str("251.25"));
+}
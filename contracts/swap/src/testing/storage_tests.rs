@@ -1,10 +1,11 @@
 use crate::{
-    admin::{delete_route, set_route},
-    state::{read_swap_route, store_swap_route, CONFIG},
-    testing::test_utils::{mock_deps_eth_inj, MultiplierQueryBehavior, TEST_CONTRACT_ADDR, TEST_USER_ADDR},
+    admin::{delete_route, set_route, set_routes},
+    error::ContractError,
+    state::{get_reachable_targets, get_routes_to_target, get_source_denoms, get_swap_routes_by_market, read_swap_route, store_swap_route, CONFIG},
+    testing::test_utils::{mock_deps_eth_inj, mock_deps_eth_inj_with_second_eth_market, MultiplierQueryBehavior, TEST_CONTRACT_ADDR, TEST_USER_ADDR},
     types::{Config, SwapRoute},
 };
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Event};
 use injective_cosmwasm::{inj_mock_deps, MarketId, OwnedDepsExt, TEST_MARKET_ID_1, TEST_MARKET_ID_2, TEST_MARKET_ID_3};
 
 #[test]
@@ -17,6 +18,8 @@ fn it_can_store_and_read_swap_route() {
         steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
         source_denom: source_denom.to_string(),
         target_denom: target_denom.to_string(),
+        enabled: true,
+        fee_bps: None,
     };
 
     store_swap_route(deps.as_mut().storage, &route).unwrap();
@@ -42,6 +45,8 @@ fn it_can_update_and_read_swap_route() {
         steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
         source_denom: source_denom.to_string(),
         target_denom: target_denom.to_string(),
+        enabled: true,
+        fee_bps: None,
     };
 
     store_swap_route(deps.as_mut().storage, &route).unwrap();
@@ -55,6 +60,8 @@ fn it_can_update_and_read_swap_route() {
         steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
         source_denom: source_denom.to_string(),
         target_denom: new_target_denom.to_string(),
+        enabled: true,
+        fee_bps: None,
     };
 
     store_swap_route(deps.as_mut().storage, &updated_route).unwrap();
@@ -70,10 +77,7 @@ fn owner_can_set_valid_route() {
     let target_denom = "inj".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -103,10 +107,7 @@ fn owner_cannot_set_route_for_markets_using_target_denom_not_found_on_target_mar
     let target_denom = "atom".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -118,9 +119,10 @@ fn owner_cannot_set_route_for_markets_using_target_denom_not_found_on_target_mar
     );
 
     assert!(result.is_err(), "result was ok");
+    let err = result.unwrap_err();
     assert!(
-        result.unwrap_err().to_string().contains("Target denom not found in last market"),
-        "wrong error message"
+        matches!(&err, ContractError::RouteDenomMismatch { denom, market_id } if denom == "atom" && market_id.as_str() == TEST_MARKET_ID_2),
+        "wrong error: {err}"
     );
 
     let stored_route = read_swap_route(&deps.storage, &source_denom, &target_denom);
@@ -134,10 +136,7 @@ fn owner_cannot_set_route_for_markets_using_source_denom_not_present_on_source_m
     let target_denom = "eth".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -149,9 +148,10 @@ fn owner_cannot_set_route_for_markets_using_source_denom_not_present_on_source_m
     );
 
     assert!(result.is_err(), "result was ok");
+    let err = result.unwrap_err();
     assert!(
-        result.unwrap_err().to_string().contains("Source denom not found in first market"),
-        "wrong error message"
+        matches!(&err, ContractError::RouteDenomMismatch { denom, market_id } if denom == "atom" && market_id.as_str() == TEST_MARKET_ID_1),
+        "wrong error: {err}"
     );
 
     let stored_route = read_swap_route(&deps.storage, &source_denom, &target_denom);
@@ -165,10 +165,7 @@ fn owner_can_set_route_single_step_route() {
     let target_denom = "usdt".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -198,10 +195,7 @@ fn owner_can_set_route_single_step_route_with_reverted_denoms() {
     let target_denom = "eth".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -231,10 +225,7 @@ fn it_returns_error_when_setting_route_for_the_same_denom_as_target_and_source()
     let target_denom = "eth".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
 
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
@@ -266,10 +257,7 @@ fn it_returns_error_when_setting_route_with_nonexistent_market_id() {
     let target_denom = "usdt".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_3)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
 
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
@@ -284,6 +272,10 @@ fn it_returns_error_when_setting_route_with_nonexistent_market_id() {
     assert!(result.is_err(), "Could set a route for non-existent market");
     let err_result = result.unwrap_err();
 
+    assert!(
+        matches!(&err_result, ContractError::MarketNotFound { market_id } if market_id.as_str() == TEST_MARKET_ID_3),
+        "wrong error: {err_result}"
+    );
     assert!(
         err_result.to_string().contains(&format!("Market {TEST_MARKET_ID_3} not found")),
         "wrong error message"
@@ -293,6 +285,87 @@ fn it_returns_error_when_setting_route_with_nonexistent_market_id() {
     assert!(stored_route.is_err(), "Could read a route for non-existent market");
 }
 
+#[test]
+fn it_collapses_round_trips_through_a_quote_denom_when_setting_a_route() {
+    let mut deps = mock_deps_eth_inj_with_second_eth_market();
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    // usdt -> eth -> usdt -> inj, the first two steps only go back to usdt
+    let response = set_route(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        "usdt".to_string(),
+        "inj".to_string(),
+        vec![
+            MarketId::unchecked(TEST_MARKET_ID_1),
+            MarketId::unchecked(TEST_MARKET_ID_3),
+            MarketId::unchecked(TEST_MARKET_ID_2),
+        ],
+    )
+    .unwrap();
+
+    let stored_route = read_swap_route(&deps.storage, "usdt", "inj").unwrap();
+    assert_eq!(
+        stored_route.steps,
+        vec![MarketId::unchecked(TEST_MARKET_ID_2)],
+        "round trip was not collapsed"
+    );
+    assert!(
+        response
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "warning" && attr.value.starts_with("dropped 2 steps of the route from usdt to inj")),
+        "collapsing should be warned about"
+    );
+}
+
+#[test]
+fn it_rejects_a_route_collapsing_to_no_steps() {
+    let mut deps = mock_deps_eth_inj_with_second_eth_market();
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    // usdt -> eth -> usdt only goes back to the source
+    let err = set_route(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        "usdt".to_string(),
+        "eth".to_string(),
+        vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_3)],
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::EmptyRoute { source_denom, target_denom } if source_denom == "usdt" && target_denom == "eth"),
+        "wrong error: {err}"
+    );
+    assert!(read_swap_route(&deps.storage, "usdt", "eth").is_err(), "route should not be stored");
+}
+
+#[test]
+fn it_rejects_a_route_whose_denoms_do_not_end_at_the_target() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    // eth -> usdt -> inj, the last market trades usdt but the route ends in inj
+    let err = set_route(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        "eth".to_string(),
+        "usdt".to_string(),
+        vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::RouteDenomMismatch { denom, market_id } if denom == "usdt" && market_id.as_str() == TEST_MARKET_ID_2),
+        "wrong error: {err}"
+    );
+    assert!(read_swap_route(&deps.storage, "eth", "usdt").is_err(), "route should not be stored");
+}
+
 #[test]
 fn it_returns_error_when_setting_route_with_no_market_ids() {
     let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
@@ -300,10 +373,7 @@ fn it_returns_error_when_setting_route_with_no_market_ids() {
     let target_denom = "usdt".to_string();
     let route = vec![];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
 
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
@@ -332,10 +402,7 @@ fn it_returns_error_when_setting_route_with_duplicated_market_ids() {
     let target_denom = "usdt".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_1)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
 
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
@@ -348,15 +415,44 @@ fn it_returns_error_when_setting_route_with_duplicated_market_ids() {
     );
 
     assert!(result.is_err(), "Could set a route that begins and ends with the same market");
+    let err = result.unwrap_err();
     assert!(
-        result.unwrap_err().to_string().contains("Route cannot have duplicate steps"),
-        "wrong error message"
+        matches!(&err, ContractError::DuplicateMarketInRoute { market_id } if market_id.as_str() == TEST_MARKET_ID_1),
+        "wrong error: {err}"
     );
 
     let stored_route = read_swap_route(&deps.storage, &source_denom, &target_denom);
     assert!(stored_route.is_err(), "Could read a route that begins and ends with the same market");
 }
 
+#[test]
+fn it_returns_error_when_setting_route_repeating_a_market_after_another() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let route = vec![
+        MarketId::unchecked(TEST_MARKET_ID_1),
+        MarketId::unchecked(TEST_MARKET_ID_2),
+        MarketId::unchecked(TEST_MARKET_ID_1),
+    ];
+
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let err = set_route(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        "eth".to_string(),
+        "inj".to_string(),
+        route,
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::DuplicateMarketInRoute { market_id } if market_id.as_str() == TEST_MARKET_ID_1),
+        "wrong error: {err}"
+    );
+    assert!(read_swap_route(&deps.storage, "eth", "inj").is_err(), "route was set");
+}
+
 #[test]
 fn it_returns_error_if_non_admin_tries_to_set_route() {
     let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
@@ -364,10 +460,7 @@ fn it_returns_error_if_non_admin_tries_to_set_route() {
     let target_denom = "inj".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let result = set_route(
@@ -395,10 +488,7 @@ fn it_allows_admint_to_delete_existing_route() {
     let target_denom = "inj".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let set_result = set_route(
@@ -427,6 +517,37 @@ fn it_allows_admint_to_delete_existing_route() {
     );
 }
 
+#[test]
+fn it_emits_events_when_routes_are_set_and_deleted() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = Addr::unchecked(TEST_USER_ADDR);
+    let config = Config::new(admin.to_owned(), admin.to_owned());
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+    let expected_route_event = |name: &str| {
+        Event::new(name)
+            .add_attribute("source_denom", "eth")
+            .add_attribute("target_denom", "inj")
+            .add_attribute("steps_count", "2")
+            .add_attribute("enabled", "true")
+    };
+
+    let set_response = set_route(
+        deps.as_mut(),
+        &admin,
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+    )
+    .unwrap();
+    assert_eq!(set_response.events, vec![expected_route_event("route_set")]);
+
+    let delete_response = delete_route(deps.as_mut(), &admin, "eth".to_string(), "inj".to_string()).unwrap();
+    assert_eq!(delete_response.events, vec![expected_route_event("route_deleted")]);
+
+    let repeated_delete_response = delete_route(deps.as_mut(), &admin, "eth".to_string(), "inj".to_string()).unwrap();
+    assert!(repeated_delete_response.events.is_empty(), "no route was deleted");
+}
+
 #[test]
 fn it_doesnt_fail_if_admin_deletes_non_existent_route() {
     let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
@@ -434,10 +555,7 @@ fn it_doesnt_fail_if_admin_deletes_non_existent_route() {
     let target_denom = "inj".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let set_result = set_route(
@@ -470,10 +588,7 @@ fn it_returns_error_if_non_admin_tries_to_delete_route() {
     let target_denom = "inj".to_string();
     let route = vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)];
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     let set_result = set_route(
@@ -498,3 +613,220 @@ fn it_returns_error_if_non_admin_tries_to_delete_route() {
     let stored_route = read_swap_route(&deps.storage, &source_denom, &target_denom);
     assert!(stored_route.is_ok(), "route was deleted");
 }
+
+#[test]
+fn it_can_read_routes_by_market_id() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let eth_inj_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        source_denom: "eth".to_string(),
+        target_denom: "inj".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+    let eth_usdt_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+        source_denom: "eth".to_string(),
+        target_denom: "usdt".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+    let inj_usdt_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_2)],
+        source_denom: "inj".to_string(),
+        target_denom: "usdt".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+
+    store_swap_route(deps.as_mut().storage, &eth_inj_route).unwrap();
+    store_swap_route(deps.as_mut().storage, &eth_usdt_route).unwrap();
+    store_swap_route(deps.as_mut().storage, &inj_usdt_route).unwrap();
+
+    let routes = get_swap_routes_by_market(&deps.storage, &MarketId::unchecked(TEST_MARKET_ID_1)).unwrap();
+    assert_eq!(
+        routes,
+        vec![eth_inj_route.clone(), eth_usdt_route],
+        "wrong routes returned for shared market"
+    );
+
+    let routes = get_swap_routes_by_market(&deps.storage, &MarketId::unchecked(TEST_MARKET_ID_2)).unwrap();
+    assert_eq!(routes, vec![eth_inj_route, inj_usdt_route], "wrong routes returned for second market");
+
+    let routes = get_swap_routes_by_market(&deps.storage, &MarketId::unchecked(TEST_MARKET_ID_3)).unwrap();
+    assert!(routes.is_empty(), "no routes expected for unused market");
+}
+
+#[test]
+fn it_returns_distinct_source_denoms_of_routes() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let routes = [("eth", "inj"), ("eth", "usdt"), ("inj", "usdt"), ("eth", "atom")];
+    for (source_denom, target_denom) in routes {
+        let route = SwapRoute {
+            steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+            source_denom: source_denom.to_string(),
+            target_denom: target_denom.to_string(),
+            enabled: true,
+            fee_bps: None,
+        };
+        store_swap_route(deps.as_mut().storage, &route).unwrap();
+    }
+
+    let source_denoms = get_source_denoms(&deps.storage).unwrap();
+    assert_eq!(
+        source_denoms,
+        vec!["eth".to_string(), "inj".to_string()],
+        "source denoms should be deduplicated"
+    );
+}
+
+#[test]
+fn it_returns_targets_reachable_from_source_denom() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let routes = [("eth", "inj"), ("eth", "usdt"), ("inj", "usdt"), ("atom", "eth")];
+    for (source_denom, target_denom) in routes {
+        let route = SwapRoute {
+            steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+            source_denom: source_denom.to_string(),
+            target_denom: target_denom.to_string(),
+            enabled: true,
+            fee_bps: None,
+        };
+        store_swap_route(deps.as_mut().storage, &route).unwrap();
+    }
+
+    let targets = get_reachable_targets(&deps.storage, "eth").unwrap();
+    assert_eq!(targets.len(), 3, "wrong targets: {targets:?}");
+    for target in ["inj", "usdt", "atom"] {
+        assert!(targets.contains(&target.to_string()), "{target} should be reachable from eth");
+    }
+
+    let targets = get_reachable_targets(&deps.storage, "usdt").unwrap();
+    assert_eq!(targets, vec!["eth".to_string(), "inj".to_string()]);
+    assert!(get_reachable_targets(&deps.storage, "btc").unwrap().is_empty());
+}
+
+#[test]
+fn it_returns_routes_converging_on_target_denom() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let routes = [
+        ("eth", "usdt", vec![TEST_MARKET_ID_1]),
+        ("inj", "usdt", vec![TEST_MARKET_ID_2]),
+        ("eth", "inj", vec![TEST_MARKET_ID_1, TEST_MARKET_ID_2]),
+    ];
+    for (source_denom, target_denom, steps) in routes {
+        let route = SwapRoute {
+            steps: steps.into_iter().map(MarketId::unchecked).collect(),
+            source_denom: source_denom.to_string(),
+            target_denom: target_denom.to_string(),
+            enabled: true,
+            fee_bps: None,
+        };
+        store_swap_route(deps.as_mut().storage, &route).unwrap();
+    }
+
+    let routes = get_routes_to_target(&deps.storage, "usdt").unwrap();
+    assert_eq!(routes.len(), 2, "wrong routes: {routes:?}");
+    for (source_denom, market_id) in [("eth", TEST_MARKET_ID_1), ("inj", TEST_MARKET_ID_2)] {
+        let route = routes
+            .iter()
+            .find(|route| route.source_denom == source_denom)
+            .expect("missing source denom");
+        assert_eq!(route.target_denom, "usdt");
+        assert_eq!(route.steps, vec![MarketId::unchecked(market_id)]);
+    }
+
+    // the stored eth -> inj route is turned around to lead to eth
+    let routes = get_routes_to_target(&deps.storage, "eth").unwrap();
+    assert_eq!(routes.len(), 1, "wrong routes: {routes:?}");
+    assert_eq!(routes[0].source_denom, "inj");
+    assert_eq!(routes[0].target_denom, "eth");
+    assert_eq!(
+        routes[0].steps,
+        vec![MarketId::unchecked(TEST_MARKET_ID_2), MarketId::unchecked(TEST_MARKET_ID_1)]
+    );
+
+    assert!(get_routes_to_target(&deps.storage, "btc").unwrap().is_empty());
+}
+
+#[test]
+fn it_allows_admin_to_set_routes_in_batch() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let eth_inj_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        source_denom: "eth".to_string(),
+        target_denom: "inj".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+    let eth_usdt_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+        source_denom: "eth".to_string(),
+        target_denom: "usdt".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+
+    set_routes(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        vec![eth_inj_route.clone(), eth_usdt_route.clone()],
+    )
+    .expect("expected success on batch set");
+
+    assert_eq!(
+        read_swap_route(&deps.storage, "eth", "inj").unwrap(),
+        eth_inj_route,
+        "first route was not stored"
+    );
+    assert_eq!(
+        read_swap_route(&deps.storage, "eth", "usdt").unwrap(),
+        eth_usdt_route,
+        "second route was not stored"
+    );
+}
+
+#[test]
+fn it_rejects_whole_batch_if_any_route_is_invalid() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
+    CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
+
+    let valid_route = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        source_denom: "eth".to_string(),
+        target_denom: "inj".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+    let route_with_duplicate_steps = SwapRoute {
+        steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_1)],
+        source_denom: "eth".to_string(),
+        target_denom: "usdt".to_string(),
+        enabled: true,
+        fee_bps: None,
+    };
+
+    let result = set_routes(
+        deps.as_mut(),
+        &Addr::unchecked(TEST_USER_ADDR),
+        vec![valid_route, route_with_duplicate_steps],
+    );
+
+    let err = result.unwrap_err();
+    assert!(matches!(err, ContractError::DuplicateMarketInRoute { .. }), "wrong error: {err}");
+    assert!(
+        read_swap_route(&deps.storage, "eth", "inj").is_err(),
+        "valid route of a rejected batch was stored"
+    );
+    assert!(read_swap_route(&deps.storage, "eth", "usdt").is_err(), "invalid route was stored");
+}
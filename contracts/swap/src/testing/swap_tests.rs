@@ -1,13 +1,36 @@
 use crate::{
     admin::set_route,
+    contract::{execute, instantiate, query, reply},
+    msg::{ExecuteMsg, FeeRecipient, InstantiateMsg, QueryMsg},
     queries::estimate_single_swap_execution,
-    state::CONFIG,
-    testing::test_utils::{mock_deps_eth_inj, str_coin, Decimals, MultiplierQueryBehavior, TEST_USER_ADDR},
-    types::{Config, FPCoin, SwapEstimationAmount},
+    state::{
+        read_swap_route, store_swap_route, CONFIG, FEE_RECIPIENTS_BY_DENOM, LARGE_SWAP_COMMITS, LARGE_SWAP_THRESHOLDS, MAX_MEMO_LENGTH,
+        MAX_SWAP_SLICES, RESERVES, STEP_STATE, SWAP_OPERATION_STATE, SWAP_RESULTS, TRUNCATED_OUTPUTS,
+    },
+    swap::parse_order_fill,
+    testing::test_utils::{
+        create_oracle_price_handler, mock_atomic_order_reply, mock_deps_eth_inj, mock_deps_eth_inj_with_eth_quantity_tick,
+        mock_failed_atomic_order_reply, str_coin, Decimals, MultiplierQueryBehavior, TEST_USER_ADDR,
+    },
+    types::{
+        AccountingResponse, Config, CurrentSwapOperation, CurrentSwapStep, Cw20AdapterMsg, DebugStateResponse, DenomAccounting, DustPolicy, FPCoin,
+        LargeSwapCommit, MinOutputValue, OrderFill, OverDeliveryPolicy, PlannedMessage, PortfolioLeg, PortfolioTarget, QuoteResponse,
+        SwapEstimationAmount, SwapQuantityMode, SwapResults, SwapRoute,
+    },
+    ContractError,
 };
 
-use cosmwasm_std::{testing::mock_env, Addr};
-use injective_cosmwasm::{MarketId, OwnedDepsExt, TEST_MARKET_ID_1, TEST_MARKET_ID_2};
+use cosmwasm_std::{
+    coin, from_json,
+    testing::{message_info, mock_env},
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Reply, ReplyOn, Response, StdResult, Storage, SubMsgResult, Timestamp, Uint128,
+    WasmMsg,
+};
+use injective_cosmwasm::{
+    create_simple_balance_bank_query_handler, create_spot_market_order_msg, get_default_subaccount_id_for_checked_address, InjectiveMsgWrapper,
+    InjectiveQueryWrapper, MarketId, OracleType, OrderType, OwnedDepsExt, SpotOrder, TEST_MARKET_ID_1, TEST_MARKET_ID_2,
+};
+use injective_math::FPDecimal;
 
 #[test]
 fn it_reverts_if_atomic_fee_multiplier_query_fails() {
@@ -15,10 +38,7 @@ fn it_reverts_if_atomic_fee_multiplier_query_fails() {
     let deps_binding = mock_deps_eth_inj(MultiplierQueryBehavior::Fail);
     let mut deps = deps_binding;
 
-    let config = Config {
-        fee_recipient: Addr::unchecked(TEST_USER_ADDR),
-        admin: Addr::unchecked(TEST_USER_ADDR),
-    };
+    let config = Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR));
     CONFIG.save(deps.as_mut_deps().storage, &config).expect("could not save config");
 
     set_route(
@@ -36,6 +56,7 @@ fn it_reverts_if_atomic_fee_multiplier_query_fails() {
         &MarketId::unchecked(TEST_MARKET_ID_1.to_string()),
         SwapEstimationAmount::InputQuantity(FPCoin::from(str_coin("1", "eth", Decimals::Eighteen))),
         true, // is_simulation
+        false,
     );
 
     assert!(response_1.is_err(), "should have failed");
@@ -44,3 +65,3431 @@ fn it_reverts_if_atomic_fee_multiplier_query_fails() {
         "wrong error message"
     );
 }
+
+fn instantiate_with_admin(deps: DepsMut<InjectiveQueryWrapper>) -> Addr {
+    let admin = Addr::unchecked(TEST_USER_ADDR);
+
+    instantiate(
+        deps,
+        mock_env(),
+        message_info(&admin, &[]),
+        InstantiateMsg {
+            fee_recipient: FeeRecipient::Address(admin.to_owned()),
+            admin: admin.to_owned(),
+            initial_routes: vec![],
+        },
+    )
+    .unwrap();
+
+    admin
+}
+
+fn instantiate_with_eth_usdt_route(mut deps: DepsMut<InjectiveQueryWrapper>) -> Addr {
+    let admin = instantiate_with_admin(deps.branch());
+    set_route(deps, &admin, "eth".to_string(), "usdt".to_string(), vec![TEST_MARKET_ID_1.into()]).unwrap();
+    admin
+}
+
+fn min_output_swap(target_denom: &str) -> ExecuteMsg {
+    ExecuteMsg::SwapMinOutput {
+        target_denom: target_denom.to_string(),
+        min_output_quantity: FPDecimal::ONE,
+        auto_convert_to: None,
+        memo: None,
+        input_fraction_bps: None,
+        min_output_value: None,
+        slices: None,
+        acknowledge_high_impact: false,
+    }
+}
+
+#[test]
+fn it_appends_conversion_route_when_auto_convert_to_differs_from_target() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "usdt".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: Some("inj".to_string()),
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(
+        swap_operation.swap_steps,
+        vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        "conversion route was not appended"
+    );
+}
+
+#[test]
+fn it_skips_conversion_when_auto_convert_to_equals_target() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: Some("usdt".to_string()),
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(
+        swap_operation.swap_steps,
+        vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+        "conversion route should not be appended"
+    );
+}
+
+#[test]
+fn it_accumulates_swap_volume_per_source_denom() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    for _ in 0..3 {
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(2u128, "eth")]),
+            min_output_swap("usdt"),
+        )
+        .unwrap();
+        reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(201000u128), FPDecimal::from(1000u128)),
+        )
+        .unwrap();
+    }
+
+    let eth_volume: Uint128 = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetVolume { denom: "eth".to_string() }).unwrap()).unwrap();
+    assert_eq!(eth_volume, Uint128::new(6), "wrong eth volume");
+
+    let usdt_volume: Uint128 = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetVolume { denom: "usdt".to_string() }).unwrap()).unwrap();
+    assert_eq!(usdt_volume, Uint128::zero(), "target denom should not accumulate input volume");
+}
+
+#[test]
+fn it_aggregates_accounting_of_completed_swaps() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+            Ok(Config {
+                swap_fee_bps: 1000,
+                ..config
+            })
+        })
+        .unwrap();
+
+    // the first swap pays 1 eth of fee, the second swaps half of its input and is refunded the rest
+    for (input_fraction_bps, sold_quantity) in [(None, 9u128), (Some(5000), 5u128)] {
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(10u128, "eth")]),
+            ExecuteMsg::SwapMinOutput {
+                target_denom: "usdt".to_string(),
+                min_output_quantity: FPDecimal::ONE,
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps,
+                min_output_value: None,
+                slices: None,
+                acknowledge_high_impact: false,
+            },
+        )
+        .unwrap();
+        reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(FPDecimal::from(sold_quantity), FPDecimal::from(201000u128), FPDecimal::ZERO),
+        )
+        .unwrap();
+    }
+
+    let accounting: AccountingResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetAccounting {}).unwrap()).unwrap();
+    assert_eq!(
+        accounting.denoms,
+        vec![(
+            "eth".to_string(),
+            DenomAccounting {
+                fees_collected: Uint128::new(1),
+                volume: Uint128::new(15),
+                refunded: Uint128::new(5),
+                completed_swaps: 2,
+            }
+        )]
+    );
+}
+
+#[test]
+fn it_counts_the_completed_swaps_of_each_sender() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let query_swap_count = |deps: Deps<InjectiveQueryWrapper>, sender: &Addr| -> u64 {
+        from_json(query(deps, mock_env(), QueryMsg::GetUserSwapCount { sender: sender.to_owned() }).unwrap()).unwrap()
+    };
+
+    assert_eq!(query_swap_count(deps.as_ref(), &admin), 0, "no swap should be counted yet");
+
+    for expected_count in 1..=3u64 {
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(2u128, "eth")]),
+            min_output_swap("usdt"),
+        )
+        .unwrap();
+        reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(201000u128), FPDecimal::ZERO),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query_swap_count(deps.as_ref(), &admin),
+            expected_count,
+            "every completed swap should be counted"
+        );
+    }
+    assert_eq!(
+        query_swap_count(deps.as_ref(), &Addr::unchecked("other_sender")),
+        0,
+        "swaps should only count for their sender"
+    );
+}
+
+// a swap of `input_funds` by the test user along the eth/usdt market, with nothing refunded or charged yet
+fn swap_operation(input_funds: Coin, swap_quantity_mode: SwapQuantityMode) -> CurrentSwapOperation {
+    CurrentSwapOperation {
+        sender_address: Addr::unchecked(TEST_USER_ADDR),
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+        swap_quantity_mode,
+        refund: coin(0u128, input_funds.denom.to_owned()),
+        fee: coin(0u128, input_funds.denom.to_owned()),
+        input_funds,
+        compound_refund: false,
+        memo: None,
+        started_at: mock_env().block.time,
+        recipients: vec![],
+        min_output_value: None,
+        slices: None,
+        estimated_output: None,
+        exact_output_input: None,
+        portfolio: vec![],
+        route_fee_bps: None,
+    }
+}
+
+// stores the state of a single step eth -> usdt swap waiting for the reply of its market order selling 9 eth
+fn save_last_step_state(storage: &mut dyn Storage, swap_operation: &CurrentSwapOperation) {
+    let current_step = CurrentSwapStep {
+        step_idx: 0,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(9u128),
+            denom: "eth".to_string(),
+        },
+        step_target_denom: "usdt".to_string(),
+        is_buy: false,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(storage, swap_operation).unwrap();
+    STEP_STATE.save(storage, &current_step).unwrap();
+    SWAP_RESULTS.save(storage, &vec![]).unwrap();
+}
+
+fn test_config() -> Config {
+    Config::new(Addr::unchecked(TEST_USER_ADDR), Addr::unchecked(TEST_USER_ADDR))
+}
+
+// settles `swap_operation` under `config` with `fill` replying to the order of its last step
+fn settle_swap(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    config: &Config,
+    swap_operation: &CurrentSwapOperation,
+    fill: Reply,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    CONFIG.save(deps.storage, config).unwrap();
+    save_last_step_state(deps.storage, swap_operation);
+    reply(deps, mock_env(), fill)
+}
+
+fn sell_eth_fill(quantity: u128) -> Reply {
+    mock_atomic_order_reply(FPDecimal::from(quantity), FPDecimal::from(201000u128), FPDecimal::ZERO)
+}
+
+fn sent_messages(res: Response<InjectiveMsgWrapper>) -> Vec<CosmosMsg<InjectiveMsgWrapper>> {
+    res.messages.into_iter().map(|sub_msg| sub_msg.msg).collect()
+}
+
+#[test]
+fn it_sends_no_bank_messages_for_zero_refund_and_fee() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let swap_operation = swap_operation(coin(9u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE));
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1809000u128, "usdt")],
+        })],
+        "only the output should be sent"
+    );
+}
+
+#[test]
+fn it_aborts_when_refund_exceeds_input() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(11u128, "eth"),
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE))
+    };
+    let err = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(9)).unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::RefundExceedsInput { refund, input } if refund == Uint128::new(11) && input == Uint128::new(10)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_orders_settlement_messages_output_fee_refund() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let fee_recipient = Addr::unchecked("fee_recipient");
+    let config = Config {
+        fee_recipient: fee_recipient.to_owned(),
+        ..test_config()
+    };
+
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(2u128, "eth"),
+        fee: coin(1u128, "eth"),
+        ..swap_operation(coin(12u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE))
+    };
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    let sends: Vec<(String, Vec<Coin>)> = sent_messages(res)
+        .into_iter()
+        .map(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => (to_address, amount),
+            other => panic!("unexpected message: {other:?}"),
+        })
+        .collect();
+
+    assert_eq!(sends.len(), 3, "expected output, fee and refund messages");
+    assert_eq!(sends[0].0, TEST_USER_ADDR, "swap output must be sent first");
+    assert_eq!(sends[0].1[0].denom, "usdt", "swap output must be sent first");
+    assert_eq!(sends[1], (fee_recipient.to_string(), vec![coin(1u128, "eth")]), "fee must be sent second");
+    assert_eq!(
+        sends[2],
+        (TEST_USER_ADDR.to_string(), vec![coin(2u128, "eth")]),
+        "refund must be sent last"
+    );
+}
+
+#[test]
+fn it_nets_the_fee_into_the_refund_when_the_sender_is_the_fee_recipient() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(2u128, "eth"),
+        fee: coin(1u128, "eth"),
+        ..swap_operation(coin(12u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE))
+    };
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(
+        sent_messages(res),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(1809000u128, "usdt")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(3u128, "eth")],
+            }),
+        ],
+        "the fee should go back along with the refund instead of in a send of its own"
+    );
+}
+
+#[test]
+fn it_falls_back_to_direct_market_when_enabled() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<_> {
+            Ok(Config {
+                allow_direct_fallback: true,
+                ..config
+            })
+        })
+        .unwrap();
+
+    let response = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+
+    assert_eq!(response.messages.len(), 1, "expected a single market order");
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(
+        swap_operation.swap_steps,
+        vec![MarketId::unchecked(TEST_MARKET_ID_1)],
+        "direct market was not used"
+    );
+}
+
+#[test]
+fn it_does_not_fall_back_to_direct_market_by_default() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+
+    assert!(err.to_string().contains("No swap route not found from eth to usdt"), "wrong error: {err}");
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_aborts_exact_output_swap_only_above_max_input() {
+    // selling 2 eth at 201000 with a 0.25% fee yields exactly 400995 usdt
+    for max_input in [3u128, 2, 1] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+        let result = execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(5u128, "eth")]),
+            ExecuteMsg::SwapExactOutput {
+                target_denom: "usdt".to_string(),
+                target_output_quantity: FPDecimal::from(400995u128),
+                max_input: Some(Uint128::new(max_input)),
+                compound_refund: false,
+                memo: None,
+                acknowledge_high_impact: false,
+            },
+        );
+
+        if max_input >= 2 {
+            result.unwrap();
+            continue;
+        }
+        let err = result.unwrap_err();
+        assert!(
+            matches!(err, ContractError::InputExceedsMax { required, max } if required == FPDecimal::from(2u128) && max == Uint128::new(1)),
+            "wrong error: {err}"
+        );
+        assert!(
+            SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none(),
+            "funds should not be locked"
+        );
+    }
+}
+
+#[test]
+fn it_rejects_extra_coins_in_strict_funds_mode() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                strict_funds: true,
+                ..test_config()
+            },
+        )
+        .unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth"), coin(7u128, "gas")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+
+    assert!(err.to_string().contains("Only one denom can be passed in funds"), "wrong error: {err}");
+    assert!(
+        SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none(),
+        "swap should not have started"
+    );
+}
+
+#[test]
+fn it_swaps_source_denom_and_returns_extra_coins_in_lenient_funds_mode() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                strict_funds: false,
+                ..test_config()
+            },
+        )
+        .unwrap();
+
+    let response = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth"), coin(7u128, "gas")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    let swap_operation = SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(7u128, "gas")],
+        }),
+        "extra coins should be given back first"
+    );
+    assert_eq!(response.messages.len(), 2, "expected the given back coins and a single market order");
+    assert_eq!(
+        swap_operation.unwrap().input_funds,
+        coin(1u128, "eth"),
+        "only the source denom should be swapped"
+    );
+}
+
+#[test]
+fn it_rejects_swap_sent_by_the_contract_itself() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let env = mock_env();
+    let err = execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&env.contract.address, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::SelfCall {}), "wrong error: {err}");
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_records_block_of_execution_in_swap_results() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    let swap_operation = swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+    save_last_step_state(deps.as_mut_deps().storage, &swap_operation);
+
+    let env = mock_env();
+    let res = reply(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        mock_atomic_order_reply(FPDecimal::from(9u128), FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+
+    let swap_results_json = res.events[0]
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "swap_results")
+        .expect("swap_results attribute expected")
+        .value
+        .to_owned();
+    let swap_results: Vec<SwapResults> = serde_json_wasm::from_str(&swap_results_json).unwrap();
+
+    assert_eq!(swap_results.len(), 1);
+    assert_eq!(swap_results[0].block_height, env.block.height, "wrong block height");
+    assert_eq!(swap_results[0].timestamp, env.block.time, "wrong block time");
+}
+
+#[test]
+fn it_bounds_the_min_output_by_the_max_slippage() {
+    // the estimated output of selling 1 eth is 200497.5 usdt, with a 1% slippage ceiling the lowest accepted min output is 198492.525
+    for (min_output_quantity, accepted) in [(FPDecimal::must_from_str("198492.525"), true), (FPDecimal::from(198000u128), false)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+        CONFIG
+            .save(
+                deps.as_mut_deps().storage,
+                &Config {
+                    max_allowed_slippage_bps: Some(100),
+                    ..test_config()
+                },
+            )
+            .unwrap();
+
+        let result = execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(1u128, "eth")]),
+            ExecuteMsg::SwapMinOutput {
+                target_denom: "usdt".to_string(),
+                min_output_quantity,
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps: None,
+                min_output_value: None,
+                slices: None,
+                acknowledge_high_impact: false,
+            },
+        );
+
+        if accepted {
+            result.unwrap();
+        } else {
+            let err = result.unwrap_err();
+            assert!(
+                matches!(err, ContractError::SlippageTooLoose { requested_bps, max_bps: 100 } if requested_bps > FPDecimal::from(100u128)),
+                "wrong error: {err}"
+            );
+        }
+    }
+}
+
+#[test]
+fn it_takes_fee_from_output_when_fee_in_output_denom_is_set() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let fee_recipient = Addr::unchecked("fee_recipient");
+    let config = Config {
+        swap_fee_bps: 100,
+        fee_in_output_denom: true,
+        fee_recipient: fee_recipient.to_owned(),
+        ..test_config()
+    };
+
+    // 9 eth sold at 201000 yield 1809000 usdt, 1% of which is the contract fee
+    let swap_operation = swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    let expected_messages: Vec<CosmosMsg<InjectiveMsgWrapper>> = vec![
+        BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1790910u128, "usdt")],
+        }
+        .into(),
+        BankMsg::Send {
+            to_address: fee_recipient.to_string(),
+            amount: vec![coin(18090u128, "usdt")],
+        }
+        .into(),
+    ];
+    assert_eq!(
+        sent_messages(res),
+        expected_messages,
+        "fee should be deducted from the output and sent in the output denom"
+    );
+}
+
+fn preview_eth_swap(deps: Deps<InjectiveQueryWrapper>, sender: &Addr) -> Vec<PlannedMessage> {
+    from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PreviewPlan {
+                sender: sender.to_owned(),
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+                input_amount: Uint128::new(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn it_previews_the_messages_emitted_by_a_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let env = mock_env();
+
+    let plan = preview_eth_swap(deps.as_ref(), &admin);
+    assert_eq!(plan.len(), 2, "expected a market order and the output send");
+
+    let execute_response = execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+
+    let PlannedMessage::MarketOrder {
+        market_id,
+        is_buy: false,
+        price,
+        quantity,
+        ..
+    } = &plan[0]
+    else {
+        panic!("expected a sell market order, got {:?}", plan[0]);
+    };
+    let expected_order = SpotOrder::new(
+        *price,
+        *quantity,
+        OrderType::SellAtomic,
+        market_id,
+        get_default_subaccount_id_for_checked_address(&env.contract.address),
+        Some(admin.to_owned()),
+        None,
+    );
+    assert_eq!(
+        execute_response.messages[0].msg,
+        create_spot_market_order_msg(env.contract.address.to_owned(), expected_order),
+        "previewed order differs from the executed one"
+    );
+
+    // the order fills at the estimated price and fee
+    let reply_response = reply(
+        deps.as_mut_deps(),
+        env,
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::must_from_str("502.5")),
+    )
+    .unwrap();
+
+    let PlannedMessage::BankSend { to_address, amount } = &plan[1] else {
+        panic!("expected a bank send, got {:?}", plan[1]);
+    };
+    assert_eq!(
+        reply_response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: to_address.to_string(),
+            amount: vec![amount.to_owned()],
+        }),
+        "previewed output send differs from the executed one"
+    );
+}
+
+#[test]
+fn it_previews_no_output_send_for_a_timelocked_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+            Ok(Config {
+                output_timelock_seconds: 3600,
+                ..config
+            })
+        })
+        .unwrap();
+
+    let plan = preview_eth_swap(deps.as_ref(), &admin);
+
+    assert_eq!(plan.len(), 1, "expected only the market order, got {plan:?}");
+    assert!(
+        matches!(plan[0], PlannedMessage::MarketOrder { .. }),
+        "expected a market order, got {:?}",
+        plan[0]
+    );
+}
+
+#[test]
+fn it_previews_the_redemption_of_a_cw20_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let adapter = deps.api.addr_make("cw20_adapter");
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[]),
+        ExecuteMsg::SetCw20Output {
+            denom: "usdt".to_string(),
+            adapter: Some(adapter.to_owned()),
+        },
+    )
+    .unwrap();
+
+    let plan = preview_eth_swap(deps.as_ref(), &admin);
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    let reply_response = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::must_from_str("502.5")),
+    )
+    .unwrap();
+
+    let PlannedMessage::WasmExecute { contract_addr, msg, funds } = &plan[1] else {
+        panic!("expected a wasm execute, got {:?}", plan[1]);
+    };
+    assert_eq!(*contract_addr, adapter);
+    assert_eq!(
+        reply_response.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: msg.to_owned(),
+            funds: funds.to_owned(),
+        }),
+        "previewed redemption differs from the executed one"
+    );
+}
+
+#[test]
+fn it_determines_step_direction_from_market_orientation() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+    let env = mock_env();
+
+    // eth -> inj sells eth on eth/usdt and buys inj on inj/usdt, inj -> eth traverses both markets the other way
+    let hops = [
+        (TEST_MARKET_ID_1, "eth", "1", false, "usdt"),
+        (TEST_MARKET_ID_2, "usdt", "1000", true, "inj"),
+        (TEST_MARKET_ID_2, "inj", "1", false, "usdt"),
+        (TEST_MARKET_ID_1, "usdt", "1000", true, "eth"),
+    ];
+
+    for (market_id, input_denom, input_quantity, expected_is_buy, expected_result_denom) in hops {
+        let estimate = estimate_single_swap_execution(
+            &deps.as_ref(),
+            &env,
+            &MarketId::unchecked(market_id),
+            SwapEstimationAmount::InputQuantity(FPCoin {
+                amount: FPDecimal::must_from_str(input_quantity),
+                denom: input_denom.to_string(),
+            }),
+            true,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            estimate.is_buy_order, expected_is_buy,
+            "wrong direction spending {input_denom} on {market_id}"
+        );
+        assert_eq!(
+            estimate.result_denom, expected_result_denom,
+            "wrong result denom spending {input_denom} on {market_id}"
+        );
+    }
+}
+
+#[test]
+fn it_rounds_bought_quantity_to_min_tick_before_selling_it() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    // buys eth with usdt on eth/usdt, then sells the eth on the same market the other way
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_1)],
+        ..swap_operation(coin(250000u128, "usdt"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+    let current_step = CurrentSwapStep {
+        step_idx: 0,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(250000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "eth".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(deps.as_mut_deps().storage, &swap_operation).unwrap();
+    STEP_STATE.save(deps.as_mut_deps().storage, &current_step).unwrap();
+    SWAP_RESULTS.save(deps.as_mut_deps().storage, &vec![]).unwrap();
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::must_from_str("1.2345"), FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1, "expected the market order of the next step");
+
+    let next_step = STEP_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert!(!next_step.is_buy, "second step should sell eth");
+    assert_eq!(
+        next_step.current_balance.amount,
+        FPDecimal::must_from_str("1.234"),
+        "sold quantity should be rounded down to the min quantity tick"
+    );
+    assert_eq!(
+        SWAP_RESULTS.load(deps.as_mut_deps().storage).unwrap()[0].quantity,
+        FPDecimal::must_from_str("1.234")
+    );
+}
+
+#[test]
+fn it_delivers_only_at_last_step_when_target_denom_appears_mid_route() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    // usdt -> eth -> usdt -> eth: the target eth is already reached after the first step
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![
+            MarketId::unchecked(TEST_MARKET_ID_1),
+            MarketId::unchecked(TEST_MARKET_ID_1),
+            MarketId::unchecked(TEST_MARKET_ID_1),
+        ],
+        ..swap_operation(coin(250000u128, "usdt"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+    let current_step = CurrentSwapStep {
+        step_idx: 0,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(250000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "eth".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(deps.as_mut_deps().storage, &swap_operation).unwrap();
+    STEP_STATE.save(deps.as_mut_deps().storage, &current_step).unwrap();
+    SWAP_RESULTS.save(deps.as_mut_deps().storage, &vec![]).unwrap();
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1, "expected only the market order of the next step");
+    assert!(
+        matches!(res.messages[0].msg, CosmosMsg::Custom(_)),
+        "eth must not be delivered before the last step"
+    );
+    assert!(res.events.is_empty(), "swap should not be finalized mid-route");
+    assert_eq!(STEP_STATE.load(deps.as_mut_deps().storage).unwrap().step_idx, 1);
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_some());
+}
+
+#[test]
+fn it_keeps_collected_fee_when_a_later_hop_fails() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    // the contract fee of the eth -> usdt -> inj swap was taken from the input before the first hop
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        fee: coin(1u128, "eth"),
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::from(1000u128)))
+    };
+    let last_step = CurrentSwapStep {
+        step_idx: 1,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(1809000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "inj".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(deps.as_mut_deps().storage, &swap_operation).unwrap();
+    STEP_STATE.save(deps.as_mut_deps().storage, &last_step).unwrap();
+    SWAP_RESULTS.save(deps.as_mut_deps().storage, &vec![]).unwrap();
+
+    // the second hop buys too little inj
+    let err = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(9u128), FPDecimal::from(800u128), FPDecimal::ZERO),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::MinOutputAmountNotReached(..)), "second hop should fail");
+
+    // the fee was never sent out, so reverting the swap gives it back along with the input
+    let stored_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(stored_operation.fee, coin(1u128, "eth"));
+    assert_eq!(stored_operation.input_funds, coin(10u128, "eth"));
+}
+
+#[test]
+fn it_completes_multi_step_route_one_step_per_reply() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    // usdt -> eth -> usdt, each reply executes the next step until the last one settles
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_1)],
+        ..swap_operation(coin(201000u128, "usdt"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+    let first_step = CurrentSwapStep {
+        step_idx: 0,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(201000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "eth".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(deps.as_mut_deps().storage, &swap_operation).unwrap();
+    STEP_STATE.save(deps.as_mut_deps().storage, &first_step).unwrap();
+    SWAP_RESULTS.save(deps.as_mut_deps().storage, &vec![]).unwrap();
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    assert!(
+        matches!(res.messages[0].msg, CosmosMsg::Custom(_)),
+        "first reply should place the second order"
+    );
+    let second_step = STEP_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(second_step.step_idx, 1, "progress should be persisted");
+    assert_eq!(second_step.current_balance, FPCoin::from(coin(1u128, "eth")));
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(201000u128, "usdt")],
+        }),
+        "second reply should deliver the output"
+    );
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_swaps_refund_above_dust_threshold_when_compounding() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        refund_dust_threshold: Uint128::new(1),
+        swap_fee_bps: 5000,
+        exact_output_tolerance_bps: 100,
+        ..test_config()
+    };
+
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(2u128, "eth"),
+        compound_refund: true,
+        ..swap_operation(coin(11u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE))
+    };
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(res.messages.len(), 2, "expected the output send and the market order of the refund swap");
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1809000u128, "usdt")],
+        }),
+        "output should be sent first"
+    );
+    assert!(
+        matches!(res.messages[1].msg, CosmosMsg::Custom(_)),
+        "refund should be swapped, not sent back"
+    );
+
+    let refund_swap = SWAP_OPERATION_STATE
+        .load(deps.as_mut_deps().storage)
+        .expect("refund swap should be in progress");
+    assert_eq!(refund_swap.input_funds, coin(2u128, "eth"), "refund swap should spend the whole refund");
+    assert_eq!(refund_swap.swap_steps, vec![MarketId::unchecked(TEST_MARKET_ID_1)]);
+    assert_eq!(refund_swap.fee, coin(1u128, "eth"), "refund swap should be charged the contract fee");
+    // the 1 eth left after the fee at the 201000 the swap executed at, less the 1% tolerance
+    assert_eq!(
+        refund_swap.swap_quantity_mode,
+        SwapQuantityMode::MinOutputQuantity(FPDecimal::from(198990u128)),
+        "refund swap should be held to the executed rate"
+    );
+    assert_eq!(
+        STEP_STATE.load(deps.as_mut_deps().storage).unwrap().current_balance,
+        FPCoin::from(coin(1u128, "eth")),
+        "only the refund left after the fee should be swapped"
+    );
+}
+
+#[test]
+fn it_sends_back_refund_below_dust_threshold_when_compounding() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        refund_dust_threshold: Uint128::new(1),
+        ..test_config()
+    };
+
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(1u128, "eth"),
+        compound_refund: true,
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::ONE))
+    };
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(
+        res.messages.last().unwrap().msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1u128, "eth")],
+        }),
+        "refund should be sent back"
+    );
+    assert!(
+        SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none(),
+        "no refund swap expected"
+    );
+}
+
+#[test]
+fn it_dumps_state_of_in_flight_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+
+    let dump_state = |sender: Option<Addr>| -> DebugStateResponse {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::DebugDumpState { sender }).unwrap()).unwrap()
+    };
+
+    let state = dump_state(Some(admin.to_owned()));
+    let operation = state.operation.expect("operation should be cached");
+    assert_eq!(operation.sender_address, admin);
+    assert_eq!(operation.input_funds, coin(1u128, "eth"));
+    let step = state.step.expect("step should be cached");
+    assert_eq!(step.step_idx, 0);
+    assert_eq!(step.current_balance.amount, FPDecimal::ONE);
+    assert_eq!(state.results, Some(vec![]));
+
+    let other_sender_state = dump_state(Some(Addr::unchecked("other")));
+    assert!(other_sender_state.operation.is_none(), "swap of another sender should not be returned");
+
+    let unfiltered_state = dump_state(None);
+    assert_eq!(
+        unfiltered_state.operation,
+        Some(operation),
+        "the swap should be returned without a sender filter"
+    );
+}
+
+#[test]
+fn it_detects_swap_stuck_in_flight_as_stale() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    // the order reply never arrives, so the swap stays in flight
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: Some("stuck".to_string()),
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+    let started_at = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap().started_at;
+    assert_eq!(started_at, mock_env().block.time);
+
+    let mut later_env = mock_env();
+    later_env.block.time = started_at.plus_seconds(120);
+    let stale_swaps = |older_than_seconds: u64| -> Vec<CurrentSwapOperation> {
+        from_json(query(deps.as_ref(), later_env.clone(), QueryMsg::GetStaleSwaps { older_than_seconds }).unwrap()).unwrap()
+    };
+
+    let swaps = stale_swaps(60);
+    assert_eq!(swaps.len(), 1, "swap started 120 seconds ago should be stale");
+    assert_eq!(swaps[0].memo, Some("stuck".to_string()));
+    assert!(stale_swaps(600).is_empty(), "swap should not be stale yet");
+}
+
+#[test]
+fn it_emits_memo_in_swap_execution_event() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: Some("invoice-42".to_string()),
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::must_from_str("502.5")),
+    )
+    .unwrap();
+
+    let memo = res.events[0]
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "memo")
+        .expect("memo attribute expected");
+    assert_eq!(memo.value, "invoice-42");
+}
+
+#[test]
+fn it_rejects_too_long_memo() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: Some("x".repeat(MAX_MEMO_LENGTH + 1)),
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::MemoTooLong { length, max_length } if length == MAX_MEMO_LENGTH + 1 && max_length == MAX_MEMO_LENGTH),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_settles_dust_outputs_by_the_dust_policy() {
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(1u128, "eth"),
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::must_from_str("0.1")))
+    };
+    // sells a tiny amount of eth for 0.201 usdt
+    let dust_fill = || mock_atomic_order_reply(FPDecimal::must_from_str("0.000001"), FPDecimal::from(201000u128), FPDecimal::ZERO);
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let err = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, dust_fill()).unwrap_err();
+    assert!(
+        matches!(&err, ContractError::OutputIsDust { amount, denom } if *amount == FPDecimal::must_from_str("0.201") && denom == "usdt"),
+        "dust output should abort by default, got: {err}"
+    );
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        dust_policy: DustPolicy::DeliverZero,
+        ..test_config()
+    };
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, dust_fill()).unwrap();
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1u128, "eth")],
+        })],
+        "only the refund should be sent when delivering zero"
+    );
+}
+
+#[test]
+fn it_settles_over_delivered_output_by_the_over_delivery_policy() {
+    // sells 1 eth for 201000 usdt, 1000 more than requested
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::from(200000u128)));
+
+    for (over_delivery_policy, delivered) in [(OverDeliveryPolicy::default(), 201000u128), (OverDeliveryPolicy::KeepAsRevenue, 200000)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let config = Config {
+            over_delivery_policy: over_delivery_policy.to_owned(),
+            ..test_config()
+        };
+        let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(1)).unwrap();
+
+        assert_eq!(
+            sent_messages(res),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(delivered, "usdt")],
+            })],
+            "wrong output sent under {over_delivery_policy:?}"
+        );
+    }
+}
+
+#[test]
+fn it_accepts_a_short_exact_output_only_within_the_tolerance() {
+    // requests 202000 usdt with a 50 bps tolerance, so anything from 200990 is accepted
+    let config = Config {
+        exact_output_tolerance_bps: 50,
+        ..test_config()
+    };
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::ExactOutputQuantity(FPDecimal::from(202000u128)));
+    let fill_at = |price: u128| mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(price), FPDecimal::ZERO);
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, fill_at(200990)).unwrap();
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(200990u128, "usdt")],
+        })],
+        "the output short by exactly the tolerance should be delivered"
+    );
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let err = settle_swap(deps.as_mut_deps(), &config, &swap_operation, fill_at(200989)).unwrap_err();
+    assert!(
+        matches!(err, ContractError::MinOutputAmountNotReached(min_output) if min_output == FPDecimal::from(200990u128)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_swaps_the_requested_fraction_of_the_input_and_refunds_the_rest() {
+    for (input_fraction_bps, swapped, refunded) in [(5000u16, 2u128, 2u128), (10000, 4, 0)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(4u128, "eth")]),
+            ExecuteMsg::SwapMinOutput {
+                target_denom: "usdt".to_string(),
+                min_output_quantity: FPDecimal::ONE,
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps: Some(input_fraction_bps),
+                min_output_value: None,
+                slices: None,
+                acknowledge_high_impact: false,
+            },
+        )
+        .unwrap();
+
+        let swap_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+        let current_step = STEP_STATE.load(deps.as_mut_deps().storage).unwrap();
+        assert_eq!(
+            current_step.current_balance.amount,
+            FPDecimal::from(swapped),
+            "wrong amount swapped for {input_fraction_bps} bps"
+        );
+        assert_eq!(swap_operation.refund, coin(refunded, "eth"), "wrong refund for {input_fraction_bps} bps");
+        assert_eq!(swap_operation.input_funds, coin(4u128, "eth"));
+    }
+}
+
+#[test]
+fn it_rejects_input_fraction_above_one_hundred_percent() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(4u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: Some(10001),
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::InvalidInputFraction { fraction_bps: 10001 }),
+        "wrong error: {err}"
+    );
+}
+
+fn save_swap_stuck_at_second_step(storage: &mut dyn Storage, started_at: Timestamp) {
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        fee: coin(1u128, "eth"),
+        started_at,
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+    let current_step = CurrentSwapStep {
+        step_idx: 1,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(1809000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "inj".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(storage, &swap_operation).unwrap();
+    STEP_STATE.save(storage, &current_step).unwrap();
+    SWAP_RESULTS.save(storage, &vec![]).unwrap();
+}
+
+#[test]
+fn it_gives_back_funds_of_cancelled_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+    save_swap_stuck_at_second_step(deps.as_mut_deps().storage, mock_env().block.time.minus_seconds(60));
+
+    let res = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::CancelSwap {},
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1u128, "eth"), coin(1809000u128, "usdt")],
+        }),
+        "usdt of the pending step and the unpaid fee should be given back"
+    );
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none());
+    assert!(STEP_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_rejects_cancel_by_other_sender_or_in_the_same_block() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+    save_swap_stuck_at_second_step(deps.as_mut_deps().storage, mock_env().block.time.minus_seconds(60));
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked("someone_else"), &[]),
+        ExecuteMsg::CancelSwap {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}), "wrong error: {err}");
+
+    save_swap_stuck_at_second_step(deps.as_mut_deps().storage, mock_env().block.time);
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::CancelSwap {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::SwapStartedInThisBlock {}), "wrong error: {err}");
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap().is_some());
+}
+
+#[test]
+fn it_distributes_output_across_recipients_by_weight() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let first_recipient = deps.api.addr_make("first_recipient");
+    let second_recipient = deps.api.addr_make("second_recipient");
+
+    let swap_operation = CurrentSwapOperation {
+        recipients: vec![(first_recipient.to_owned(), 3333), (second_recipient.to_owned(), 6667)],
+        ..swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(9)).unwrap();
+
+    // 1809000 usdt split 33.33% / 66.67%, the rounding dust of 1 goes to the last recipient
+    assert_eq!(
+        sent_messages(res),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: first_recipient.to_string(),
+                amount: vec![coin(602939u128, "usdt")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: second_recipient.to_string(),
+                amount: vec![coin(1206061u128, "usdt")],
+            }),
+        ]
+    );
+}
+
+#[test]
+fn it_rejects_recipient_weights_not_adding_up_to_one_hundred_percent() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    let recipient = deps.api.addr_make("recipient");
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        ExecuteMsg::SwapAndDistribute {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            recipients: vec![(admin.to_owned(), 5000), (recipient, 4999)],
+        },
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::InvalidRecipientWeights { total_bps: 9999 }),
+        "wrong error: {err}"
+    );
+}
+
+/// Quotes a swap of 2 eth to usdt by the admin with `update` applied to the config, then swaps and settles it.
+fn quote_and_settle_eth_swap(
+    mut deps: DepsMut<InjectiveQueryWrapper>,
+    update: fn(Config) -> Config,
+) -> (QuoteResponse, Vec<CosmosMsg<InjectiveMsgWrapper>>) {
+    let admin = instantiate_with_eth_usdt_route(deps.branch());
+    CONFIG.update(deps.storage, |config| -> StdResult<Config> { Ok(update(config)) }).unwrap();
+
+    let quote: QuoteResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetQuote {
+                from_quantity: FPDecimal::from(2u128),
+                source_denom: "eth".to_string(),
+                target_denom: "usdt".to_string(),
+                sender: Some(admin.to_owned()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    execute(
+        deps.branch(),
+        mock_env(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    let res = reply(
+        deps.branch(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+
+    (quote, sent_messages(res))
+}
+
+#[test]
+fn it_quotes_the_number_of_bank_messages_the_swap_emits() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let (quote, messages) = quote_and_settle_eth_swap(deps.as_mut_deps(), |config| Config {
+        swap_fee_bps: 5000,
+        ..config
+    });
+
+    let bank_sends = messages.iter().filter(|msg| matches!(msg, CosmosMsg::Bank(_))).count();
+    assert_eq!(bank_sends, 2, "output and fee should be sent");
+    assert_eq!(quote.expected_bank_msgs as usize, bank_sends);
+}
+
+#[test]
+fn it_quotes_no_output_send_for_a_timelocked_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let (quote, messages) = quote_and_settle_eth_swap(deps.as_mut_deps(), |config| Config {
+        swap_fee_bps: 5000,
+        output_timelock_seconds: 3600,
+        ..config
+    });
+
+    assert_eq!(messages.len(), 1, "only the fee should be sent");
+    assert_eq!(quote.expected_bank_msgs as usize, messages.len());
+}
+
+#[test]
+fn it_quotes_a_fee_netted_into_the_output_of_the_fee_recipient() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let (quote, messages) = quote_and_settle_eth_swap(deps.as_mut_deps(), |config| Config {
+        swap_fee_bps: 5000,
+        fee_in_output_denom: true,
+        ..config
+    });
+
+    assert_eq!(messages.len(), 1, "the fee should go out with the output");
+    assert_eq!(quote.expected_bank_msgs as usize, messages.len());
+}
+
+#[test]
+fn it_rejects_swaps_along_a_disabled_route_until_it_is_re_enabled() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let swap_msg = min_output_swap("usdt");
+    let set_route_enabled_msg = |enabled: bool| ExecuteMsg::SetRouteEnabled {
+        source_denom: "eth".to_string(),
+        target_denom: "usdt".to_string(),
+        enabled,
+    };
+
+    execute(deps.as_mut_deps(), mock_env(), message_info(&admin, &[]), set_route_enabled_msg(false)).unwrap();
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        swap_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&err, ContractError::RouteDisabled { source_denom, target_denom } if source_denom == "eth" && target_denom == "usdt"),
+        "wrong error: {err}"
+    );
+    assert!(
+        read_swap_route(deps.as_ref().storage, "eth", "usdt").is_ok(),
+        "disabled route should be kept"
+    );
+
+    execute(deps.as_mut_deps(), mock_env(), message_info(&admin, &[]), set_route_enabled_msg(true)).unwrap();
+    execute(deps.as_mut_deps(), mock_env(), message_info(&admin, &[coin(2u128, "eth")]), swap_msg)
+        .expect("swap along the re-enabled route should start");
+}
+
+#[test]
+fn it_rejects_setting_route_enabled_by_non_admin() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked("not_admin"), &[]),
+        ExecuteMsg::SetRouteEnabled {
+            source_denom: "eth".to_string(),
+            target_denom: "usdt".to_string(),
+            enabled: false,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}), "wrong error: {err}");
+    assert!(
+        read_swap_route(deps.as_ref().storage, "eth", "usdt").unwrap().enabled,
+        "route should stay enabled"
+    );
+}
+
+#[test]
+fn it_exempts_admin_swaps_from_the_contract_fee() {
+    for (sender, expected_fee) in [(TEST_USER_ADDR, 0u128), ("other_user", 2)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        instantiate_with_eth_usdt_route(deps.as_mut_deps());
+        CONFIG
+            .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+                Ok(Config {
+                    swap_fee_bps: 5000,
+                    admin_fee_exempt: true,
+                    ..config
+                })
+            })
+            .unwrap();
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&Addr::unchecked(sender), &[coin(4u128, "eth")]),
+            min_output_swap("usdt"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap().fee,
+            coin(expected_fee, "eth"),
+            "wrong fee charged to {sender}"
+        );
+    }
+}
+
+#[test]
+fn it_charges_the_route_fee_instead_of_the_global_one() {
+    // the global fee is 50%, so 2 of the 4 eth without a route fee
+    for (route_fee_bps, expected_fee) in [(Some(2500u16), 1u128), (Some(0), 0), (None, 2)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+        CONFIG
+            .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+                Ok(Config {
+                    swap_fee_bps: 5000,
+                    ..config
+                })
+            })
+            .unwrap();
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[]),
+            ExecuteMsg::SetRouteFee {
+                source_denom: "usdt".to_string(),
+                target_denom: "eth".to_string(),
+                fee_bps: route_fee_bps,
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&Addr::unchecked("other_user"), &[coin(4u128, "eth")]),
+            min_output_swap("usdt"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap().fee,
+            coin(expected_fee, "eth"),
+            "wrong fee charged for route fee {route_fee_bps:?}"
+        );
+    }
+}
+
+#[test]
+fn it_decodes_order_fill_from_atomic_order_reply() {
+    let reply = mock_atomic_order_reply(
+        FPDecimal::must_from_str("1.5"),
+        FPDecimal::from(201000u128),
+        FPDecimal::must_from_str("753.75"),
+    );
+
+    let order_fill = parse_order_fill(reply).unwrap();
+
+    assert_eq!(
+        order_fill,
+        OrderFill {
+            quantity: FPDecimal::must_from_str("1.5"),
+            price: FPDecimal::from(201000u128),
+            fee: FPDecimal::must_from_str("753.75"),
+        }
+    );
+    assert_eq!(
+        order_fill.output_quantity(true),
+        FPDecimal::must_from_str("1.5"),
+        "buys deliver the base quantity"
+    );
+    assert_eq!(
+        order_fill.output_quantity(false),
+        FPDecimal::must_from_str("300746.25"),
+        "sells deliver the proceeds net of the fee"
+    );
+}
+
+#[test]
+fn it_settles_swap_only_when_its_output_value_is_met() {
+    let min_output_value_swap = |min_value: u128| CurrentSwapOperation {
+        min_output_value: Some(MinOutputValue {
+            value: FPDecimal::from(min_value),
+            oracle_type: OracleType::PriceFeed,
+            base: "usdt".to_string(),
+            quote: "usd".to_string(),
+        }),
+        ..swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+
+    // sells 1 eth for 201000 usdt, worth 201000 usd at the oracle price
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    deps.querier.oracle_price_response_handler = create_oracle_price_handler(FPDecimal::ONE);
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &min_output_value_swap(200000), sell_eth_fill(1)).unwrap();
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(201000u128, "usdt")],
+        })]
+    );
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    deps.querier.oracle_price_response_handler = create_oracle_price_handler(FPDecimal::ONE);
+    let err = settle_swap(deps.as_mut_deps(), &test_config(), &min_output_value_swap(202000), sell_eth_fill(1)).unwrap_err();
+    assert!(
+        matches!(err, ContractError::OutputValueNotReached { value, min_value } if value == FPDecimal::from(201000u128) && min_value == FPDecimal::from(202000u128)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_retries_a_failed_hop_with_a_wider_price() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let env = mock_env();
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                hop_retry_price_band_bps: 1000,
+                ..config
+            },
+        )
+        .unwrap();
+
+    let execute_response = execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    assert_eq!(
+        execute_response.messages[0].reply_on,
+        ReplyOn::Always,
+        "failure of the first order should be replied to"
+    );
+
+    let retry_response = reply(deps.as_mut_deps(), env.to_owned(), mock_failed_atomic_order_reply()).unwrap();
+
+    // the worst price of selling 2 eth is 201000, widened by 10%
+    let expected_order = SpotOrder::new(
+        FPDecimal::from(180900u128),
+        FPDecimal::from(2u128),
+        OrderType::SellAtomic,
+        &MarketId::unchecked(TEST_MARKET_ID_1),
+        get_default_subaccount_id_for_checked_address(&env.contract.address),
+        Some(Addr::unchecked(TEST_USER_ADDR)),
+        None,
+    );
+    assert_eq!(retry_response.messages.len(), 1);
+    assert_eq!(
+        retry_response.messages[0].msg,
+        create_spot_market_order_msg(env.contract.address.to_owned(), expected_order)
+    );
+    assert_eq!(retry_response.messages[0].reply_on, ReplyOn::Success, "no retry should be left");
+    assert_eq!(STEP_STATE.load(deps.as_ref().storage).unwrap().retry_count, 1);
+
+    let final_response = reply(
+        deps.as_mut_deps(),
+        env,
+        mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    assert_eq!(
+        final_response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(402000u128, "usdt")],
+        })
+    );
+}
+
+#[test]
+fn it_does_not_widen_the_retry_price_of_the_last_hop_past_the_min_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let env = mock_env();
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                hop_retry_price_band_bps: 1000,
+                ..config
+            },
+        )
+        .unwrap();
+    execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::from(400000u128),
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let retry_response = reply(deps.as_mut_deps(), env.to_owned(), mock_failed_atomic_order_reply()).unwrap();
+
+    // 2 eth have to sell for 400000 usdt after the 0.25% atomic taker fee, instead of at 180900 the band would allow
+    let expected_order = SpotOrder::new(
+        FPDecimal::must_from_str("200501.254"),
+        FPDecimal::from(2u128),
+        OrderType::SellAtomic,
+        &MarketId::unchecked(TEST_MARKET_ID_1),
+        get_default_subaccount_id_for_checked_address(&env.contract.address),
+        Some(Addr::unchecked(TEST_USER_ADDR)),
+        None,
+    );
+    assert_eq!(
+        retry_response.messages[0].msg,
+        create_spot_market_order_msg(env.contract.address.to_owned(), expected_order)
+    );
+}
+
+#[test]
+fn it_fails_the_swap_when_the_hop_retry_is_exhausted() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                hop_retry_price_band_bps: 1000,
+                ..config
+            },
+        )
+        .unwrap();
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    reply(deps.as_mut_deps(), mock_env(), mock_failed_atomic_order_reply()).unwrap();
+
+    let err = reply(deps.as_mut_deps(), mock_env(), mock_failed_atomic_order_reply()).unwrap_err();
+
+    assert!(matches!(err, ContractError::SubMsgFailure(_)), "wrong error: {err}");
+}
+
+#[test]
+fn it_does_not_retry_a_hop_failing_for_another_reason_than_its_price() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                hop_retry_price_band_bps: 1000,
+                ..config
+            },
+        )
+        .unwrap();
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+
+    // insufficient funds
+    let failed_reply = Reply {
+        result: SubMsgResult::Err("codespace: sdk, code: 5".to_string()),
+        ..mock_failed_atomic_order_reply()
+    };
+    let err = reply(deps.as_mut_deps(), mock_env(), failed_reply).unwrap_err();
+
+    assert!(matches!(err, ContractError::SubMsgFailure(_)), "wrong error: {err}");
+    assert_eq!(
+        STEP_STATE.load(deps.as_ref().storage).unwrap().retry_count,
+        0,
+        "the step should not be retried"
+    );
+}
+
+#[test]
+fn it_aborts_settlement_when_the_contract_balance_drifted_below_the_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    // the order proceeds are not held by the contract anymore
+    deps.querier.balance_query_handler = create_simple_balance_bank_query_handler(vec![coin(100u128, "usdt")]);
+
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+    let err = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(1)).unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::BalanceDrift { denom, required, available }
+            if denom == "usdt" && *required == Uint128::new(201000) && *available == Uint128::new(100)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_settles_a_swap_only_if_it_leaves_the_reserve() {
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+
+    // the contract holds the 201000 usdt of the output and 500 more
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    deps.querier.balance_query_handler = create_simple_balance_bank_query_handler(vec![coin(201500u128, "usdt")]);
+    RESERVES.save(deps.as_mut_deps().storage, "usdt".to_string(), &Uint128::new(500)).unwrap();
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(1)).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(201000u128, "usdt")],
+        })
+    );
+
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    deps.querier.balance_query_handler = create_simple_balance_bank_query_handler(vec![coin(201500u128, "usdt")]);
+    RESERVES.save(deps.as_mut_deps().storage, "usdt".to_string(), &Uint128::new(501)).unwrap();
+    let err = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(1)).unwrap_err();
+    assert!(
+        matches!(&err, ContractError::ReserveProtected { denom, reserve, remaining }
+            if denom == "usdt" && *reserve == Uint128::new(501) && *remaining == Uint128::new(500)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_sends_the_burned_share_of_the_fee_to_the_burn_address() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let burn_address = deps.api.addr_make("burn");
+    let config = Config {
+        fee_burn_bps: 2500,
+        burn_address: Some(burn_address.to_owned()),
+        ..test_config()
+    };
+    let swap_operation = CurrentSwapOperation {
+        fee: coin(4u128, "eth"),
+        ..swap_operation(coin(13u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(
+        sent_messages(res),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(1809000u128, "usdt")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: burn_address.to_string(),
+                amount: vec![coin(1u128, "eth")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(3u128, "eth")],
+            }),
+        ],
+        "a quarter of the fee should be burned and the rest sent to the fee recipient"
+    );
+}
+
+#[test]
+fn it_swaps_only_along_valid_and_allowed_custom_routes() {
+    let swap_along = |allow_custom_routes: bool, route: Vec<MarketId>| {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_admin(deps.as_mut_deps());
+        let config = CONFIG.load(deps.as_ref().storage).unwrap();
+        CONFIG
+            .save(
+                deps.as_mut_deps().storage,
+                &Config {
+                    allow_custom_routes,
+                    ..config
+                },
+            )
+            .unwrap();
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(2u128, "eth")]),
+            ExecuteMsg::SwapMinOutputWithRoute {
+                target_denom: "usdt".to_string(),
+                min_output_quantity: FPDecimal::ONE,
+                route,
+            },
+        )
+    };
+
+    let res = swap_along(true, vec![TEST_MARKET_ID_1.into()]).unwrap();
+    assert_eq!(res.messages.len(), 1, "the order of the first step should be placed");
+
+    let err = swap_along(true, vec![TEST_MARKET_ID_2.into()]).unwrap_err();
+    assert!(
+        matches!(&err, ContractError::RouteDenomMismatch { denom, market_id } if denom == "eth" && market_id.as_str() == TEST_MARKET_ID_2),
+        "wrong error: {err}"
+    );
+
+    let err = swap_along(false, vec![TEST_MARKET_ID_1.into()]).unwrap_err();
+    assert!(matches!(err, ContractError::CustomRoutesDisabled {}), "wrong error: {err}");
+}
+
+#[test]
+fn it_requires_a_commit_for_swaps_above_the_large_swap_threshold_of_their_input_denom() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let sender = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    LARGE_SWAP_THRESHOLDS
+        .save(deps.as_mut_deps().storage, "eth".to_string(), &Uint128::one())
+        .unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&sender, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, ContractError::LargeSwapRequiresCommit { amount, threshold } if amount == Uint128::new(2) && threshold == Uint128::one()),
+        "wrong error: {err}"
+    );
+
+    // a threshold in another denom says nothing about the size of an eth input
+    LARGE_SWAP_THRESHOLDS.remove(deps.as_mut_deps().storage, "eth".to_string());
+    LARGE_SWAP_THRESHOLDS
+        .save(deps.as_mut_deps().storage, "usdt".to_string(), &Uint128::one())
+        .unwrap();
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&sender, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn it_rejects_a_swap_while_another_is_in_flight() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    // the order reply never arrives, so the swap stays in flight
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    let in_flight = SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked("other_user"), &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::SwapInFlight {}), "wrong error: {err}");
+    assert_eq!(
+        SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap(),
+        in_flight,
+        "the swap in flight should be left as is"
+    );
+}
+
+#[test]
+fn it_rejects_a_swap_next_to_a_pending_large_swap_commit() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let sender = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let commit = LargeSwapCommit {
+        target_denom: "usdt".to_string(),
+        swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+        funds: vec![coin(10u128, "eth")],
+        committed_at_height: mock_env().block.height,
+        acknowledge_high_impact: false,
+    };
+    LARGE_SWAP_COMMITS.save(deps.as_mut_deps().storage, &sender, &commit).unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&sender, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::PendingLargeSwapCommit {}), "wrong error: {err}");
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked("other_user"), &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .expect("other senders should still be able to swap");
+}
+
+#[test]
+fn it_rejects_a_large_swap_commit_while_a_swap_is_in_flight() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    // the order reply never arrives, so the swap stays in flight
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+
+    let sender = Addr::unchecked("other_user");
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&sender, &[coin(2u128, "eth")]),
+        ExecuteMsg::CommitLargeSwap {
+            target_denom: "usdt".to_string(),
+            swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::SwapInFlight {}), "wrong error: {err}");
+    assert!(!LARGE_SWAP_COMMITS.has(deps.as_ref().storage, &sender));
+}
+
+#[test]
+fn it_executes_a_committed_large_swap_only_after_the_delay() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let sender = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut_deps().storage,
+            &Config {
+                large_swap_delay_blocks: 2,
+                ..config
+            },
+        )
+        .unwrap();
+    LARGE_SWAP_THRESHOLDS
+        .save(deps.as_mut_deps().storage, "eth".to_string(), &Uint128::one())
+        .unwrap();
+    let committed_at_height = mock_env().block.height;
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&sender, &[coin(2u128, "eth")]),
+        ExecuteMsg::CommitLargeSwap {
+            target_denom: "usdt".to_string(),
+            swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.height = committed_at_height + 1;
+    let err = execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&sender, &[]),
+        ExecuteMsg::ExecuteLargeSwap {},
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, ContractError::LargeSwapNotReady { ready_at_height } if ready_at_height == committed_at_height + 2),
+        "wrong error: {err}"
+    );
+
+    env.block.height = committed_at_height + 2;
+    let res = execute(deps.as_mut_deps(), env, message_info(&sender, &[]), ExecuteMsg::ExecuteLargeSwap {}).unwrap();
+
+    assert_eq!(res.messages.len(), 1, "the order of the committed swap should be placed");
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_mut_deps().storage).unwrap();
+    assert_eq!(swap_operation.input_funds, coin(2u128, "eth"), "the committed funds should be swapped");
+    assert!(
+        !LARGE_SWAP_COMMITS.has(deps.as_mut_deps().storage, &sender),
+        "the commit should be consumed by the execution"
+    );
+}
+
+#[test]
+fn it_delivers_the_output_of_all_slices_of_a_sliced_swap() {
+    // swaps 4 eth to usdt with every order filled at 201000, returns the inputs of the placed orders and the settlement
+    let swap_in_slices = |slices: Option<u8>| {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+        let mut res = execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(4u128, "eth")]),
+            ExecuteMsg::SwapMinOutput {
+                target_denom: "usdt".to_string(),
+                min_output_quantity: FPDecimal::ONE,
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps: None,
+                min_output_value: None,
+                slices,
+                acknowledge_high_impact: false,
+            },
+        )
+        .unwrap();
+
+        let mut order_inputs = vec![];
+        while let Ok(current_step) = STEP_STATE.load(deps.as_mut_deps().storage) {
+            assert_eq!(res.messages.len(), 1, "one order should be placed at a time");
+            let order_input = current_step.current_balance.amount;
+            order_inputs.push(order_input);
+            res = reply(
+                deps.as_mut_deps(),
+                mock_env(),
+                mock_atomic_order_reply(order_input, FPDecimal::from(201000u128), FPDecimal::ZERO),
+            )
+            .unwrap();
+        }
+
+        (order_inputs, sent_messages(res))
+    };
+
+    let (single_order_inputs, single_order_settlement) = swap_in_slices(None);
+    let (sliced_inputs, sliced_settlement) = swap_in_slices(Some(2));
+
+    assert_eq!(single_order_inputs, vec![FPDecimal::from(4u128)]);
+    assert_eq!(
+        sliced_inputs,
+        vec![FPDecimal::from(2u128), FPDecimal::from(2u128)],
+        "the input should be split across the slices"
+    );
+    assert_eq!(
+        single_order_settlement,
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(804000u128, "usdt")],
+        })]
+    );
+    assert_eq!(
+        sliced_settlement, single_order_settlement,
+        "slices filled at the same price should settle the output of a single order"
+    );
+}
+
+#[test]
+fn it_splits_an_uneven_input_into_tick_aligned_slices() {
+    // eth is traded in multiples of 2, so 8 eth in 3 slices can't be split into equal ones
+    let mut deps = mock_deps_eth_inj_with_eth_quantity_tick(FPDecimal::from(2u128));
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    let mut res = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(8u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: Some(3),
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let mut order_inputs = vec![];
+    while let Ok(current_step) = STEP_STATE.load(deps.as_mut_deps().storage) {
+        let order_input = current_step.current_balance.amount;
+        order_inputs.push(order_input);
+        res = reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(order_input, FPDecimal::from(201000u128), FPDecimal::ZERO),
+        )
+        .unwrap();
+    }
+
+    assert_eq!(
+        order_inputs,
+        vec![FPDecimal::from(2u128), FPDecimal::from(2u128), FPDecimal::from(4u128)],
+        "the slices should be rounded down to the tick, with the remainder in the last one"
+    );
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1608000u128, "usdt")],
+        })]
+    );
+}
+
+#[test]
+fn it_rejects_more_slices_than_the_cap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(4u128, "eth")]),
+        ExecuteMsg::SwapMinOutput {
+            target_denom: "usdt".to_string(),
+            min_output_quantity: FPDecimal::ONE,
+            auto_convert_to: None,
+            memo: None,
+            input_fraction_bps: None,
+            min_output_value: None,
+            slices: Some(MAX_SWAP_SLICES + 1),
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidSlices { .. }), "wrong error: {err}");
+}
+
+#[test]
+fn it_aligns_an_exact_output_to_the_quantity_tick_unless_rejecting() {
+    // swaps usdt to the requested inj output, returns the result and the started swap
+    let swap_usdt_to_inj_output = |target_output_quantity: &str, reject_unaligned_exact_output: bool, reject_sub_tick_amounts: bool| {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_admin(deps.as_mut_deps());
+        set_route(
+            deps.as_mut_deps(),
+            &admin,
+            "usdt".to_string(),
+            "inj".to_string(),
+            vec![TEST_MARKET_ID_2.into()],
+        )
+        .unwrap();
+        let config = CONFIG.load(deps.as_ref().storage).unwrap();
+        CONFIG
+            .save(
+                deps.as_mut_deps().storage,
+                &Config {
+                    reject_unaligned_exact_output,
+                    reject_sub_tick_amounts,
+                    ..config
+                },
+            )
+            .unwrap();
+
+        let result = execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(1000000u128, "usdt")]),
+            ExecuteMsg::SwapExactOutput {
+                target_denom: "inj".to_string(),
+                target_output_quantity: FPDecimal::must_from_str(target_output_quantity),
+                max_input: None,
+                compound_refund: false,
+                memo: None,
+                acknowledge_high_impact: false,
+            },
+        );
+        (result, SWAP_OPERATION_STATE.may_load(deps.as_mut_deps().storage).unwrap())
+    };
+
+    for (target_output_quantity, aligned_output_quantity) in [("10.0005", "10.001"), ("0.0005", "0.001")] {
+        let (result, swap_operation) = swap_usdt_to_inj_output(target_output_quantity, false, false);
+        result.unwrap();
+        assert_eq!(
+            swap_operation.unwrap().swap_quantity_mode,
+            SwapQuantityMode::ExactOutputQuantity(FPDecimal::must_from_str(aligned_output_quantity)),
+            "{target_output_quantity} should be rounded up to the 0.001 quantity tick"
+        );
+    }
+
+    let (result, _) = swap_usdt_to_inj_output("10.0005", true, false);
+    let err = result.unwrap_err();
+    assert!(
+        matches!(err, ContractError::UnalignedOutputQuantity { quantity, min_quantity_tick_size }
+            if quantity == FPDecimal::must_from_str("10.0005") && min_quantity_tick_size == FPDecimal::must_from_str("0.001")),
+        "wrong error: {err}"
+    );
+
+    let (result, swap_operation) = swap_usdt_to_inj_output("0.0005", false, true);
+    let err = result.unwrap_err();
+    assert!(
+        matches!(err, ContractError::AmountBelowTick { amount, min_quantity_tick_size }
+            if amount == FPDecimal::must_from_str("0.0005") && min_quantity_tick_size == FPDecimal::must_from_str("0.001")),
+        "wrong error: {err}"
+    );
+    assert!(swap_operation.is_none(), "no swap should be started");
+}
+
+#[test]
+fn it_delivers_output_registered_as_cw20_through_its_adapter() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    let adapter = deps.api.addr_make("cw20_adapter");
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[]),
+        ExecuteMsg::SetCw20Output {
+            denom: "usdt".to_string(),
+            adapter: Some(adapter.to_owned()),
+        },
+    )
+    .unwrap();
+
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(1)).unwrap();
+
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: adapter.to_string(),
+            msg: to_json_binary(&Cw20AdapterMsg::RedeemAndTransfer {
+                recipient: Some(TEST_USER_ADDR.to_string()),
+            })
+            .unwrap(),
+            funds: vec![coin(201000u128, "usdt")],
+        })],
+        "the output should be redeemed as the cw20 token for the sender"
+    );
+}
+
+#[test]
+fn it_skips_refunds_below_the_min_refund_amount() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        min_refund_amount: Uint128::new(5),
+        ..test_config()
+    };
+    let swap_operation = CurrentSwapOperation {
+        refund: coin(3u128, "eth"),
+        ..swap_operation(coin(4u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(1)).unwrap();
+
+    let swap_event = res.events.iter().find(|event| event.ty == "atomic_swap_execution").unwrap();
+    assert!(
+        swap_event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "skipped_refund_amount" && attr.value == "3"),
+        "the skipped refund should be reported"
+    );
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(201000u128, "usdt")],
+        })],
+        "only the output should be sent"
+    );
+}
+
+#[test]
+fn it_rejects_funds_in_a_denom_without_a_route_to_the_target() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "usdt".to_string(),
+        "eth".to_string(),
+        vec![TEST_MARKET_ID_1.into()],
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(10u128, "atom")]),
+        min_output_swap("eth"),
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::WrongInputDenom { expected, got } if expected == "usdt" && got == "atom"),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_rounds_a_fractional_output_down_for_delivery() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+
+    // the order fee leaves 200999.5 usdt
+    let fill = mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::must_from_str("0.5"));
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, fill).unwrap();
+
+    let swap_event = res.events.iter().find(|event| event.ty == "atomic_swap_execution").unwrap();
+    assert!(
+        swap_event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "truncated_output_amount" && attr.value == "0.5"),
+        "the truncated fraction should be reported"
+    );
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(200999u128, "usdt")],
+        })],
+        "the output should be rounded down"
+    );
+    assert_eq!(
+        TRUNCATED_OUTPUTS.load(deps.as_ref().storage, "usdt".to_string()).unwrap(),
+        FPDecimal::must_from_str("0.5")
+    );
+}
+
+#[test]
+fn it_credits_the_truncated_fractions_of_all_outputs_to_the_contract() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let swap_operation = swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+    let fill_with_order_fee = |fee: &str| mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::must_from_str(fee));
+
+    settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, fill_with_order_fee("0")).unwrap();
+    assert!(
+        TRUNCATED_OUTPUTS.may_load(deps.as_ref().storage, "usdt".to_string()).unwrap().is_none(),
+        "a whole output leaves nothing to credit"
+    );
+
+    settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, fill_with_order_fee("0.5")).unwrap();
+    settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, fill_with_order_fee("0.75")).unwrap();
+    assert_eq!(
+        TRUNCATED_OUTPUTS.load(deps.as_ref().storage, "usdt".to_string()).unwrap(),
+        FPDecimal::must_from_str("1.25")
+    );
+}
+
+#[test]
+fn it_refunds_all_in_flight_swaps_in_an_emergency() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+
+    // the order reply never arrives, so the swap stays in flight
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap();
+    let committers = [deps.api.addr_make("first"), deps.api.addr_make("second")];
+    for (idx, committer) in committers.iter().enumerate() {
+        let commit = LargeSwapCommit {
+            target_denom: "usdt".to_string(),
+            swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+            funds: vec![coin(10u128 + idx as u128, "eth")],
+            committed_at_height: mock_env().block.height,
+            acknowledge_high_impact: false,
+        };
+        LARGE_SWAP_COMMITS.save(deps.as_mut_deps().storage, committer, &commit).unwrap();
+    }
+
+    let mut later_env = mock_env();
+    later_env.block.time = later_env.block.time.plus_seconds(6);
+    let err = execute(
+        deps.as_mut_deps(),
+        later_env.to_owned(),
+        message_info(&committers[0], &[]),
+        ExecuteMsg::RefundAllInFlight {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}), "wrong error: {err}");
+
+    let res = execute(deps.as_mut_deps(), later_env, message_info(&admin, &[]), ExecuteMsg::RefundAllInFlight {}).unwrap();
+
+    let messages: Vec<CosmosMsg<InjectiveMsgWrapper>> = res.messages.into_iter().map(|sub_msg| sub_msg.msg).collect();
+    let expected_refunds = [
+        (admin.to_owned(), 1u128),
+        (committers[0].to_owned(), 10u128),
+        (committers[1].to_owned(), 11u128),
+    ];
+    assert_eq!(messages.len(), expected_refunds.len());
+    for (to_address, amount) in expected_refunds {
+        assert!(
+            messages.contains(&CosmosMsg::Bank(BankMsg::Send {
+                to_address: to_address.to_string(),
+                amount: vec![coin(amount, "eth")],
+            })),
+            "{to_address} should get {amount}eth back"
+        );
+    }
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_ref().storage).unwrap().is_none());
+    assert!(STEP_STATE.may_load(deps.as_ref().storage).unwrap().is_none());
+    for committer in &committers {
+        assert!(LARGE_SWAP_COMMITS.may_load(deps.as_ref().storage, committer).unwrap().is_none());
+    }
+    assert!(
+        res.attributes.iter().any(|attr| attr.key == "has_more" && attr.value == "false"),
+        "all commits fit in one batch"
+    );
+}
+
+#[test]
+fn it_keeps_the_protocol_share_of_an_execution_better_than_estimated() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        positive_slippage_bps: 2000,
+        ..test_config()
+    };
+    let swap_operation = CurrentSwapOperation {
+        estimated_output: Some(FPDecimal::from(200000u128)),
+        ..swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+
+    // the order fills for 201000 usdt
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(1)).unwrap();
+
+    let swap_event = res.events.iter().find(|event| event.ty == "atomic_swap_execution").unwrap();
+    assert!(
+        swap_event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "positive_slippage_take" && attr.value == "200"),
+        "the protocol take should be reported"
+    );
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(200800u128, "usdt")],
+        })],
+        "the sender should get the estimate and 80% of the 1000 usdt surplus"
+    );
+}
+
+#[test]
+fn it_caps_the_protocol_share_of_a_better_execution_at_the_min_output() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        positive_slippage_bps: 5000,
+        ..test_config()
+    };
+    let swap_operation = CurrentSwapOperation {
+        estimated_output: Some(FPDecimal::from(100u128)),
+        ..swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::from(110u128)))
+    };
+
+    // the order fills for 112 usdt, half of the 12 usdt surplus would leave the sender below the min output
+    let fill = mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(112u128), FPDecimal::ZERO);
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, fill).unwrap();
+
+    let swap_event = res.events.iter().find(|event| event.ty == "atomic_swap_execution").unwrap();
+    assert!(
+        swap_event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "positive_slippage_take" && attr.value == "2"),
+        "only what exceeds the min output should be taken"
+    );
+    assert_eq!(
+        sent_messages(res),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(110u128, "usdt")],
+        })],
+        "the sender should get the min output"
+    );
+}
+
+#[test]
+fn it_delivers_the_whole_output_when_execution_does_not_beat_the_estimate() {
+    for (estimated_output, positive_slippage_bps) in [(201000u128, 2000u16), (202000, 2000), (200000, 0)] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let config = Config {
+            positive_slippage_bps,
+            ..test_config()
+        };
+        let swap_operation = CurrentSwapOperation {
+            estimated_output: Some(FPDecimal::from(estimated_output)),
+            ..swap_operation(coin(1u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+        };
+
+        // the order fills for 201000 usdt
+        let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(1)).unwrap();
+
+        assert_eq!(
+            sent_messages(res),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_USER_ADDR.to_string(),
+                amount: vec![coin(201000u128, "usdt")],
+            })],
+            "nothing should be taken with estimate {estimated_output} and {positive_slippage_bps} bps"
+        );
+    }
+}
+
+#[test]
+fn it_reports_the_estimated_required_and_consumed_input_of_exact_output_swaps() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "usdt".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(1000000u128, "usdt")]),
+        ExecuteMsg::SwapExactOutput {
+            target_denom: "inj".to_string(),
+            target_output_quantity: FPDecimal::from(10u128),
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(10u128), FPDecimal::from(800u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+
+    let swap_event = res.events.iter().find(|event| event.ty == "atomic_swap_execution").unwrap();
+    let attribute = |key: &str| -> FPDecimal {
+        let attr = swap_event.attributes.iter().find(|attr| attr.key == key);
+        FPDecimal::must_from_str(&attr.unwrap_or_else(|| panic!("{key} should be reported")).value)
+    };
+    let estimated_input = attribute("estimated_input");
+    let required_input = attribute("required_input");
+    let actual_consumed = attribute("actual_consumed");
+    assert!(
+        estimated_input <= required_input,
+        "the estimate {estimated_input} should not exceed the required input {required_input}"
+    );
+    assert!(actual_consumed > FPDecimal::ZERO, "the swap should have consumed input");
+}
+
+#[test]
+fn it_refunds_what_the_rounded_up_required_input_and_fee_leave_of_an_exact_output_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "usdt".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+    CONFIG
+        .update(deps.as_mut_deps().storage, |config| -> StdResult<Config> {
+            Ok(Config { swap_fee_bps: 100, ..config })
+        })
+        .unwrap();
+
+    execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&Addr::unchecked("other_user"), &[coin(1000000u128, "usdt")]),
+        ExecuteMsg::SwapExactOutput {
+            target_denom: "inj".to_string(),
+            target_output_quantity: FPDecimal::from(10u128),
+            max_input: None,
+            compound_refund: false,
+            memo: None,
+            acknowledge_high_impact: false,
+        },
+    )
+    .unwrap();
+
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap();
+    let estimated_input = swap_operation.exact_output_input.unwrap().estimated;
+    let swapped = STEP_STATE.load(deps.as_ref().storage).unwrap().current_balance.amount;
+    assert!(
+        swapped > estimated_input,
+        "the fractional estimate {estimated_input} should be rounded up, got {swapped}"
+    );
+    assert!(!swap_operation.fee.amount.is_zero(), "the swap should be charged a fee");
+    assert_eq!(
+        swapped + FPDecimal::from(swap_operation.fee.amount) + FPDecimal::from(swap_operation.refund.amount),
+        FPDecimal::from(1000000u128),
+        "the swapped input, the fee and the refund should add up to the provided funds"
+    );
+}
+
+fn instantiate_with_portfolio_routes(mut deps: DepsMut<InjectiveQueryWrapper>) -> Addr {
+    let admin = instantiate_with_admin(deps.branch());
+    for (target_denom, market_id) in [("eth", TEST_MARKET_ID_1), ("inj", TEST_MARKET_ID_2)] {
+        set_route(
+            deps.branch(),
+            &admin,
+            "usdt".to_string(),
+            target_denom.to_string(),
+            vec![market_id.into()],
+        )
+        .unwrap();
+    }
+    admin
+}
+
+fn swap_usdt_to_eth_and_inj(
+    deps: DepsMut<InjectiveQueryWrapper>,
+    sender: &Addr,
+    min_eth: u128,
+    min_inj: u128,
+) -> Result<Response<InjectiveMsgWrapper>, ContractError> {
+    execute(
+        deps,
+        mock_env(),
+        message_info(sender, &[coin(800000u128, "usdt")]),
+        ExecuteMsg::SwapToPortfolio {
+            source_denom: "usdt".to_string(),
+            targets: vec![
+                PortfolioTarget {
+                    denom: "eth".to_string(),
+                    weight_bps: 5000,
+                    min_output_quantity: FPDecimal::from(min_eth),
+                },
+                PortfolioTarget {
+                    denom: "inj".to_string(),
+                    weight_bps: 5000,
+                    min_output_quantity: FPDecimal::from(min_inj),
+                },
+            ],
+        },
+    )
+}
+
+#[test]
+fn it_swaps_into_a_portfolio_one_target_after_another() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_portfolio_routes(deps.as_mut_deps());
+
+    swap_usdt_to_eth_and_inj(deps.as_mut_deps(), &admin, 2, 500).unwrap();
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        swap_operation.input_funds,
+        coin(400000u128, "usdt"),
+        "eth should be bought with half of the input"
+    );
+    assert_eq!(
+        swap_operation.portfolio,
+        vec![PortfolioLeg {
+            target_denom: "inj".to_string(),
+            input: coin(400000u128, "usdt"),
+            min_output_quantity: FPDecimal::from(500u128),
+        }]
+    );
+
+    // settling the eth part starts the inj part
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(200000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2, "expected the eth output and the inj order");
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: admin.to_string(),
+            amount: vec![coin(2u128, "eth")],
+        })
+    );
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        swap_operation.input_funds,
+        coin(400000u128, "usdt"),
+        "inj should be bought with the other half"
+    );
+    assert!(swap_operation.portfolio.is_empty());
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(500u128), FPDecimal::from(800u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    let messages: Vec<CosmosMsg<InjectiveMsgWrapper>> = res.messages.into_iter().map(|sub_msg| sub_msg.msg).collect();
+    assert_eq!(
+        messages,
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: admin.to_string(),
+            amount: vec![coin(500u128, "inj")],
+        })]
+    );
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_ref().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_holds_each_portfolio_part_to_the_min_output_of_its_target() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_portfolio_routes(deps.as_mut_deps());
+
+    swap_usdt_to_eth_and_inj(deps.as_mut_deps(), &admin, 2, 501).unwrap();
+    reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(200000u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+    let swap_operation = SWAP_OPERATION_STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        swap_operation.swap_quantity_mode,
+        SwapQuantityMode::MinOutputQuantity(FPDecimal::from(501u128)),
+        "the inj part should be swapped for at least the min output of its target"
+    );
+
+    let err = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(500u128), FPDecimal::from(800u128), FPDecimal::ZERO),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, ContractError::MinOutputAmountNotReached(min_output) if min_output == FPDecimal::from(501u128)),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_holds_the_whole_portfolio_input_to_the_large_swap_threshold() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_portfolio_routes(deps.as_mut_deps());
+    LARGE_SWAP_THRESHOLDS
+        .save(deps.as_mut_deps().storage, "usdt".to_string(), &Uint128::new(500000))
+        .unwrap();
+
+    // each half stays below the threshold, the whole input does not
+    let err = swap_usdt_to_eth_and_inj(deps.as_mut_deps(), &admin, 1, 1).unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::LargeSwapRequiresCommit { amount, .. } if amount == Uint128::new(800000)),
+        "wrong error: {err}"
+    );
+    assert!(SWAP_OPERATION_STATE.may_load(deps.as_ref().storage).unwrap().is_none());
+}
+
+#[test]
+fn it_rejects_portfolio_weights_not_adding_up() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(800000u128, "usdt")]),
+        ExecuteMsg::SwapToPortfolio {
+            source_denom: "usdt".to_string(),
+            targets: vec![
+                PortfolioTarget {
+                    denom: "eth".to_string(),
+                    weight_bps: 5000,
+                    min_output_quantity: FPDecimal::ONE,
+                },
+                PortfolioTarget {
+                    denom: "inj".to_string(),
+                    weight_bps: 4000,
+                    min_output_quantity: FPDecimal::ONE,
+                },
+            ],
+        },
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, ContractError::InvalidPortfolioWeights { total_bps: 9000 }),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_requires_acknowledging_swaps_above_the_price_impact_threshold() {
+    let swap_eth_to_usdt = |eth_amount: u128, acknowledge_high_impact: bool| {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let admin = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+        let config = Config {
+            high_impact_threshold_bps: 100,
+            ..CONFIG.load(deps.as_mut_deps().storage).unwrap()
+        };
+        CONFIG.save(deps.as_mut_deps().storage, &config).unwrap();
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(eth_amount, "eth")]),
+            ExecuteMsg::SwapMinOutput {
+                target_denom: "usdt".to_string(),
+                min_output_quantity: FPDecimal::ONE,
+                auto_convert_to: None,
+                memo: None,
+                input_fraction_bps: None,
+                min_output_value: None,
+                slices: None,
+                acknowledge_high_impact,
+            },
+        )
+    };
+
+    // 6 eth fill down to the 195000 level while the best bid is 201000, an impact of about 298 bps
+    let err = swap_eth_to_usdt(6, false).unwrap_err();
+    assert!(
+        matches!(&err, ContractError::HighImpactNotAcknowledged { impact_bps, threshold_bps: 100 }
+            if *impact_bps > FPDecimal::from(298u128) && *impact_bps < FPDecimal::from(299u128)),
+        "wrong error: {err}"
+    );
+    swap_eth_to_usdt(6, true).unwrap();
+
+    // 1 eth fills entirely at the best bid
+    swap_eth_to_usdt(1, false).unwrap();
+}
+
+#[test]
+fn it_requires_acknowledging_the_price_impact_of_a_committed_large_swap() {
+    let execute_committed_swap = |acknowledge_high_impact: bool| {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        let sender = instantiate_with_eth_usdt_route(deps.as_mut_deps());
+        let config = Config {
+            high_impact_threshold_bps: 100,
+            large_swap_delay_blocks: 1,
+            ..CONFIG.load(deps.as_mut_deps().storage).unwrap()
+        };
+        CONFIG.save(deps.as_mut_deps().storage, &config).unwrap();
+        LARGE_SWAP_THRESHOLDS
+            .save(deps.as_mut_deps().storage, "eth".to_string(), &Uint128::one())
+            .unwrap();
+
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&sender, &[coin(6u128, "eth")]),
+            ExecuteMsg::CommitLargeSwap {
+                target_denom: "usdt".to_string(),
+                swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE),
+                acknowledge_high_impact,
+            },
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.height += 1;
+        execute(deps.as_mut_deps(), env, message_info(&sender, &[]), ExecuteMsg::ExecuteLargeSwap {})
+    };
+
+    // 6 eth fill down to the 195000 level while the best bid is 201000, an impact of about 298 bps
+    let err = execute_committed_swap(false).unwrap_err();
+    assert!(
+        matches!(err, ContractError::HighImpactNotAcknowledged { threshold_bps: 100, .. }),
+        "wrong error: {err}"
+    );
+    execute_committed_swap(true).unwrap();
+}
+
+#[test]
+fn it_reports_the_denom_path_of_a_multi_hop_swap() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    let swap_operation = CurrentSwapOperation {
+        swap_steps: vec![MarketId::unchecked(TEST_MARKET_ID_1), MarketId::unchecked(TEST_MARKET_ID_2)],
+        ..swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::from(1000u128)))
+    };
+    let last_step = CurrentSwapStep {
+        step_idx: 1,
+        current_balance: FPCoin {
+            amount: FPDecimal::from(1809000u128),
+            denom: "usdt".to_string(),
+        },
+        step_target_denom: "inj".to_string(),
+        is_buy: true,
+        retry_count: 0,
+    };
+    SWAP_OPERATION_STATE.save(deps.as_mut_deps().storage, &swap_operation).unwrap();
+    STEP_STATE.save(deps.as_mut_deps().storage, &last_step).unwrap();
+    SWAP_RESULTS.save(deps.as_mut_deps().storage, &vec![]).unwrap();
+
+    let res = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::from(2261u128), FPDecimal::from(800u128), FPDecimal::ZERO),
+    )
+    .unwrap();
+
+    let swap_event = res
+        .events
+        .iter()
+        .find(|event| event.ty == "atomic_swap_execution")
+        .expect("missing swap event");
+    let route_path = swap_event
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "route_path")
+        .expect("missing route_path");
+    assert_eq!(route_path.value, "eth>usdt>inj");
+}
+
+#[test]
+fn it_accumulates_trading_fees_per_market() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    set_route(
+        deps.as_mut_deps(),
+        &admin,
+        "eth".to_string(),
+        "inj".to_string(),
+        vec![TEST_MARKET_ID_1.into(), TEST_MARKET_ID_2.into()],
+    )
+    .unwrap();
+
+    for _ in 0..2 {
+        execute(
+            deps.as_mut_deps(),
+            mock_env(),
+            message_info(&admin, &[coin(2u128, "eth")]),
+            min_output_swap("inj"),
+        )
+        .unwrap();
+        reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(FPDecimal::from(2u128), FPDecimal::from(201000u128), FPDecimal::from(1000u128)),
+        )
+        .unwrap();
+        reply(
+            deps.as_mut_deps(),
+            mock_env(),
+            mock_atomic_order_reply(FPDecimal::from(500u128), FPDecimal::from(800u128), FPDecimal::must_from_str("400.7")),
+        )
+        .unwrap();
+    }
+
+    let query_market_fees = |market_id: &str| -> Uint128 {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetMarketFees {
+                    market_id: MarketId::unchecked(market_id),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(query_market_fees(TEST_MARKET_ID_1), Uint128::new(2000), "wrong eth/usdt market fees");
+    assert_eq!(query_market_fees(TEST_MARKET_ID_2), Uint128::new(800), "wrong inj/usdt market fees");
+    assert_eq!(query_market_fees("unused_market"), Uint128::zero(), "unused market should have no fees");
+}
+
+#[test]
+fn it_holds_back_a_timelocked_output_until_it_can_be_claimed() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let config = Config {
+        output_timelock_seconds: 3600,
+        ..test_config()
+    };
+    let swap_operation = swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+
+    let res = settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert!(res.messages.is_empty(), "the output should not be sent right away");
+    let claimable_at = mock_env().block.time.plus_seconds(3600).seconds();
+    let swap_event = res
+        .events
+        .iter()
+        .find(|event| event.ty == "atomic_swap_execution")
+        .expect("missing swap event");
+    assert!(swap_event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "output_claimable_at" && attr.value == claimable_at.to_string()));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3599);
+    let err = execute(
+        deps.as_mut_deps(),
+        env,
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::ClaimOutput {},
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, ContractError::OutputStillLocked { claimable_at: locked_until } if locked_until == claimable_at),
+        "wrong error: {err}"
+    );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3600);
+    let res = execute(
+        deps.as_mut_deps(),
+        env.to_owned(),
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::ClaimOutput {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1809000u128, "usdt")],
+        })
+    );
+
+    let err = execute(
+        deps.as_mut_deps(),
+        env,
+        message_info(&Addr::unchecked(TEST_USER_ADDR), &[]),
+        ExecuteMsg::ClaimOutput {},
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, ContractError::NoOutputToClaim {}),
+        "the output should only be claimable once"
+    );
+}
+
+#[test]
+fn it_sends_the_output_right_away_without_a_timelock() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let swap_operation = swap_operation(coin(9u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE));
+
+    let res = settle_swap(deps.as_mut_deps(), &test_config(), &swap_operation, sell_eth_fill(9)).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: TEST_USER_ADDR.to_string(),
+            amount: vec![coin(1809000u128, "usdt")],
+        })
+    );
+}
+
+#[test]
+fn it_rejects_swapping_along_a_stored_route_without_steps() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let admin = instantiate_with_admin(deps.as_mut_deps());
+    // set_route refuses empty routes, so it is written to storage directly
+    store_swap_route(
+        deps.as_mut_deps().storage,
+        &SwapRoute {
+            steps: vec![],
+            source_denom: "eth".to_string(),
+            target_denom: "usdt".to_string(),
+            enabled: true,
+            fee_bps: None,
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut_deps(),
+        mock_env(),
+        message_info(&admin, &[coin(2u128, "eth")]),
+        min_output_swap("usdt"),
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(&err, ContractError::EmptyRoute { source_denom, target_denom } if source_denom == "eth" && target_denom == "usdt"),
+        "wrong error: {err}"
+    );
+}
+
+#[test]
+fn it_sends_the_fee_to_the_fee_recipient_of_its_denom() {
+    let swap_operation = CurrentSwapOperation {
+        fee: coin(1u128, "eth"),
+        ..swap_operation(coin(10u128, "eth"), SwapQuantityMode::MinOutputQuantity(FPDecimal::ONE))
+    };
+
+    // only an override for the denom of the fee redirects it
+    for (override_denom, expected_recipient) in [("eth", "treasury"), ("usdt", "fee_recipient")] {
+        let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+        FEE_RECIPIENTS_BY_DENOM
+            .save(deps.as_mut_deps().storage, override_denom.to_string(), &Addr::unchecked("treasury"))
+            .unwrap();
+        let config = Config::new(Addr::unchecked("fee_recipient"), Addr::unchecked(TEST_USER_ADDR));
+
+        let messages = sent_messages(settle_swap(deps.as_mut_deps(), &config, &swap_operation, sell_eth_fill(9)).unwrap());
+
+        assert_eq!(
+            messages[1],
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: expected_recipient.to_string(),
+                amount: vec![coin(1u128, "eth")],
+            }),
+            "wrong fee recipient with an override for {override_denom}"
+        );
+    }
+}
+
+#[test]
+fn it_rejects_replies_not_answering_a_pending_swap_order() {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    instantiate_with_admin(deps.as_mut_deps());
+
+    let mut unregistered_reply = mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO);
+    unregistered_reply.id = 42;
+    let err = reply(deps.as_mut_deps(), mock_env(), unregistered_reply).unwrap_err();
+    assert!(matches!(err, ContractError::UnrecognizedReply(42)), "wrong error: {err}");
+
+    // the order reply id is only accepted while a swap step awaits its order
+    let err = reply(
+        deps.as_mut_deps(),
+        mock_env(),
+        mock_atomic_order_reply(FPDecimal::ONE, FPDecimal::from(201000u128), FPDecimal::ZERO),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::UnexpectedReply(1)), "wrong error: {err}");
+}
@@ -1,4 +1,5 @@
 use crate::{
+    contract::ATOMIC_ORDER_REPLY_ID,
     helpers::Scaled,
     msg::{ExecuteMsg, FeeRecipient, InstantiateMsg},
     types::FPCoin,
@@ -7,12 +8,14 @@ use crate::{
 use cosmwasm_std::{
     coin,
     testing::{MockApi, MockStorage},
-    to_json_binary, Addr, Coin, ContractResult, OwnedDeps, QuerierResult, SystemError, SystemResult, Uint128,
+    to_json_binary, Addr, Binary, Coin, ContractResult, MsgResponse, OwnedDeps, QuerierResult, Reply, SubMsgResponse, SubMsgResult, SystemError,
+    SystemResult, Uint128,
 };
 use injective_cosmwasm::{
     create_orderbook_response_handler, create_spot_multi_market_handler, get_default_subaccount_id_for_checked_address, inj_mock_deps,
-    test_market_ids, HandlesMarketIdQuery, InjectiveQueryWrapper, MarketId, PriceLevel, QueryMarketAtomicExecutionFeeMultiplierResponse, SpotMarket,
-    WasmMockQuerier, TEST_MARKET_ID_1, TEST_MARKET_ID_2,
+    test_market_ids, HandlesMarketIdQuery, HandlesOraclePriceQuery, InjectiveQueryWrapper, MarketId, OraclePriceResponse, OracleType, PriceLevel,
+    PricePairState, QueryMarketAtomicExecutionFeeMultiplierResponse, SpotMarket, SpotMarketResponse, WasmMockQuerier, TEST_MARKET_ID_1,
+    TEST_MARKET_ID_2, TEST_MARKET_ID_3,
 };
 use injective_math::FPDecimal;
 use injective_std::{
@@ -24,7 +27,9 @@ use injective_std::{
             bank::v1beta1::{QueryAllBalancesRequest, QueryBalanceRequest},
         },
         cosmwasm::wasm::v1::{AcceptedMessageKeysFilter, ContractExecutionAuthorization, ContractGrant, MaxCallsLimit},
-        injective::exchange::v1beta1::{MsgCreateSpotLimitOrder, OrderInfo, OrderType, SpotOrder},
+        injective::exchange::v1beta1::{
+            MsgCreateSpotLimitOrder, MsgCreateSpotMarketOrderResponse, OrderInfo, OrderType, SpotMarketOrderResults, SpotOrder,
+        },
     },
 };
 use injective_test_tube::{Account, Authz, Bank, Exchange, InjectiveTestApp, Module, SigningAccount, Wasm};
@@ -108,6 +113,82 @@ pub fn create_price_level(p: u128, q: u128) -> PriceLevel {
     }
 }
 
+// Helper function to create a successful atomic order reply, values are scaled like protobuf Dec values
+#[allow(deprecated)]
+pub fn mock_atomic_order_reply(quantity: FPDecimal, price: FPDecimal, fee: FPDecimal) -> Reply {
+    let order_response = MsgCreateSpotMarketOrderResponse {
+        results: Some(SpotMarketOrderResults {
+            quantity: quantity.scaled(18).to_string(),
+            price: price.scaled(18).to_string(),
+            fee: fee.scaled(18).to_string(),
+        }),
+        ..Default::default()
+    };
+
+    Reply {
+        id: ATOMIC_ORDER_REPLY_ID,
+        payload: Binary::default(),
+        gas_used: 0,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/injective.exchange.v1beta1.MsgCreateSpotMarketOrderResponse".to_string(),
+                value: Binary::from(order_response.encode_to_vec()),
+            }],
+        }),
+    }
+}
+
+pub fn mock_failed_atomic_order_reply() -> Reply {
+    Reply {
+        id: ATOMIC_ORDER_REPLY_ID,
+        payload: Binary::default(),
+        gas_used: 0,
+        // the chain passes on only the codespace and code of a failed submessage
+        result: SubMsgResult::Err("codespace: exchange, code: 25".to_string()),
+    }
+}
+
+pub fn create_oracle_price_handler(pair_price: FPDecimal) -> Option<Box<dyn HandlesOraclePriceQuery>> {
+    struct Temp {
+        pair_price: FPDecimal,
+    }
+
+    impl HandlesOraclePriceQuery for Temp {
+        fn handle(&self, _: &OracleType, _: &str, _: &str) -> QuerierResult {
+            let response = OraclePriceResponse {
+                price_pair_state: Some(PricePairState {
+                    pair_price: self.pair_price,
+                    base_price: self.pair_price,
+                    quote_price: FPDecimal::ONE,
+                    base_cumulative_price: FPDecimal::ZERO,
+                    quote_cumulative_price: FPDecimal::ZERO,
+                    base_timestamp: 0,
+                    quote_timestamp: 0,
+                }),
+            };
+            SystemResult::Ok(ContractResult::from(to_json_binary(&response)))
+        }
+    }
+
+    Some(Box::new(Temp { pair_price }))
+}
+
+/// Answers every spot market query like for a market that does not exist.
+pub fn create_missing_spot_market_handler() -> Option<Box<dyn HandlesMarketIdQuery>> {
+    struct Temp {}
+
+    impl HandlesMarketIdQuery for Temp {
+        fn handle(&self, _: MarketId) -> QuerierResult {
+            let response = SpotMarketResponse { market: None };
+            SystemResult::Ok(ContractResult::from(to_json_binary(&response)))
+        }
+    }
+
+    Some(Box::new(Temp {}))
+}
+
 #[derive(PartialEq)]
 pub enum MultiplierQueryBehavior {
     Success,
@@ -117,7 +198,7 @@ pub enum MultiplierQueryBehavior {
 pub fn mock_deps_eth_inj(
     multiplier_query_behavior: MultiplierQueryBehavior,
 ) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier, InjectiveQueryWrapper> {
-    inj_mock_deps(|querier| {
+    let mut deps = inj_mock_deps(|querier| {
         let mut markets = HashMap::new();
         markets.insert(
             MarketId::new(TEST_MARKET_ID_1).unwrap(),
@@ -200,13 +281,57 @@ pub fn mock_deps_eth_inj(
 
             querier.market_atomic_execution_fee_multiplier_response_handler = create_spot_ok_multiplier_handler()
         }
-    })
+    });
+    // addresses used in tests are injective addresses
+    deps.api = MockApi::default().with_prefix("inj");
+    deps
+}
+
+/// Like `mock_deps_eth_inj`, with TEST_MARKET_ID_3 as a second eth/usdt market for routes passing through the pair twice.
+pub fn mock_deps_eth_inj_with_second_eth_market() -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier, InjectiveQueryWrapper> {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let mut markets = HashMap::new();
+    markets.insert(
+        MarketId::new(TEST_MARKET_ID_1).unwrap(),
+        create_mock_spot_market("eth", FPDecimal::must_from_str("0.001"), FPDecimal::must_from_str("0.001"), 0),
+    );
+    markets.insert(
+        MarketId::new(TEST_MARKET_ID_2).unwrap(),
+        create_mock_spot_market("inj", FPDecimal::must_from_str("0.001"), FPDecimal::must_from_str("0.001"), 1),
+    );
+    markets.insert(
+        MarketId::new(TEST_MARKET_ID_3).unwrap(),
+        SpotMarket {
+            market_id: MarketId::unchecked(TEST_MARKET_ID_3),
+            ..create_mock_spot_market("eth", FPDecimal::must_from_str("0.001"), FPDecimal::must_from_str("0.001"), 0)
+        },
+    );
+    deps.querier.spot_market_response_handler = create_spot_multi_market_handler(markets);
+    deps
+}
+
+/// Like `mock_deps_eth_inj`, with eth traded in multiples of `min_quantity_tick_size`.
+pub fn mock_deps_eth_inj_with_eth_quantity_tick(
+    min_quantity_tick_size: FPDecimal,
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier, InjectiveQueryWrapper> {
+    let mut deps = mock_deps_eth_inj(MultiplierQueryBehavior::Success);
+    let mut markets = HashMap::new();
+    markets.insert(
+        MarketId::new(TEST_MARKET_ID_1).unwrap(),
+        create_mock_spot_market("eth", FPDecimal::must_from_str("0.001"), min_quantity_tick_size, 0),
+    );
+    markets.insert(
+        MarketId::new(TEST_MARKET_ID_2).unwrap(),
+        create_mock_spot_market("inj", FPDecimal::must_from_str("0.001"), FPDecimal::must_from_str("0.001"), 1),
+    );
+    deps.querier.spot_market_response_handler = create_spot_multi_market_handler(markets);
+    deps
 }
 
 pub fn mock_realistic_deps_eth_atom(
     multiplier_query_behavior: MultiplierQueryBehavior,
 ) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier, InjectiveQueryWrapper> {
-    inj_mock_deps(|querier| {
+    let mut deps = inj_mock_deps(|querier| {
         let mut markets = HashMap::new();
         markets.insert(
             MarketId::new(TEST_MARKET_ID_1).unwrap(),
@@ -294,7 +419,10 @@ pub fn mock_realistic_deps_eth_atom(
 
             querier.market_atomic_execution_fee_multiplier_response_handler = create_spot_ok_multiplier_handler()
         }
-    })
+    });
+    // addresses used in tests are injective addresses
+    deps.api = MockApi::default().with_prefix("inj");
+    deps
 }
 
 fn create_mock_spot_market(base: &str, min_price_tick_size: FPDecimal, min_quantity_tick_size: FPDecimal, idx: u32) -> SpotMarket {
@@ -626,6 +754,7 @@ pub fn init_self_relaying_contract_and_get_address(wasm: &Wasm<InjectiveTestApp>
         &InstantiateMsg {
             fee_recipient: FeeRecipient::SwapContract,
             admin: Addr::unchecked(owner.address()),
+            initial_routes: vec![],
         },
         Some(&owner.address()),
         Some("Swap"),
@@ -3,7 +3,10 @@
 
 #[cfg(test)]
 mod vulnerability_tests {
-    use cosmwasm_std::{testing::mock_dependencies, Addr, Coin};
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env},
+        Addr, Coin,
+    };
     use cw_storage_plus::Item;
 
     use crate::state::SWAP_OPERATION_STATE;
@@ -23,6 +26,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(100u128)),
             input_funds: Coin::new(10000_000000u128, "usdt"), // 10,000 USDT
             refund: Coin::new(0u128, "usdt"),
+            fee: Coin::new(0u128, "usdt"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         // Save User A's state to global storage
@@ -40,6 +54,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(50u128)),
             input_funds: Coin::new(1_000000u128, "atom"), // 1 ATOM
             refund: Coin::new(0u128, "atom"),
+            fee: Coin::new(0u128, "atom"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         // Save User B's state - overwrites User A completely
@@ -71,6 +96,17 @@ mod vulnerability_tests {
                 swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(100u128)),
                 input_funds: Coin::new(1000u128, "token"),
                 refund: Coin::new(0u128, "token"),
+                fee: Coin::new(0u128, "token"),
+                compound_refund: false,
+                memo: None,
+                started_at: mock_env().block.time,
+                recipients: vec![],
+                min_output_value: None,
+                slices: None,
+                estimated_output: None,
+                exact_output_input: None,
+                portfolio: vec![],
+                route_fee_bps: None,
             };
 
             SWAP_OPERATION_STATE.save(&mut deps.storage, &state).unwrap();
@@ -96,6 +132,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(1u128)),
             input_funds: Coin::new(1000000_000000u128, "usdt"), // 1 Million USDT
             refund: Coin::new(0u128, "usdt"),
+            fee: Coin::new(0u128, "usdt"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         SWAP_OPERATION_STATE.save(&mut deps.storage, &victim_state).unwrap();
@@ -108,6 +155,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(1u128)),
             input_funds: Coin::new(1_000000u128, "usdt"), // 1 USDT
             refund: Coin::new(0u128, "usdt"),
+            fee: Coin::new(0u128, "usdt"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         SWAP_OPERATION_STATE.save(&mut deps.storage, &attacker_state).unwrap();
@@ -162,6 +220,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(100u128)),
             input_funds: Coin::new(10000u128, "usdt"),
             refund: Coin::new(0u128, "usdt"),
+            fee: Coin::new(0u128, "usdt"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         let state_b = CurrentSwapOperation {
@@ -170,6 +239,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(50u128)),
             input_funds: Coin::new(5000u128, "atom"),
             refund: Coin::new(0u128, "atom"),
+            fee: Coin::new(0u128, "atom"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         // Both states can coexist
@@ -202,6 +282,17 @@ mod vulnerability_tests {
             swap_quantity_mode: SwapQuantityMode::MinOutputQuantity(FPDecimal::from(100u128)),
             input_funds: Coin::new(10000u128, "usdt"),
             refund: Coin::new(0u128, "usdt"),
+            fee: Coin::new(0u128, "usdt"),
+            compound_refund: false,
+            memo: None,
+            started_at: mock_env().block.time,
+            recipients: vec![],
+            min_output_value: None,
+            slices: None,
+            estimated_output: None,
+            exact_output_input: None,
+            portfolio: vec![],
+            route_fee_bps: None,
         };
 
         SWAP_OPERATION_STATE.save(&mut deps.storage, &state).unwrap();
@@ -1,6 +1,6 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin};
-use injective_cosmwasm::MarketId;
+use cosmwasm_std::{Addr, Binary, Coin, Timestamp, Uint128};
+use injective_cosmwasm::{MarketId, OracleType};
 use injective_math::FPDecimal;
 
 #[cw_serde]
@@ -36,12 +36,126 @@ pub struct ConfigResponse {
     pub contract_version: String,
 }
 
+#[cw_serde]
+pub enum PlannedMessage {
+    // atomic market order placed by the contract for one step of the swap
+    MarketOrder {
+        market_id: MarketId,
+        is_buy: bool,
+        price: FPDecimal,
+        quantity: FPDecimal,
+        input: FPCoin,
+    },
+    BankSend {
+        to_address: Addr,
+        amount: Coin,
+    },
+    // output of a CW20 denom, redeemed through its adapter
+    WasmExecute {
+        contract_addr: Addr,
+        msg: Binary,
+        funds: Vec<Coin>,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct DenomAccounting {
+    // contract fees charged in the denom
+    pub fees_collected: Uint128,
+    // swapped input volume of the denom, only filled in queries
+    #[serde(default)]
+    pub volume: Uint128,
+    // unused input given back in the denom
+    pub refunded: Uint128,
+    // completed swaps with the denom as input
+    pub completed_swaps: u64,
+}
+
+#[cw_serde]
+pub struct AccountingResponse {
+    pub denoms: Vec<(String, DenomAccounting)>,
+}
+
+#[cw_serde]
+pub struct QuoteResponse {
+    // estimated output, only set when every step of the route could be estimated
+    pub result_quantity: Option<FPDecimal>,
+    // market fees of the steps estimated so far
+    pub expected_fees: Vec<FPCoin>,
+    // why the quote is incomplete, only set when result_quantity is not
+    pub error: Option<String>,
+    // messages settling the swap, to size gas for: the output unless it is timelocked, and the contract fee unless
+    // netted into what the sender receives anyway
+    pub expected_bank_msgs: u32,
+}
+
+#[cw_serde]
+pub struct FeeBudgetResponse {
+    // largest input of a min output swap whose contract fee is within the budget
+    pub input_quantity: Uint128,
+    // contract fee charged on input_quantity
+    pub fee: Uint128,
+    // estimated output of the input left after the fee
+    pub result_quantity: FPDecimal,
+}
+
+#[cw_serde]
+pub struct SwapBoundsResponse {
+    // smallest input the first market of the route accepts, one quantity tick worth of it
+    pub min_input: FPDecimal,
+    // largest input the orderbook of the first market can fill
+    pub max_input: FPDecimal,
+}
+
+#[cw_serde]
+pub struct LimitsResponse {
+    pub paused: bool,
+    pub swap_fee_bps: u16,
+    pub min_fee: Uint128,
+    pub max_fee: Option<Uint128>,
+    pub max_allowed_slippage_bps: Option<u16>,
+    // largest input of each denom swapped directly, larger ones have to be committed first
+    pub max_inputs: Vec<Coin>,
+    pub max_swap_slices: u8,
+    pub max_memo_length: usize,
+}
+
+#[cw_serde]
+pub struct SwappableResponse {
+    pub swappable: bool,
+    // why the pair can't be swapped, only set when not swappable
+    pub reason: Option<String>,
+}
+
+impl SwappableResponse {
+    pub fn swappable() -> Self {
+        SwappableResponse {
+            swappable: true,
+            reason: None,
+        }
+    }
+
+    pub fn not_swappable(reason: impl Into<String>) -> Self {
+        SwappableResponse {
+            swappable: false,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
 #[cw_serde]
 pub enum SwapQuantityMode {
     MinOutputQuantity(FPDecimal),
     ExactOutputQuantity(FPDecimal),
 }
 
+#[cw_serde]
+pub enum SwapMode {
+    MinOutput,
+    ExactOutput,
+}
+
 #[cw_serde]
 pub struct StepExecutionEstimate {
     pub worst_price: FPDecimal,
@@ -59,6 +173,91 @@ pub struct CurrentSwapOperation {
     pub swap_quantity_mode: SwapQuantityMode,
     pub input_funds: Coin,
     pub refund: Coin,
+    pub fee: Coin,
+    // if set, a refund above the dust threshold is swapped instead of given back
+    #[serde(default)]
+    pub compound_refund: bool,
+    // opaque tag of the sender, only emitted in events
+    #[serde(default)]
+    pub memo: Option<String>,
+    // block time the swap was started at, to tell swaps stuck in flight
+    #[serde(default)]
+    pub started_at: Timestamp,
+    // if set, the output is split across these addresses by bps weight instead of sent to the sender
+    #[serde(default)]
+    pub recipients: Vec<(Addr, u16)>,
+    // if set, the swap is aborted when its output is worth less than this at the oracle price
+    #[serde(default)]
+    pub min_output_value: Option<MinOutputValue>,
+    // if set, the input is swapped in slices one after another and the output is settled after the last one
+    #[serde(default)]
+    pub slices: Option<SwapSlices>,
+    // output the min output swap was estimated to deliver when started, only kept to share a better execution
+    #[serde(default)]
+    pub estimated_output: Option<FPDecimal>,
+    // how the input of an exact output swap was estimated, emitted on settlement to audit its refund
+    #[serde(default)]
+    pub exact_output_input: Option<ExactOutputInput>,
+    // parts of a portfolio swap still to be swapped once this one settled
+    #[serde(default)]
+    pub portfolio: Vec<PortfolioLeg>,
+    // fee of the route the swap was started along, charged instead of the global one
+    #[serde(default)]
+    pub route_fee_bps: Option<u16>,
+}
+
+#[cw_serde]
+pub struct PortfolioTarget {
+    pub denom: String,
+    // share of the input swapped into the denom
+    pub weight_bps: u16,
+    // the part swapped into the denom has to deliver at least this, or the whole portfolio swap is aborted
+    pub min_output_quantity: FPDecimal,
+}
+
+#[cw_serde]
+pub struct PortfolioLeg {
+    pub target_denom: String,
+    pub input: Coin,
+    pub min_output_quantity: FPDecimal,
+}
+
+#[cw_serde]
+pub struct ExactOutputInput {
+    // input the route was estimated to take for the requested output
+    pub estimated: FPDecimal,
+    // estimated input rounded to what the first market accepts, the first order is placed with it
+    pub required: FPDecimal,
+}
+
+#[cw_serde]
+pub struct SwapSlices {
+    // inputs of the slices still to be swapped
+    pub remaining: Vec<Uint128>,
+    // output of the slices swapped so far
+    pub output: FPCoin,
+}
+
+#[cw_serde]
+pub struct MinOutputValue {
+    // least worth of the output, in the quote of the oracle pair
+    pub value: FPDecimal,
+    // oracle pair pricing the output denom
+    pub oracle_type: OracleType,
+    pub base: String,
+    pub quote: String,
+}
+
+#[cw_serde]
+pub struct LargeSwapCommit {
+    pub target_denom: String,
+    pub swap_quantity_mode: SwapQuantityMode,
+    pub funds: Vec<Coin>,
+    // block the swap was committed in
+    pub committed_at_height: u64,
+    // lets the swap go through a price impact above the configured threshold
+    #[serde(default)]
+    pub acknowledge_high_impact: bool,
 }
 
 #[cw_serde]
@@ -68,6 +267,38 @@ pub struct CurrentSwapStep {
     pub current_balance: FPCoin,
     pub step_target_denom: String,
     pub is_buy: bool,
+    // times the order of this step was placed again after failing
+    #[serde(default)]
+    pub retry_count: u8,
+}
+
+#[cw_serde]
+pub struct DebugStateResponse {
+    pub operation: Option<CurrentSwapOperation>,
+    pub step: Option<CurrentSwapStep>,
+    pub results: Option<Vec<SwapResults>>,
+}
+
+/// Fill of an atomic market order, decoded from its reply and without the protobuf scale factor.
+#[cw_serde]
+pub struct OrderFill {
+    // filled base quantity
+    pub quantity: FPDecimal,
+    // average price of the fill
+    pub price: FPDecimal,
+    // trading fee paid in the quote denom
+    pub fee: FPDecimal,
+}
+
+impl OrderFill {
+    /// Amount the order delivers: the base quantity for buys, the quote proceeds net of the fee for sells.
+    pub fn output_quantity(&self, is_buy: bool) -> FPDecimal {
+        if is_buy {
+            self.quantity
+        } else {
+            self.quantity * self.price - self.fee
+        }
+    }
 }
 
 #[cw_serde]
@@ -76,6 +307,11 @@ pub struct SwapResults {
     pub quantity: FPDecimal,
     pub price: FPDecimal,
     pub fee: FPDecimal,
+    // block in which the step was executed
+    #[serde(default)]
+    pub block_height: u64,
+    #[serde(default)]
+    pub timestamp: Timestamp,
 }
 
 #[cw_serde]
@@ -84,6 +320,145 @@ pub struct Config {
     pub fee_recipient: Addr,
     // who can change routes
     pub admin: Addr,
+    // if set, no new swaps can be started
+    #[serde(default)]
+    pub paused: bool,
+    // contract fee charged on the swapped input and sent to fee_recipient
+    #[serde(default)]
+    pub swap_fee_bps: u16,
+    // if set, pairs without a registered route can be swapped through a known market trading them directly
+    #[serde(default)]
+    pub allow_direct_fallback: bool,
+    // if set, swaps must attach exactly one denom, otherwise denoms not being swapped are given back
+    #[serde(default = "default_strict_funds")]
+    pub strict_funds: bool,
+    // if set, min output swaps may not accept more slippage from the estimated output than this
+    #[serde(default)]
+    pub max_allowed_slippage_bps: Option<u16>,
+    // if set, the contract fee of min output swaps is taken from the output instead of the input
+    #[serde(default)]
+    pub fee_in_output_denom: bool,
+    // refunds of swaps with compound_refund are only swapped when above this amount
+    #[serde(default)]
+    pub refund_dust_threshold: Uint128,
+    // what to do when the swap output is below one unit of the target denom
+    #[serde(default)]
+    pub dust_policy: DustPolicy,
+    // absolute bounds of the contract fee, in units of the denom it is charged in; a min_fee of 1 keeps
+    // tiny swaps from rounding their fee down to zero
+    #[serde(default)]
+    pub min_fee: Uint128,
+    #[serde(default)]
+    pub max_fee: Option<Uint128>,
+    // what to do with the output an exact output swap receives above the requested quantity
+    #[serde(default)]
+    pub over_delivery_policy: OverDeliveryPolicy,
+    // if set, swaps of the admin are not charged the contract fee
+    #[serde(default)]
+    pub admin_fee_exempt: bool,
+    // if not zero, a failed step is placed once more with its worst price widened by this much
+    #[serde(default)]
+    pub hop_retry_price_band_bps: u16,
+    // share of the contract fee sent to burn_address instead of fee_recipient
+    #[serde(default)]
+    pub fee_burn_bps: u16,
+    // unspendable address receiving the burned share of the fee, nothing is burned while unset
+    #[serde(default)]
+    pub burn_address: Option<Addr>,
+    // if set, swaps may pass their route inline instead of using a registered one
+    #[serde(default)]
+    pub allow_custom_routes: bool,
+    // swaps above the large swap threshold of their input denom are executed this many blocks after being committed
+    #[serde(default)]
+    pub large_swap_delay_blocks: u64,
+    // if set, exact outputs that aren't a multiple of the quantity tick are rejected instead of rounded up to it
+    #[serde(default)]
+    pub reject_unaligned_exact_output: bool,
+    // exact output swaps delivering less than requested by at most this much are settled instead of failing
+    #[serde(default)]
+    pub exact_output_tolerance_bps: u16,
+    // refunds below this amount are not sent back but stay with the contract, a bank send of them would cost more than they are worth
+    #[serde(default)]
+    pub min_refund_amount: Uint128,
+    // share of the output above the estimate of a min output swap the protocol keeps, the rest goes to the sender
+    #[serde(default)]
+    pub positive_slippage_bps: u16,
+    // swaps with a price impact above this have to acknowledge it, 0 disables the check
+    #[serde(default)]
+    pub high_impact_threshold_bps: u16,
+    // reject exact output quantities below the quantity tick instead of rounding them up to a whole tick
+    #[serde(default)]
+    pub reject_sub_tick_amounts: bool,
+    // outputs are held back this long and have to be claimed with ClaimOutput, 0 sends them right away
+    #[serde(default)]
+    pub output_timelock_seconds: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum DustPolicy {
+    // fail the swap, so that the sender keeps the input
+    #[default]
+    Abort,
+    // settle the swap without sending the output, any refund is still given back
+    DeliverZero,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum OverDeliveryPolicy {
+    // send the whole output to the sender
+    #[default]
+    ReturnToSender,
+    // send the requested quantity only, the excess stays with the contract
+    KeepAsRevenue,
+}
+
+fn default_strict_funds() -> bool {
+    true
+}
+
+fn default_route_enabled() -> bool {
+    true
+}
+
+impl Config {
+    pub fn new(fee_recipient: Addr, admin: Addr) -> Self {
+        Config {
+            fee_recipient,
+            admin,
+            paused: false,
+            swap_fee_bps: 0,
+            allow_direct_fallback: false,
+            strict_funds: default_strict_funds(),
+            max_allowed_slippage_bps: None,
+            fee_in_output_denom: false,
+            refund_dust_threshold: Uint128::zero(),
+            dust_policy: DustPolicy::default(),
+            min_fee: Uint128::zero(),
+            max_fee: None,
+            over_delivery_policy: OverDeliveryPolicy::default(),
+            admin_fee_exempt: false,
+            hop_retry_price_band_bps: 0,
+            fee_burn_bps: 0,
+            burn_address: None,
+            allow_custom_routes: false,
+            large_swap_delay_blocks: 0,
+            reject_unaligned_exact_output: false,
+            exact_output_tolerance_bps: 0,
+            min_refund_amount: Uint128::zero(),
+            positive_slippage_bps: 0,
+            high_impact_threshold_bps: 0,
+            reject_sub_tick_amounts: false,
+            output_timelock_seconds: 0,
+        }
+    }
+}
+
+/// Execute message of the CW20 adapter, which burns the bank denom it minted for a CW20 token and transfers the token.
+#[cw_serde]
+pub enum Cw20AdapterMsg {
+    RedeemAndTransfer { recipient: Option<String> },
 }
 
 #[cw_serde]
@@ -91,6 +466,12 @@ pub struct SwapRoute {
     pub steps: Vec<MarketId>,
     pub source_denom: String,
     pub target_denom: String,
+    // disabled routes are kept but can't be swapped along
+    #[serde(default = "default_route_enabled")]
+    pub enabled: bool,
+    // overrides the global swap_fee_bps for swaps along this route
+    #[serde(default)]
+    pub fee_bps: Option<u16>,
 }
 
 impl SwapRoute {
@@ -105,6 +486,17 @@ impl SwapRoute {
     }
 }
 
+#[cw_serde]
+pub struct SwapRouteResponse {
+    pub steps: Vec<MarketId>,
+    pub source_denom: String,
+    pub target_denom: String,
+    pub enabled: bool,
+    pub fee_bps: Option<u16>,
+    // denoms the route passes through in order, from source_denom to target_denom, empty if its markets can't be followed
+    pub denoms: Vec<String>,
+}
+
 #[cw_serde]
 pub struct SwapStep {
     pub market_id: MarketId,